@@ -0,0 +1,39 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- Links refund/reimbursement expense to the expense(s) it pays back. Amount is the part of the
+-- reimbursement applied to given expense, in the same sign as the expense it offsets.
+CREATE TABLE reimbursements (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  reimbursement_id INTEGER NOT NULL,
+  expense_id INTEGER NOT NULL,
+  amount INTEGER NOT NULL,
+  UNIQUE(reimbursement_id, expense_id),
+  FOREIGN KEY(reimbursement_id) REFERENCES expenses(id) ON DELETE CASCADE,
+  FOREIGN KEY(expense_id) REFERENCES expenses(id) ON DELETE CASCADE
+);
+
+CREATE TABLE pending_reimbursements (
+  expense_id INTEGER PRIMARY KEY NOT NULL,
+  amount INTEGER NOT NULL,
+  FOREIGN KEY(expense_id) REFERENCES expenses(id) ON DELETE CASCADE
+);
+
+-- Expenses as they should count against budget: linked reimbursements are removed, and instead
+-- their amounts are subtracted from expenses they pay back, on the original expense date.
+CREATE VIEW view_expense_spend AS
+SELECT
+  expenses.id AS expense_id,
+  expenses.budget_item_id,
+  expenses.transaction_date,
+  expenses.amount
+FROM expenses
+WHERE expenses.id NOT IN (SELECT reimbursement_id FROM reimbursements)
+UNION ALL
+SELECT
+  expenses.id AS expense_id,
+  expenses.budget_item_id,
+  expenses.transaction_date,
+  -reimbursements.amount AS amount
+FROM reimbursements
+JOIN expenses ON (reimbursements.expense_id = expenses.id);
@@ -0,0 +1,29 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- Full-text index over expense description and notes, kept in sync with expenses by triggers.
+CREATE VIRTUAL TABLE expenses_fts USING fts5(
+  description,
+  notes,
+  content='expenses',
+  content_rowid='id'
+);
+
+CREATE TRIGGER expenses_fts_insert AFTER INSERT ON expenses BEGIN
+  INSERT INTO expenses_fts (rowid, description, notes)
+  VALUES (new.id, new.description, new.notes);
+END;
+
+CREATE TRIGGER expenses_fts_delete AFTER DELETE ON expenses BEGIN
+  INSERT INTO expenses_fts (expenses_fts, rowid, description, notes)
+  VALUES ('delete', old.id, old.description, old.notes);
+END;
+
+CREATE TRIGGER expenses_fts_update AFTER UPDATE OF description, notes ON expenses BEGIN
+  INSERT INTO expenses_fts (expenses_fts, rowid, description, notes)
+  VALUES ('delete', old.id, old.description, old.notes);
+  INSERT INTO expenses_fts (rowid, description, notes)
+  VALUES (new.id, new.description, new.notes);
+END;
+
+INSERT INTO expenses_fts (expenses_fts) VALUES ('rebuild');
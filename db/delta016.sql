@@ -0,0 +1,15 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+CREATE TABLE tags (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE expense_tags (
+  expense_id INTEGER NOT NULL,
+  tag_id INTEGER NOT NULL,
+  PRIMARY KEY(expense_id, tag_id),
+  FOREIGN KEY(expense_id) REFERENCES expenses(id) ON DELETE CASCADE,
+  FOREIGN KEY(tag_id) REFERENCES tags(id)
+);
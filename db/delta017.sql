@@ -0,0 +1,18 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- Files are stored on disk under a path derived from sha256 of the content, so the same file
+-- attached to several expenses is stored once.
+CREATE TABLE attachments (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  expense_id INTEGER NOT NULL,
+  filename TEXT NOT NULL,
+  content_type TEXT NOT NULL,
+  size INTEGER NOT NULL,
+  sha256 TEXT NOT NULL,
+  uploaded_at TEXT NOT NULL,
+  FOREIGN KEY(expense_id) REFERENCES expenses(id) ON DELETE CASCADE
+);
+
+CREATE INDEX attachments_expense_id ON attachments(expense_id);
+CREATE INDEX attachments_sha256 ON attachments(sha256);
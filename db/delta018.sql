@@ -0,0 +1,52 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- Values of an expense as it was imported or created, saved before its first edit. Import
+-- deduplication compares new statement rows against these, not against the edited values.
+CREATE TABLE expense_originals (
+  expense_id INTEGER PRIMARY KEY,
+  transaction_date TEXT NOT NULL,
+  transaction_time TEXT,
+  description TEXT NOT NULL,
+  amount INTEGER NOT NULL,
+  FOREIGN KEY(expense_id) REFERENCES expenses(id) ON DELETE CASCADE
+);
+
+-- One row per edit, with values from before and after the edit. write_log_id points at the
+-- request which made the edit, for who and when.
+CREATE TABLE expense_changes (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  expense_id INTEGER NOT NULL,
+  write_log_id INTEGER NOT NULL,
+  old_transaction_date TEXT NOT NULL,
+  old_transaction_time TEXT,
+  old_description TEXT NOT NULL,
+  old_amount INTEGER NOT NULL,
+  new_transaction_date TEXT NOT NULL,
+  new_transaction_time TEXT,
+  new_description TEXT NOT NULL,
+  new_amount INTEGER NOT NULL,
+  FOREIGN KEY(expense_id) REFERENCES expenses(id) ON DELETE CASCADE,
+  FOREIGN KEY(write_log_id) REFERENCES write_log(id)
+);
+
+CREATE INDEX expense_changes_expense_id ON expense_changes(expense_id);
+
+-- Expenses with edited core fields replaced by their original values
+CREATE VIEW view_original_expenses AS
+  SELECT
+    expenses.id,
+    expenses.account_id,
+    COALESCE(originals.transaction_date, expenses.transaction_date) AS transaction_date,
+    CASE WHEN originals.expense_id IS NULL
+      THEN expenses.transaction_time
+      ELSE originals.transaction_time
+    END AS transaction_time,
+    COALESCE(originals.description, expenses.description) AS description,
+    COALESCE(originals.amount, expenses.amount) AS amount,
+    expenses.raw_csv,
+    expenses.budget_item_id,
+    expenses.notes
+  FROM expenses
+  LEFT JOIN expense_originals originals
+    ON (expenses.id = originals.expense_id);
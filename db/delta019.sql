@@ -0,0 +1,16 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- Expenses expected in the future, e.g. annual insurance or property tax. Once the matching
+-- transaction is imported, expense_id links to it and the planned expense no longer counts as
+-- committed spend.
+CREATE TABLE planned_expenses (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  budget_item_id INTEGER NOT NULL,
+  expected_date TEXT NOT NULL,
+  amount INTEGER NOT NULL,
+  description TEXT NOT NULL,
+  expense_id INTEGER UNIQUE,
+  FOREIGN KEY(budget_item_id) REFERENCES budget_items(id),
+  FOREIGN KEY(expense_id) REFERENCES expenses(id) ON DELETE SET NULL
+);
@@ -0,0 +1,20 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- Balance of the account before its first expense, or before opening_date if set; expenses from
+-- before opening_date don't count towards the balance.
+ALTER TABLE accounts ADD COLUMN opening_balance INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE accounts ADD COLUMN opening_date TEXT;
+
+-- Statement closing balances which matched the balance computed from expenses. Expenses up to
+-- the latest statement_date of an account can no longer be imported, added or deleted.
+CREATE TABLE reconciliations (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  account_id INTEGER NOT NULL,
+  statement_date TEXT NOT NULL,
+  statement_balance INTEGER NOT NULL,
+  write_log_id INTEGER NOT NULL,
+  UNIQUE(account_id, statement_date),
+  FOREIGN KEY(account_id) REFERENCES accounts(id),
+  FOREIGN KEY(write_log_id) REFERENCES write_log(id)
+);
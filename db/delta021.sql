@@ -0,0 +1,28 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- Running balance of the account after the expense, as printed on the imported statement. NULL
+-- when statement schema has no balance column, or the expense was added by hand.
+ALTER TABLE expenses ADD COLUMN balance INTEGER;
+
+DROP VIEW view_original_expenses;
+
+-- Expenses with edited core fields replaced by their original values
+CREATE VIEW view_original_expenses AS
+  SELECT
+    expenses.id,
+    expenses.account_id,
+    COALESCE(originals.transaction_date, expenses.transaction_date) AS transaction_date,
+    CASE WHEN originals.expense_id IS NULL
+      THEN expenses.transaction_time
+      ELSE originals.transaction_time
+    END AS transaction_time,
+    COALESCE(originals.description, expenses.description) AS description,
+    COALESCE(originals.amount, expenses.amount) AS amount,
+    expenses.raw_csv,
+    expenses.balance,
+    expenses.budget_item_id,
+    expenses.notes
+  FROM expenses
+  LEFT JOIN expense_originals originals
+    ON (expenses.id = originals.expense_id);
@@ -0,0 +1,41 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- ISO 4217 code; expenses of accounts in other than home currency (USD) are converted on import
+ALTER TABLE accounts ADD COLUMN currency TEXT NOT NULL DEFAULT 'USD';
+
+-- Amount as on the statement, before conversion to home currency; NULL for home currency
+ALTER TABLE expenses ADD COLUMN original_currency TEXT;
+ALTER TABLE expenses ADD COLUMN original_amount INTEGER;
+
+-- Units of home currency for one unit of currency, as of date
+CREATE TABLE exchange_rates (
+  currency TEXT NOT NULL,
+  date TEXT NOT NULL,
+  rate REAL NOT NULL,
+  PRIMARY KEY(currency, date)
+);
+
+DROP VIEW view_original_expenses;
+
+-- Expenses with edited core fields replaced by their original values
+CREATE VIEW view_original_expenses AS
+  SELECT
+    expenses.id,
+    expenses.account_id,
+    COALESCE(originals.transaction_date, expenses.transaction_date) AS transaction_date,
+    CASE WHEN originals.expense_id IS NULL
+      THEN expenses.transaction_time
+      ELSE originals.transaction_time
+    END AS transaction_time,
+    COALESCE(originals.description, expenses.description) AS description,
+    COALESCE(originals.amount, expenses.amount) AS amount,
+    expenses.raw_csv,
+    expenses.balance,
+    expenses.original_currency,
+    expenses.original_amount,
+    expenses.budget_item_id,
+    expenses.notes
+  FROM expenses
+  LEFT JOIN expense_originals originals
+    ON (expenses.id = originals.expense_id);
@@ -0,0 +1,11 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- Closed accounts are kept for their history, but hidden and can't get new expenses; NULL when open
+ALTER TABLE accounts ADD COLUMN closed_date TEXT;
+
+ALTER TABLE accounts ADD COLUMN institution TEXT;
+-- Last 4 digits of card or account number, to tell similar accounts apart
+ALTER TABLE accounts ADD COLUMN last_four TEXT;
+ALTER TABLE accounts ADD COLUMN notes TEXT;
+ALTER TABLE accounts ADD COLUMN owner TEXT REFERENCES credentials(username) ON DELETE SET NULL;
@@ -0,0 +1,78 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+CREATE TABLE households (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  name TEXT NOT NULL UNIQUE
+);
+
+-- role is JSON string of HouseholdRole, e.g. '"Owner"'
+CREATE TABLE household_members (
+  household_id INTEGER NOT NULL,
+  username TEXT NOT NULL,
+  role TEXT NOT NULL,
+  PRIMARY KEY(household_id, username),
+  FOREIGN KEY(household_id) REFERENCES households(id) ON DELETE CASCADE,
+  FOREIGN KEY(username) REFERENCES credentials(username) ON DELETE CASCADE
+);
+
+-- Existing data was shared by everyone, so it all goes to one household owned by all users
+INSERT INTO households (id, name) VALUES (1, 'Home');
+INSERT INTO household_members (household_id, username, role)
+SELECT 1, username, '"Owner"' FROM credentials;
+
+ALTER TABLE accounts ADD COLUMN household_id INTEGER REFERENCES households(id);
+UPDATE accounts SET household_id = 1;
+
+-- Names only need to be unique within household, so both tables are rebuilt
+DROP VIEW view_budget_items;
+
+CREATE TABLE budget_categories_household (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  household_id INTEGER NOT NULL,
+  name TEXT NOT NULL,
+  ignored BOOLEAN NOT NULL,
+  year INTEGER NOT NULL,
+  UNIQUE(household_id, name, year),
+  FOREIGN KEY(household_id) REFERENCES households(id)
+);
+
+INSERT INTO budget_categories_household (id, household_id, name, ignored, year)
+SELECT id, 1, name, ignored, year FROM budget_categories;
+
+DROP TABLE budget_categories;
+ALTER TABLE budget_categories_household RENAME TO budget_categories;
+
+CREATE TABLE funds_household (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  household_id INTEGER NOT NULL,
+  name TEXT NOT NULL,
+  UNIQUE(household_id, name),
+  FOREIGN KEY(household_id) REFERENCES households(id)
+);
+
+INSERT INTO funds_household (id, household_id, name) SELECT id, 1, name FROM funds;
+
+DROP TABLE funds;
+ALTER TABLE funds_household RENAME TO funds;
+
+CREATE VIEW view_budget_items AS
+SELECT
+  budget_items.*,
+  budget_categories.household_id,
+  budget_categories.year,
+  budget_categories.ignored,
+  budget_categories.name || ' :: ' || budget_items.name AS display_name
+FROM budget_items
+JOIN budget_categories ON (budget_items.category_id = budget_categories.id);
+
+-- Members of account's household get their household role, and owner of account can edit it even
+-- without being a member; a user can have more than one row for the same account
+CREATE VIEW view_account_access AS
+SELECT accounts.id AS account_id, household_members.username, household_members.role
+FROM accounts
+JOIN household_members ON (accounts.household_id = household_members.household_id)
+UNION ALL
+SELECT id AS account_id, owner AS username, '"Editor"' AS role
+FROM accounts
+WHERE owner IS NOT NULL;
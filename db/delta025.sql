@@ -0,0 +1,6 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- New users can edit, but not run admin operations; existing users could do anything so far
+ALTER TABLE credentials ADD COLUMN role TEXT NOT NULL DEFAULT '"Editor"';
+UPDATE credentials SET role = '"Admin"';
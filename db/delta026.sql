@@ -0,0 +1,14 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- Only hash of the session token is stored, the token itself lives in private cookie
+CREATE TABLE sessions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  token_hash TEXT NOT NULL UNIQUE,
+  username TEXT NOT NULL REFERENCES credentials(username) ON DELETE CASCADE,
+  created_ts INTEGER NOT NULL,
+  last_seen_ts INTEGER NOT NULL,
+  user_agent TEXT,
+  ip TEXT
+);
+CREATE INDEX sessions_username ON sessions(username);
@@ -0,0 +1,16 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- TOTP is enabled when secret is set; last step prevents reusing the same code twice
+ALTER TABLE credentials ADD COLUMN totp_secret TEXT;
+ALTER TABLE credentials ADD COLUMN totp_last_step INTEGER;
+ALTER TABLE credentials ADD COLUMN totp_failures INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE credentials ADD COLUMN totp_failed_ts INTEGER;
+
+CREATE TABLE recovery_codes (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  username TEXT NOT NULL REFERENCES credentials(username) ON DELETE CASCADE,
+  code_hash TEXT NOT NULL,
+  used_ts INTEGER
+);
+CREATE INDEX recovery_codes_username ON recovery_codes(username);
@@ -0,0 +1,14 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- No foreign key on username, failed logins for unknown and deleted users are kept too
+CREATE TABLE auth_log (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  username TEXT NOT NULL,
+  ip TEXT,
+  user_agent TEXT,
+  event TEXT NOT NULL,
+  ts INTEGER NOT NULL
+);
+CREATE INDEX auth_log_username_ts ON auth_log(username, ts);
+CREATE INDEX auth_log_ip_ts ON auth_log(ip, ts);
@@ -0,0 +1,18 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- Like sessions, only hash of the token is stored
+CREATE TABLE api_tokens (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  username TEXT NOT NULL REFERENCES credentials(username) ON DELETE CASCADE,
+  name TEXT NOT NULL,
+  token_hash TEXT NOT NULL UNIQUE,
+  scopes TEXT NOT NULL,
+  created_ts INTEGER NOT NULL,
+  expires_ts INTEGER NOT NULL,
+  last_used_ts INTEGER
+);
+CREATE INDEX api_tokens_username ON api_tokens(username);
+
+-- No foreign key, log outlives revoked tokens
+ALTER TABLE write_log ADD COLUMN api_token_id INTEGER;
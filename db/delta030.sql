@@ -0,0 +1,16 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- Entities each logged request refers to, parsed from its uri and content when it's logged. No
+-- foreign key on entity_id, history of deleted entities is kept too.
+CREATE TABLE write_log_entities (
+  write_log_id INTEGER NOT NULL,
+  entity TEXT NOT NULL,
+  entity_id INTEGER NOT NULL,
+  PRIMARY KEY (write_log_id, entity, entity_id),
+  FOREIGN KEY(write_log_id) REFERENCES write_log(id)
+);
+CREATE INDEX write_log_entities_entity ON write_log_entities(entity, entity_id);
+
+CREATE INDEX write_log_username ON write_log(username);
+CREATE INDEX write_log_start_ts ON write_log(start_ts);
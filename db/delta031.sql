@@ -0,0 +1,15 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- Editable fields of a row from before and after a logged update, as JSON, for undo. No foreign
+-- key on entity_id, kind tells which table it's from.
+CREATE TABLE write_log_snapshots (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  write_log_id INTEGER NOT NULL,
+  kind TEXT NOT NULL,
+  entity_id INTEGER NOT NULL,
+  before_image TEXT NOT NULL,
+  after_image TEXT NOT NULL,
+  FOREIGN KEY(write_log_id) REFERENCES write_log(id)
+);
+CREATE INDEX write_log_snapshots_write_log_id ON write_log_snapshots(write_log_id);
@@ -0,0 +1,49 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- Tags belong to a household like funds do, names only need to be unique within it
+CREATE TABLE tags_household (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  household_id INTEGER NOT NULL,
+  name TEXT NOT NULL,
+  UNIQUE(household_id, name),
+  FOREIGN KEY(household_id) REFERENCES households(id)
+);
+
+-- Tag keeps its id in the first household using it, unused tags go to the default one
+INSERT INTO tags_household (id, household_id, name)
+SELECT
+  tags.id,
+  COALESCE((
+    SELECT MIN(accounts.household_id)
+    FROM expense_tags
+    JOIN expenses ON (expense_tags.expense_id = expenses.id)
+    JOIN accounts ON (expenses.account_id = accounts.id)
+    WHERE expense_tags.tag_id = tags.id
+  ), 1),
+  tags.name
+FROM tags;
+
+-- Other households using the tag get their own copy, and their expenses are moved to it
+INSERT OR IGNORE INTO tags_household (household_id, name)
+SELECT DISTINCT accounts.household_id, tags.name
+FROM expense_tags
+JOIN tags ON (expense_tags.tag_id = tags.id)
+JOIN expenses ON (expense_tags.expense_id = expenses.id)
+JOIN accounts ON (expenses.account_id = accounts.id);
+
+UPDATE expense_tags SET tag_id = (
+  SELECT tags_household.id
+  FROM tags_household
+  JOIN tags ON (tags.name = tags_household.name)
+  WHERE tags.id = expense_tags.tag_id
+    AND tags_household.household_id = (
+      SELECT accounts.household_id
+      FROM expenses
+      JOIN accounts ON (expenses.account_id = accounts.id)
+      WHERE expenses.id = expense_tags.expense_id
+    )
+);
+
+DROP TABLE tags;
+ALTER TABLE tags_household RENAME TO tags;
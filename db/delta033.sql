@@ -0,0 +1,31 @@
+.bail on
+PRAGMA foreign_key = 1;
+
+-- Statement amount from before the first edit, so that edited expenses in other currency still
+-- match their statement row on import. NULL for home currency, and for edits made before this.
+ALTER TABLE expense_originals ADD COLUMN original_currency TEXT;
+ALTER TABLE expense_originals ADD COLUMN original_amount INTEGER;
+
+DROP VIEW view_original_expenses;
+
+-- Expenses with edited core fields replaced by their original values
+CREATE VIEW view_original_expenses AS
+  SELECT
+    expenses.id,
+    expenses.account_id,
+    COALESCE(originals.transaction_date, expenses.transaction_date) AS transaction_date,
+    CASE WHEN originals.expense_id IS NULL
+      THEN expenses.transaction_time
+      ELSE originals.transaction_time
+    END AS transaction_time,
+    COALESCE(originals.description, expenses.description) AS description,
+    COALESCE(originals.amount, expenses.amount) AS amount,
+    expenses.raw_csv,
+    expenses.balance,
+    COALESCE(originals.original_currency, expenses.original_currency) AS original_currency,
+    COALESCE(originals.original_amount, expenses.original_amount) AS original_amount,
+    expenses.budget_item_id,
+    expenses.notes
+  FROM expenses
+  LEFT JOIN expense_originals originals
+    ON (expenses.id = originals.expense_id);
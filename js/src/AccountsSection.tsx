@@ -29,6 +29,8 @@ const ACCOUNT_TYPE_OPTIONS: Array<AccountType> = [
   "Shop",
 ];
 
+const HOME_CURRENCY = "USD";
+
 function createAccountRequest(fields: AccountFields): Request {
   return new Request("/api/accounts", {
     method: "POST",
@@ -104,6 +106,7 @@ function AccountForm({
         name: formHelper.getString("name"),
         account_type: formHelper.getString("accountType") as AccountType,
         statement_schema_id: formHelper.getNumberOrNull("importConfig"),
+        currency: formHelper.getString("currency"),
       } as AccountFields;
 
       const request =
@@ -119,6 +122,7 @@ function AccountForm({
   const accountName = account?.name;
   const accountType = account?.account_type ?? ACCOUNT_TYPE_OPTIONS[0];
   const importConfig = account?.statement_schema_id ?? FormHelper.EMPTY;
+  const currency = account?.currency ?? HOME_CURRENCY;
 
   return (
     <>
@@ -139,6 +143,13 @@ function AccountForm({
           <AccountTypeOptions />
         </LabeledSelect>
 
+        <LabeledInput
+          label="Currency"
+          type="text"
+          name="currency"
+          defaultValue={currency}
+        />
+
         <LabeledSelect
           label="ImportSchema"
           name="importConfig"
@@ -189,6 +200,11 @@ function AccountsTable({
         </td>
         <td>{account.account_type}</td>
         {schema ? <td>{schema}</td> : <td className="soft">—</td>}
+        {account.closed_date ? (
+          <td>{account.closed_date}</td>
+        ) : (
+          <td className="soft">—</td>
+        )}
       </tr>
     );
   });
@@ -200,6 +216,7 @@ function AccountsTable({
           <th>Name</th>
           <th>Type</th>
           <th>Schema</th>
+          <th>Closed</th>
         </tr>
       </thead>
       <tbody>{rows}</tbody>
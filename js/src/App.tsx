@@ -126,9 +126,11 @@ function App({
     fetchBudget();
   }, [year]);
 
+  // expenses of closed accounts still need their account
   const fetchAccounts = () =>
-    fetchHelper.fetch(new Request("/api/accounts"), (json) =>
-      setAccounts(json as Accounts),
+    fetchHelper.fetch(
+      new Request("/api/accounts?include_closed=true"),
+      (json) => setAccounts(json as Accounts),
     );
 
   const fetchSchemas = () =>
@@ -88,7 +88,7 @@ export class FetchHelper {
       // catch block will handle unexpected not-json responses
       const json = await response.json();
       if (!response.ok) {
-        this.setErrorMessage(json.error ?? DEFAULT_ERROR);
+        this.setErrorMessage(json.message ?? DEFAULT_ERROR);
         return;
       }
 
@@ -3,6 +3,7 @@ import { useState } from "react";
 
 export function LoginPage({ setUser }: { setUser: (string) => void }) {
   const [loginFailed, setLoginFailed] = useState<boolean>(false);
+  const [totpRequired, setTotpRequired] = useState<boolean>(false);
 
   const onSubmit = async (e: React.SyntheticEvent) => {
     e.preventDefault();
@@ -10,7 +11,8 @@ export function LoginPage({ setUser }: { setUser: (string) => void }) {
     try {
       const form = e.target as HTMLFormElement;
       const formData = new FormData(form);
-      const response = await fetch("/api/login", {
+      const url = totpRequired ? "/api/login/totp" : "/api/login";
+      const response = await fetch(url, {
         method: "POST",
         body: formData,
       });
@@ -19,15 +21,36 @@ export function LoginPage({ setUser }: { setUser: (string) => void }) {
       }
 
       const result = await response.json();
-      const user = result as { username: string };
-
       setLoginFailed(false);
+
+      // second step, password was correct
+      if (result.totp_required) {
+        setTotpRequired(true);
+        return;
+      }
+
+      const user = result as { username: string };
       setUser(user.username);
     } catch (_error) {
       setLoginFailed(true);
     }
   };
 
+  if (totpRequired) {
+    return (
+      <div className="login-container">
+        {loginFailed && "Wrong code"}
+        <form onSubmit={onSubmit}>
+          <div className="login-row">
+            <label htmlFor="code">Code from app or recovery code</label>
+            <input name="code" type="text" autoComplete="one-time-code"></input>
+          </div>
+          <input className="login-submit" type="submit" value="Login"></input>
+        </form>
+      </div>
+    );
+  }
+
   return (
     <div className="login-container">
       {loginFailed && "Login failed"}
@@ -2,15 +2,35 @@
 
 export type Account = {
   id: number;
+  household_id: number;
+  closed_date: string | null;
   name: string;
   account_type: AccountType;
   statement_schema_id: number | null;
+  currency: string;
+  institution: string | null;
+  last_four: string | null;
+  notes: string | null;
+  owner: string | null;
+};
+
+export type AccountBalance = {
+  balance: Money;
+  locked_until: string | null;
+  history: Array<BalancePoint>;
+  opening_balance: Money;
+  opening_date: string | null;
 };
 
 export type AccountFields = {
   name: string;
   account_type: AccountType;
   statement_schema_id: number | null;
+  currency: string;
+  institution: string | null;
+  last_four: string | null;
+  notes: string | null;
+  owner: string | null;
 };
 
 export type AccountType = "Bank" | "CreditCard" | "Shop" | "Cash";
@@ -18,9 +38,9 @@ export type AccountType = "Bank" | "CreditCard" | "Shop" | "Cash";
 export type Accounts = { accounts: Array<Account> };
 
 export type Allowance =
-  | { variant: "Weekly"; amount: number }
-  | { variant: "Monthly"; amount: number }
-  | { variant: "Yearly"; amount: number };
+  | { variant: "Weekly"; amount: Money }
+  | { variant: "Monthly"; amount: Money }
+  | { variant: "Yearly"; amount: Money };
 
 export type AmountField =
   | {
@@ -37,6 +57,50 @@ export type AmountField =
       };
     };
 
+export type ApiToken = {
+  id: number;
+  name: string;
+  scopes: Array<TokenScope>;
+  created_ts: number;
+  expires_ts: number;
+  last_used_ts: number | null;
+};
+
+export type ApiTokenFields = {
+  name: string;
+  scopes: Array<TokenScope>;
+  expires_in_days: bigint;
+};
+
+export type ApiTokens = { tokens: Array<ApiToken> };
+
+export type Attachment = {
+  id: number;
+  uploaded_at: string;
+  expense_id: number;
+  filename: string;
+  content_type: string;
+  size: number;
+  sha256: string;
+};
+
+export type Attachments = { attachments: Array<Attachment> };
+
+export type BalanceField = {
+  variant: "FromColumn";
+  params: { col: number; invert: boolean };
+};
+
+export type BalanceGap = {
+  from_date: string;
+  to_date: string;
+  difference: Money | null;
+};
+
+export type BalanceGaps = { gaps: Array<BalanceGap> };
+
+export type BalancePoint = { date: string; balance: Money };
+
 export type Budget = {
   year: number;
   categories: Array<BudgetCategory>;
@@ -45,6 +109,7 @@ export type Budget = {
 
 export type BudgetCategory = {
   id: number;
+  household_id: number;
   year: number;
   name: string;
   ignored: boolean;
@@ -75,7 +140,7 @@ export type BudgetItemFields = {
 
 export type BudgetItemWithSpend = {
   year: number;
-  spend: number;
+  spend: Money;
   id: number;
   ignored: boolean;
   display_name: string;
@@ -86,33 +151,107 @@ export type BudgetItemWithSpend = {
   budget_only: boolean;
 };
 
+export type CreatedApiToken = { id: number; token: string };
+
 export type DateField = {
   variant: "FromColumn";
   params: { col: number; tz: TZ };
 };
 
+export type ErrorCode =
+  | "unauthorized"
+  | "not_found"
+  | "forbidden"
+  | "conflict"
+  | "validation"
+  | "import_error"
+  | "too_many_requests"
+  | "internal";
+
+export type ExchangeRate = { currency: string; date: string; rate: number };
+
+export type ExchangeRates = { rates: Array<ExchangeRate> };
+
 export type Expense = {
   id: number;
   account_id: number;
   transaction_date: string;
   transaction_time: string | null;
   description: string;
-  amount: number;
+  amount: Money;
+  balance?: Money | null;
+  original_currency?: string | null;
+  original_amount?: Money | null;
   budget_item_id: number | null;
   notes: string | null;
 };
 
+export type ExpenseChange = {
+  id: number;
+  username: string;
+  timestamp: number;
+  old_transaction_date: string;
+  old_transaction_time: string | null;
+  old_description: string;
+  old_amount: Money;
+  new_transaction_date: string;
+  new_transaction_time: string | null;
+  new_description: string;
+  new_amount: Money;
+};
+
 export type ExpenseFields = {
   account_id: number;
   transaction_date: string;
   transaction_time: string | null;
   description: string;
-  amount: number;
+  amount: Money;
+  balance?: Money | null;
+  original_currency?: string | null;
+  original_amount?: Money | null;
+};
+
+export type ExpenseHistory = {
+  original: ExpenseOriginal | null;
+  changes: Array<ExpenseChange>;
+};
+
+export type ExpenseOriginal = {
+  transaction_date: string;
+  transaction_time: string | null;
+  description: string;
+  amount: Money;
 };
 
+export type ExpenseTag = { expense_id: number; tag_id: number };
+
 export type Expenses = { expenses: Array<Expense> };
 
-export type ExpensesQuery = { period: string; selector: ExpensesQuerySelector };
+export type ExpensesCursor = {
+  transaction_date: string;
+  transaction_time: string | null;
+  id: number;
+};
+
+export type ExpensesPageRequest = {
+  after: ExpensesCursor | null;
+  limit: number;
+};
+
+export type ExpensesQuery = {
+  period: string;
+  selector: ExpensesQuerySelector;
+  page?: ExpensesPageRequest;
+  tag_ids?: Array<number>;
+};
+
+export type ExpensesQueryResult = {
+  expenses: Array<Expense>;
+  tags: Array<ExpenseTag>;
+  next: ExpensesCursor | null;
+  total_count: number;
+  total_amount: Money;
+};
 
 export type ExpensesQuerySelector =
   | { variant: "AllNotIgnored" }
@@ -121,7 +260,31 @@ export type ExpensesQuerySelector =
   | { variant: "BudgetItem"; id: number }
   | { variant: "BudgetCategory"; id: number };
 
-export type Fund = { id: number; name: string };
+export type ExpensesSearch = {
+  text: string | null;
+  min_amount: Money | null;
+  max_amount: Money | null;
+  from_date: string | null;
+  to_date: string | null;
+  account_ids: Array<number>;
+  tag_ids: Array<number>;
+  categorized: boolean | null;
+  has_notes: boolean | null;
+  sort_by: ExpensesSortField;
+  descending: boolean;
+  limit: number | null;
+  offset: number;
+};
+
+export type ExpensesSearchResults = {
+  expenses: Array<Expense>;
+  tags: Array<ExpenseTag>;
+  total: number;
+};
+
+export type ExpensesSortField = "Date" | "Amount" | "Description";
+
+export type Fund = { id: number; household_id: number; name: string };
 
 export type FundFields = { name: string };
 
@@ -129,22 +292,171 @@ export type FundItems = { items: Array<BudgetItemWithSpend> };
 
 export type Funds = { funds: Array<Fund> };
 
+export type ImportSummary = { imported: number; gaps: Array<BalanceGap> };
+
+export type Money = number;
+
+export type OpeningBalance = {
+  opening_balance: Money;
+  opening_date: string | null;
+};
+
+export type PendingReimbursement = {
+  expected_amount: Money;
+  received_amount: Money;
+  age_days: number;
+  id: number;
+  account_id: number;
+  transaction_date: string;
+  transaction_time: string | null;
+  description: string;
+  amount: Money;
+  balance?: Money | null;
+  original_currency?: string | null;
+  original_amount?: Money | null;
+  budget_item_id: number | null;
+  notes: string | null;
+};
+
+export type PendingReimbursementFields = { amount: Money | null };
+
+export type PendingReimbursements = { expenses: Array<PendingReimbursement> };
+
+export type PlannedExpense = {
+  id: number;
+  expense_id: number | null;
+  budget_item_id: number;
+  expected_date: string;
+  amount: Money;
+  description: string;
+};
+
+export type PlannedExpenseFields = {
+  budget_item_id: number;
+  expected_date: string;
+  amount: Money;
+  description: string;
+};
+
+export type PlannedExpenseLink = { expense_id: number | null };
+
+export type PlannedExpenses = { planned_expenses: Array<PlannedExpense> };
+
+export type Problem = {
+  status: number;
+  code: ErrorCode;
+  message: string;
+  correlation_id: string;
+};
+
+export type Reconciliation = {
+  id: number;
+  account_id: number;
+  statement_date: string;
+  statement_balance: Money;
+};
+
+export type ReconciliationRequest = {
+  statement_date: string;
+  statement_balance: Money;
+};
+
+export type ReconciliationResult = {
+  statement_balance: Money;
+  computed_balance: Money;
+  difference: Money;
+  reconciled: boolean;
+};
+
+export type Reconciliations = { reconciliations: Array<Reconciliation> };
+
 export type RecordMapping = {
   transaction_date: DateField;
   transaction_time: TimeField;
   description: TextField;
   amount: AmountField;
+  balance?: BalanceField | null;
+};
+
+export type RecurringAlert =
+  | {
+      variant: "MissingCharge";
+      description: string;
+      account_id: number;
+      expected_date: string;
+      expected_amount: Money;
+    }
+  | {
+      variant: "AmountChanged";
+      description: string;
+      account_id: number;
+      expense_id: number;
+      previous_amount: Money;
+      amount: Money;
+    };
+
+export type RecurringCadence ="Weekly" | "Biweekly" | "Monthly" | "Quarterly" | "Yearly";
+
+export type RecurringForecast = { data: Array<RecurringForecastPoint> };
+
+export type RecurringForecastPoint = {
+  month: string;
+  budget_item_id: number | null;
+  amount: Money;
+  monthly_allowance: Money | null;
 };
 
+export type RecurringSeries = {
+  description: string;
+  account_id: number;
+  budget_item_id: number | null;
+  cadence: RecurringCadence;
+  expense_ids: Array<number>;
+  last_date: string;
+  last_amount: Money;
+  expected_date: string;
+  expected_amount: Money;
+  status: RecurringStatus;
+};
+
+export type RecurringSeriesList = {
+  series: Array<RecurringSeries>;
+  alerts: Array<RecurringAlert>;
+};
+
+export type RecurringStatus = "Active" | "Missing" | "Ended";
+
+export type Reimbursement = {
+  id: number;
+  reimbursement_id: number;
+  expense_id: number;
+  amount: Money;
+};
+
+export type ReimbursementFields = { expense_id: number; amount: Money };
+
+export type Reimbursements = { reimbursements: Array<Reimbursement> };
+
+export type Session = {
+  id: number;
+  created_ts: number;
+  last_seen_ts: number;
+  user_agent: string | null;
+  ip: string | null;
+};
+
+export type Sessions = { sessions: Array<Session>; current_id: number | null };
+
 export type SpendingData = {
   data: Array<SpendingDataPoint>;
+  committed: Array<SpendingDataPoint>;
   fund_items: Array<BudgetItemWithSpend>;
 };
 
 export type SpendingDataPoint = {
   budget_item_id: number | null;
   month: string;
-  amount: number;
+  amount: Money;
 };
 
 export type StatementSchema = {
@@ -164,6 +476,22 @@ export type StatementSchemas = { schemas: Array<StatementSchema> };
 
 export type TZ = "Local" | "UTC";
 
+export type Tag = { id: number; household_id: number; name: string };
+
+export type TagExpensesRequest = { expense_ids: Array<number> };
+
+export type TagFields = { name: string };
+
+export type TagSpendingData = { data: Array<TagSpendingDataPoint> };
+
+export type TagSpendingDataPoint = {
+  tag_id: number;
+  month: string;
+  amount: Money;
+};
+
+export type Tags = { tags: Array<Tag> };
+
 export type TestSchemaRequest = { schema: StatementSchemaFields; row: string };
 
 export type TestSchemaResponse = {
@@ -179,3 +507,23 @@ export type TextField = { variant: "FromColumn"; params: { col: number } };
 export type TimeField =
   | { variant: "FromColumn"; params: { col: number; tz: TZ } }
   | { variant: "Empty" };
+
+export type TokenScope = "Read" | "Import" | "Write";
+
+export type UpdateReimbursementsRequest = {
+  reimbursements: Array<ReimbursementFields>;
+};
+
+export type WriteLogRecord = {
+  id: number;
+  uri: string;
+  method: string;
+  username: string;
+  api_token_id: number | null;
+  content: string | null;
+  status: string | null;
+  start_ts: number;
+  end_ts: number | null;
+};
+
+export type WriteLogRecords = { entries: Array<WriteLogRecord> };
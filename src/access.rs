@@ -0,0 +1,63 @@
+use crate::database::{Database, ID};
+use crate::response::ApiResponse;
+use crate::schema::household::HouseholdRole;
+
+/* Controllers check the role of the user for records they read or change by id. Records of other
+households look the same as missing ones, and viewers get Forbidden on writes. */
+
+pub fn can_view(role: anyhow::Result<Option<HouseholdRole>>) -> Result<HouseholdRole, ApiResponse> {
+    match role {
+        Ok(Some(value)) => Ok(value),
+        Ok(None) => Err(ApiResponse::not_found()),
+        Err(e) => Err(ApiResponse::error(e)),
+    }
+}
+
+pub fn can_edit(role: anyhow::Result<Option<HouseholdRole>>) -> Result<HouseholdRole, ApiResponse> {
+    let role = can_view(role)?;
+    if !role.can_edit() {
+        return Err(ApiResponse::forbidden(
+            "Viewers can't change household data.",
+        ));
+    }
+
+    Ok(role)
+}
+
+pub fn can_manage(
+    role: anyhow::Result<Option<HouseholdRole>>,
+) -> Result<HouseholdRole, ApiResponse> {
+    let role = can_view(role)?;
+    if role != HouseholdRole::Owner {
+        return Err(ApiResponse::forbidden(
+            "Only owners can manage the household.",
+        ));
+    }
+
+    Ok(role)
+}
+
+// New records go to the given household, or the only one the user can edit when none is given
+pub async fn household_for_create(
+    db: &Database,
+    username: &str,
+    household_id: Option<ID>,
+) -> Result<ID, ApiResponse> {
+    if let Some(id) = household_id {
+        can_edit(HouseholdRole::fetch_for_household(db, username, id).await)?;
+        return Ok(id);
+    }
+
+    let ids = match HouseholdRole::fetch_editable_household_ids(db, username).await {
+        Ok(value) => value,
+        Err(e) => return Err(ApiResponse::error(e)),
+    };
+
+    match ids.as_slice() {
+        [id] => Ok(*id),
+        [] => Err(ApiResponse::forbidden("User can't edit any household.")),
+        _ => Err(ApiResponse::bad(
+            "User is in several households, household_id is required.",
+        )),
+    }
+}
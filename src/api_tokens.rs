@@ -0,0 +1,310 @@
+use rocket::http::uri::Origin;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use ts_rs::TS;
+
+use crate::common::{now, TS_FILE};
+use crate::crypto::{random_hex, sha256_hex};
+use crate::database::{Database, ID};
+use crate::schema::token_scope::{TokenScope, TokenScopes};
+
+/* Personal tokens for scripts, sent as `Authorization: Bearer <token>` instead of logging in.
+Token acts as its user, limited to its scopes, and can't be used to manage sessions or tokens,
+so that a leaked token can't be turned into more access. */
+
+const TOKEN_PREFIX: &str = "bgt_"; // makes tokens easy to spot, e.g. in committed scripts
+const TOKEN_BYTES: usize = 32;
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+const LAST_USED_RESOLUTION_MS: i64 = 60 * 1000;
+
+pub const MAX_EXPIRY_DAYS: i64 = 365;
+
+// Queries which are POST only because of their body size
+const READ_POSTS: [&str; 4] = [
+    "/api/expenses/query",
+    "/api/expenses/query/stream",
+    "/api/expenses/search",
+    "/api/schemas/test",
+];
+const IMPORT_POSTS: [&str; 1] = ["/api/expenses/import"];
+const LOGIN_ONLY_PREFIXES: [&str; 3] = ["/api/tokens", "/api/sessions", "/api/logout"];
+
+#[derive(Debug, FromRow, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct ApiToken {
+    pub id: ID,
+    #[serde(skip)]
+    #[ts(skip)]
+    pub username: String,
+    pub name: String,
+    #[ts(as = "Vec<TokenScope>")]
+    pub scopes: TokenScopes,
+    #[ts(type = "number")]
+    pub created_ts: i64,
+    #[ts(type = "number")]
+    pub expires_ts: i64,
+    #[ts(type = "number | null")]
+    pub last_used_ts: Option<i64>,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct ApiTokens {
+    pub tokens: Vec<ApiToken>,
+}
+
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct ApiTokenFields {
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    pub expires_in_days: i64,
+}
+
+// The only time the token is shown
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct CreatedApiToken {
+    pub id: ID,
+    pub token: String,
+}
+
+impl ApiTokenFields {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err(String::from("Token name can't be empty."));
+        }
+
+        if self.scopes.is_empty() {
+            return Err(String::from("Token needs at least one scope."));
+        }
+
+        if self.expires_in_days < 1 || self.expires_in_days > MAX_EXPIRY_DAYS {
+            return Err(format!(
+                "Token has to expire in 1 to {} days.",
+                MAX_EXPIRY_DAYS
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/* Rocket routes on percent-decoded path segments and skips empty ones, so /api//tokens and
+/api/%74okens both reach /api/tokens. Scopes are checked against the path built the same way. */
+pub fn normalized_path(uri: &Origin<'_>) -> String {
+    let segments: Vec<&str> = uri.path().segments().collect();
+
+    format!("/{}", segments.join("/"))
+}
+
+// Path has to be normalized, see above
+pub fn token_allows(scopes: &TokenScopes, is_get: bool, path: &str) -> bool {
+    if LOGIN_ONLY_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+    {
+        return false;
+    }
+
+    if is_get || READ_POSTS.contains(&path) {
+        return scopes.contains(TokenScope::Read);
+    }
+
+    if IMPORT_POSTS.contains(&path) {
+        return scopes.contains(TokenScope::Import) || scopes.contains(TokenScope::Write);
+    }
+
+    scopes.contains(TokenScope::Write)
+}
+
+impl ApiToken {
+    pub async fn create(
+        db: &Database,
+        username: &str,
+        fields: &ApiTokenFields,
+    ) -> anyhow::Result<CreatedApiToken> {
+        let token = format!("{}{}", TOKEN_PREFIX, random_hex(TOKEN_BYTES));
+        let token_hash = sha256_hex(token.as_bytes());
+        let scopes = TokenScopes(fields.scopes.clone());
+        let created_ts = now();
+        let expires_ts = created_ts + fields.expires_in_days * DAY_MS;
+
+        let mut conn = db.acquire_db_conn().await?;
+        let id: ID = sqlx::query_scalar!(
+            "INSERT INTO api_tokens (
+              username,
+              name,
+              token_hash,
+              scopes,
+              created_ts,
+              expires_ts
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6) RETURNING id",
+            username,
+            fields.name,
+            token_hash,
+            scopes,
+            created_ts,
+            expires_ts,
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .expect("INSERT failed, likely FOREIGN KEY constraint")
+        .try_into()
+        .unwrap();
+
+        Ok(CreatedApiToken { id, token })
+    }
+
+    // Valid token, marking it as used
+    pub async fn fetch_by_token(db: &Database, token: &str) -> anyhow::Result<Option<ApiToken>> {
+        if !token.starts_with(TOKEN_PREFIX) {
+            return Ok(None);
+        }
+
+        let token_hash = sha256_hex(token.as_bytes());
+        let ts = now();
+
+        let mut conn = db.acquire_db_conn().await?;
+        let mut api_token = match sqlx::query_as::<_, ApiToken>(
+            "SELECT id, username, name, scopes, created_ts, expires_ts, last_used_ts
+            FROM api_tokens WHERE token_hash = ?1 AND expires_ts > ?2",
+        )
+        .bind(token_hash)
+        .bind(ts)
+        .fetch_optional(&mut *conn)
+        .await?
+        {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        if api_token
+            .last_used_ts
+            .is_none_or(|last_used| ts - last_used > LAST_USED_RESOLUTION_MS)
+        {
+            sqlx::query!(
+                "UPDATE api_tokens SET last_used_ts = ?2 WHERE id = ?1",
+                api_token.id,
+                ts,
+            )
+            .execute(&mut *conn)
+            .await?;
+            api_token.last_used_ts = Some(ts);
+        }
+
+        Ok(Some(api_token))
+    }
+
+    // Expired tokens are listed too, so that it's clear why a script stopped working
+    pub async fn fetch_by_username(db: &Database, username: &str) -> anyhow::Result<ApiTokens> {
+        let mut conn = db.acquire_db_conn().await?;
+        let results = sqlx::query_as::<_, ApiToken>(
+            "SELECT id, username, name, scopes, created_ts, expires_ts, last_used_ts
+            FROM api_tokens WHERE username = ?1
+            ORDER BY created_ts DESC",
+        )
+        .bind(username)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(ApiTokens { tokens: results })
+    }
+
+    // Users can only revoke their own tokens; returns whether there was one to revoke
+    pub async fn delete(db: &Database, username: &str, id: ID) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query!(
+            "DELETE FROM api_tokens WHERE id = ?1 AND username = ?2",
+            id,
+            username,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_allows() {
+        let read = TokenScopes(vec![TokenScope::Read]);
+        assert!(token_allows(&read, true, "/api/accounts"));
+        assert!(token_allows(&read, false, "/api/expenses/query"));
+        assert!(!token_allows(&read, false, "/api/expenses/import"));
+        assert!(!token_allows(&read, false, "/api/accounts"));
+
+        let import = TokenScopes(vec![TokenScope::Import]);
+        assert!(token_allows(&import, false, "/api/expenses/import"));
+        assert!(!token_allows(&import, true, "/api/accounts"));
+        assert!(!token_allows(&import, false, "/api/expenses/bulk_delete"));
+
+        let write = TokenScopes(vec![TokenScope::Write]);
+        assert!(token_allows(&write, false, "/api/expenses/import"));
+        assert!(token_allows(&write, false, "/api/expenses/bulk_delete"));
+        assert!(!token_allows(&write, true, "/api/accounts"));
+
+        let all = TokenScopes(vec![
+            TokenScope::Read,
+            TokenScope::Import,
+            TokenScope::Write,
+        ]);
+        assert!(!token_allows(&all, true, "/api/tokens"));
+        assert!(!token_allows(&all, false, "/api/tokens"));
+        assert!(!token_allows(&all, true, "/api/sessions"));
+        assert!(!token_allows(&all, true, "/api/logout"));
+    }
+
+    #[test]
+    fn test_normalized_path() {
+        let cases = [
+            ("/api/tokens", "/api/tokens"),
+            ("/api//tokens", "/api/tokens"),
+            ("//api/tokens/", "/api/tokens"),
+            ("/api/%74okens", "/api/tokens"),
+            ("/api/expenses/%69mport?x=1", "/api/expenses/import"),
+            ("/", "/"),
+        ];
+
+        for (uri, expected) in cases.into_iter() {
+            let origin = Origin::parse(uri).unwrap();
+            assert_eq!(normalized_path(&origin), expected);
+        }
+
+        let write = TokenScopes(vec![TokenScope::Write]);
+        let origin = Origin::parse("/api//tokens").unwrap();
+        assert!(!token_allows(&write, false, &normalized_path(&origin)));
+        let origin = Origin::parse("/api/%73essions").unwrap();
+        let read = TokenScopes(vec![TokenScope::Read]);
+        assert!(!token_allows(&read, true, &normalized_path(&origin)));
+        let origin = Origin::parse("/api//expenses/query").unwrap();
+        assert!(token_allows(&read, false, &normalized_path(&origin)));
+    }
+
+    #[test]
+    fn test_validate_fields() {
+        let mut fields = ApiTokenFields {
+            name: String::from("cron import"),
+            scopes: vec![TokenScope::Import],
+            expires_in_days: 90,
+        };
+        assert!(fields.validate().is_ok());
+
+        fields.expires_in_days = MAX_EXPIRY_DAYS + 1;
+        assert!(fields.validate().is_err());
+        fields.expires_in_days = 0;
+        assert!(fields.validate().is_err());
+
+        fields.expires_in_days = 90;
+        fields.scopes = vec![];
+        assert!(fields.validate().is_err());
+
+        fields.scopes = vec![TokenScope::Read];
+        fields.name = String::from(" ");
+        assert!(fields.validate().is_err());
+    }
+}
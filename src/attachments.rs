@@ -0,0 +1,129 @@
+use std::path::PathBuf;
+use tokio::fs;
+
+use crate::crypto::sha256_hex;
+use crate::database::Database;
+use crate::schema::attachment::Attachment;
+
+pub const ATTACHMENTS_PATH: &str = "www/upload/attachments";
+
+// Receipts and invoices are either scanned documents or photos
+pub const ALLOWED_CONTENT_TYPES: [&str; 5] = [
+    "application/pdf",
+    "image/jpeg",
+    "image/png",
+    "image/gif",
+    "image/webp",
+];
+
+/* Files are stored under the hex encoded sha256 of their content, with the first two characters
+used as a subdirectory to keep directories small, e.g. "ba/ba7816bf...". */
+pub fn content_path(sha256: &str) -> PathBuf {
+    PathBuf::from(ATTACHMENTS_PATH)
+        .join(&sha256[..2])
+        .join(sha256)
+}
+
+pub fn is_allowed_content_type(content_type: &str) -> bool {
+    ALLOWED_CONTENT_TYPES.contains(&content_type)
+}
+
+// Only the file name is kept from what browser sent, and characters which can't be sent in
+// Content-Disposition header on download are replaced.
+pub fn sanitize_filename(filename: &str) -> String {
+    let name = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c.is_control() || c == '"' || !c.is_ascii() {
+            true => '_',
+            false => c,
+        })
+        .collect();
+
+    match sanitized.trim().is_empty() {
+        true => String::from("attachment"),
+        false => sanitized.trim().to_string(),
+    }
+}
+
+// Returns sha256 of the content. Writing same content twice is a no-op.
+pub async fn store(content: &[u8]) -> anyhow::Result<String> {
+    let sha256 = sha256_hex(content);
+    let path = content_path(&sha256);
+    if fs::try_exists(&path).await? {
+        return Ok(sha256);
+    }
+
+    let dir = path.parent().unwrap();
+    fs::create_dir_all(dir).await?;
+
+    // write to temp file first, so partially written file is never visible under final path
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, content).await?;
+    fs::rename(&tmp_path, &path).await?;
+
+    Ok(sha256)
+}
+
+pub async fn read(sha256: &str) -> anyhow::Result<Vec<u8>> {
+    let content = fs::read(content_path(sha256)).await?;
+
+    Ok(content)
+}
+
+// Removes the file unless other attachment still refers to the same content
+pub async fn remove_if_unreferenced(db: &Database, sha256: &str) -> anyhow::Result<()> {
+    if Attachment::any_has_sha256(db, sha256).await? {
+        return Ok(());
+    }
+
+    let path = content_path(sha256);
+    if fs::try_exists(&path).await? {
+        fs::remove_file(path).await?;
+    }
+
+    Ok(())
+}
+
+/* Deleting expenses removes their attachments through ON DELETE CASCADE, which leaves the files
+behind. Callers fetch the hashes before the delete and pass them here, only those files are
+checked, so that a file of upload which is not yet saved in the database isn't removed. */
+pub async fn remove_all_if_unreferenced(db: &Database, hashes: &[String]) -> anyhow::Result<()> {
+    for sha256 in hashes.iter() {
+        remove_if_unreferenced(db, sha256).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_path() {
+        let sha256 = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let path = content_path(sha256);
+        assert_eq!(
+            path,
+            PathBuf::from(ATTACHMENTS_PATH).join("ba").join(sha256)
+        );
+    }
+
+    #[test]
+    fn test_sanitize_filename() {
+        let cases = vec![
+            ("receipt.pdf", "receipt.pdf"),
+            ("C:\\Users\\me\\scan 1.jpg", "scan 1.jpg"),
+            ("../../etc/passwd", "passwd"),
+            ("in\"voice\n.pdf", "in_voice_.pdf"),
+            ("účet.png", "__et.png"),
+            ("dir/", "attachment"),
+            ("   ", "attachment"),
+        ];
+
+        for (input, expected) in cases.into_iter() {
+            assert_eq!(sanitize_filename(input), expected);
+        }
+    }
+}
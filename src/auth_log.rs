@@ -0,0 +1,259 @@
+use sqlx::FromRow;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use crate::common::now;
+use crate::database::Database;
+use crate::guards::client::ClientInfo;
+
+/* Every login attempt is recorded, and recent failures slow down further attempts, both for the
+username and for the IP. First few failures are free, then each one doubles the wait, up to
+a temporary lockout. Throttled attempts are rejected before the password is checked, as checking
+it is deliberately expensive. Success clears failures of the username only, otherwise anyone with
+an account could clear failures of their IP by logging in between guesses. */
+
+const FAILURE_WINDOW_MS: i64 = 60 * 60 * 1000; // older failures are forgotten
+const FIRST_DELAY_MS: i64 = 1000;
+const MAX_DELAY_MS: i64 = 15 * 60 * 1000;
+
+// IP can be shared by whole household behind NAT, so it gets more attempts
+pub const FREE_USERNAME_FAILURES: i64 = 3;
+pub const FREE_IP_FAILURES: i64 = 10;
+
+#[derive(Debug, Clone, Copy)]
+pub enum AuthEvent {
+    LoginSucceeded,
+    PasswordFailed,
+    CodeFailed,
+    Throttled,
+}
+
+impl AuthEvent {
+    fn name(self) -> &'static str {
+        match self {
+            AuthEvent::LoginSucceeded => "LoginSucceeded",
+            AuthEvent::PasswordFailed => "PasswordFailed",
+            AuthEvent::CodeFailed => "CodeFailed",
+            AuthEvent::Throttled => "Throttled",
+        }
+    }
+}
+
+/* Failures are recorded only after the password is checked, so attempts running at the same time
+would all see the same past failures and all get checked. Only one attempt per username and per
+IP runs at a time, others are throttled right away. Server is a single process, so this is kept
+in memory. */
+#[derive(Debug, Default)]
+pub struct LoginAttempts {
+    in_progress: Mutex<HashSet<String>>,
+}
+
+// Ends the attempt when dropped, so it has to be kept until its outcome is recorded
+#[derive(Debug)]
+pub struct LoginAttempt<'a> {
+    attempts: &'a LoginAttempts,
+    keys: Vec<String>,
+}
+
+impl LoginAttempts {
+    // None when another attempt of the username or the IP is still running
+    pub fn begin(&self, username: &str, client: &ClientInfo) -> Option<LoginAttempt<'_>> {
+        let mut keys = vec![format!("username:{}", username)];
+        if let Some(ip) = &client.ip {
+            keys.push(format!("ip:{}", ip));
+        }
+
+        let mut in_progress = self.in_progress.lock().unwrap();
+        if keys.iter().any(|key| in_progress.contains(key)) {
+            return None;
+        }
+        in_progress.extend(keys.iter().cloned());
+
+        Some(LoginAttempt {
+            attempts: self,
+            keys,
+        })
+    }
+}
+
+impl Drop for LoginAttempt<'_> {
+    fn drop(&mut self) {
+        let mut in_progress = self.attempts.in_progress.lock().unwrap();
+        for key in &self.keys {
+            in_progress.remove(key);
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+pub struct FailedAttempts {
+    pub count: i64,
+    pub last_ts: Option<i64>,
+}
+
+fn retry_delay_ms(failures: i64, free_failures: i64) -> i64 {
+    if failures < free_failures {
+        return 0;
+    }
+
+    // capped before shifting, so that the shift can't overflow
+    let doublings = (failures - free_failures).min(32);
+    (FIRST_DELAY_MS << doublings).min(MAX_DELAY_MS)
+}
+
+impl FailedAttempts {
+    // How long until next attempt is allowed, None when it's allowed now
+    pub fn wait_ms(&self, free_failures: i64, now: i64) -> Option<i64> {
+        let last_ts = self.last_ts?;
+        let wait = last_ts + retry_delay_ms(self.count, free_failures) - now;
+
+        if wait > 0 {
+            Some(wait)
+        } else {
+            None
+        }
+    }
+
+    pub async fn fetch_by_username(
+        db: &Database,
+        username: &str,
+    ) -> anyhow::Result<FailedAttempts> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query_as::<_, FailedAttempts>(
+            "SELECT COUNT(*) AS count, MAX(ts) AS last_ts FROM auth_log
+            WHERE username = ?1
+              AND event IN ('PasswordFailed', 'CodeFailed')
+              AND ts > ?2
+              AND ts > COALESCE((
+                SELECT MAX(ts) FROM auth_log WHERE username = ?1 AND event = 'LoginSucceeded'
+              ), 0)",
+        )
+        .bind(username)
+        .bind(now() - FAILURE_WINDOW_MS)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(result)
+    }
+
+    pub async fn fetch_by_ip(db: &Database, ip: &str) -> anyhow::Result<FailedAttempts> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query_as::<_, FailedAttempts>(
+            "SELECT COUNT(*) AS count, MAX(ts) AS last_ts FROM auth_log
+            WHERE ip = ?1
+              AND event IN ('PasswordFailed', 'CodeFailed')
+              AND ts > ?2",
+        )
+        .bind(ip)
+        .bind(now() - FAILURE_WINDOW_MS)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(result)
+    }
+}
+
+pub async fn record_auth_event(
+    db: &Database,
+    event: AuthEvent,
+    username: &str,
+    client: &ClientInfo,
+) -> anyhow::Result<()> {
+    let ts = now();
+    let event = event.name();
+
+    let mut conn = db.acquire_db_conn().await?;
+    sqlx::query!(
+        "INSERT INTO auth_log (username, ip, user_agent, event, ts) VALUES (?1, ?2, ?3, ?4, ?5)",
+        username,
+        client.ip,
+        client.user_agent,
+        event,
+        ts,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// Longest wait of the username and the IP, None when login can be attempted now
+pub async fn fetch_login_wait_ms(
+    db: &Database,
+    username: &str,
+    client: &ClientInfo,
+) -> anyhow::Result<Option<i64>> {
+    let ts = now();
+
+    let by_username = FailedAttempts::fetch_by_username(db, username).await?;
+    let mut wait = by_username.wait_ms(FREE_USERNAME_FAILURES, ts);
+
+    if let Some(ip) = &client.ip {
+        let by_ip = FailedAttempts::fetch_by_ip(db, ip).await?;
+        wait = wait.max(by_ip.wait_ms(FREE_IP_FAILURES, ts));
+    }
+
+    Ok(wait)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_ms() {
+        assert_eq!(retry_delay_ms(0, 3), 0);
+        assert_eq!(retry_delay_ms(2, 3), 0);
+        assert_eq!(retry_delay_ms(3, 3), 1000);
+        assert_eq!(retry_delay_ms(4, 3), 2000);
+        assert_eq!(retry_delay_ms(6, 3), 8000);
+        assert_eq!(retry_delay_ms(13, 3), MAX_DELAY_MS);
+        assert_eq!(retry_delay_ms(1000, 3), MAX_DELAY_MS);
+    }
+
+    #[test]
+    fn test_wait_ms() {
+        let ts = 1_000_000_000_000;
+        let none = FailedAttempts {
+            count: 0,
+            last_ts: None,
+        };
+        assert_eq!(none.wait_ms(3, ts), None);
+
+        let few = FailedAttempts {
+            count: 2,
+            last_ts: Some(ts),
+        };
+        assert_eq!(few.wait_ms(3, ts), None);
+
+        let many = FailedAttempts {
+            count: 5,
+            last_ts: Some(ts),
+        };
+        assert_eq!(many.wait_ms(3, ts + 1000), Some(3000));
+        assert_eq!(many.wait_ms(3, ts + 4000), None);
+        assert_eq!(many.wait_ms(10, ts), None);
+    }
+
+    #[test]
+    fn test_concurrent_login_attempts() {
+        let attempts = LoginAttempts::default();
+        let client = ClientInfo {
+            user_agent: None,
+            ip: Some(String::from("192.0.2.1")),
+        };
+        let other_client = ClientInfo {
+            user_agent: None,
+            ip: Some(String::from("192.0.2.2")),
+        };
+
+        let first = attempts.begin("tester", &client);
+        assert!(first.is_some());
+        assert!(attempts.begin("tester", &other_client).is_none());
+        assert!(attempts.begin("other", &client).is_none());
+        assert!(attempts.begin("other", &other_client).is_some());
+
+        drop(first);
+        assert!(attempts.begin("tester", &client).is_some());
+    }
+}
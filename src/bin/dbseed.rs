@@ -3,12 +3,15 @@ use clap::{Parser, Subcommand};
 use budget::schema::account::{Account, AccountFields, AccountType};
 use budget::schema::budget::Budget;
 use budget::schema::category::{BudgetCategory, BudgetCategoryFields};
+use budget::schema::currency::HOME_CURRENCY;
 use budget::schema::datetime::TZ;
+use budget::schema::household::{Household, HouseholdFields};
 use budget::schema::item::{Allowance, BudgetItem, BudgetItemFields};
+use budget::schema::money::Money;
 use budget::schema::record_mapping::{AmountField, DateField, RecordMapping, TextField, TimeField};
 use budget::schema::statement_schema::{StatementSchema, StatementSchemaFields};
 
-use budget::database::Database;
+use budget::database::{Database, ID};
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -19,8 +22,9 @@ struct Args {
 #[derive(Debug, Subcommand)]
 enum Command {
     /// Seed the database with dummy data; use after creating database and applying all
-    /// schema changes from delta.{}.sql files
-    Seed,
+    /// schema changes from delta.{}.sql files. Data goes to new household owned by the user,
+    /// who has to exist already
+    Seed { username: String },
 }
 
 #[tokio::main]
@@ -29,7 +33,7 @@ async fn main() {
 
     let args = Args::parse();
     let _ = match args.command {
-        Command::Seed => match seed_db_for_testing(db).await {
+        Command::Seed { username } => match seed_db_for_testing(db, &username).await {
             Ok(_) => {}
             Err(e) => {
                 println!("{:?}", e);
@@ -38,14 +42,23 @@ async fn main() {
     };
 }
 
-async fn seed_db_for_testing(db: Database) -> anyhow::Result<()> {
+async fn seed_db_for_testing(db: Database, username: &str) -> anyhow::Result<()> {
+    let household_id = Household::create(
+        &db,
+        username,
+        HouseholdFields {
+            name: String::from("seed"),
+        },
+    )
+    .await?;
+
     add_statement_schemas(&db).await?;
-    add_accounts(&db).await?;
-    add_budget(&db).await?;
+    add_accounts(&db, household_id).await?;
+    add_budget(&db, household_id).await?;
 
     print_statement_schemas(&db).await?;
-    print_accounts(&db).await?;
-    print_budget(&db).await?;
+    print_accounts(&db, username).await?;
+    print_budget(&db, username).await?;
 
     Ok(())
 }
@@ -87,6 +100,7 @@ fn get_bank_record_mapping() -> RecordMapping {
             invert: false,
             skip_pattern: None,
         },
+        balance: None,
     }
 }
 
@@ -106,26 +120,39 @@ fn get_shop_record_mapping() -> RecordMapping {
             invert: false,
             skip_pattern: Some(String::from("Not Available")),
         },
+        balance: None,
     }
 }
 
-async fn add_accounts(db: &Database) -> anyhow::Result<()> {
+async fn add_accounts(db: &Database, household_id: ID) -> anyhow::Result<()> {
     Account::create(
         db,
+        household_id,
         AccountFields {
             name: String::from("big bank"),
             account_type: AccountType::Bank,
             statement_schema_id: Some(1),
+            currency: String::from(HOME_CURRENCY),
+            institution: None,
+            last_four: None,
+            notes: None,
+            owner: None,
         },
     )
     .await?;
 
     Account::create(
         db,
+        household_id,
         AccountFields {
             name: String::from("some shop"),
             account_type: AccountType::Shop,
             statement_schema_id: Some(2),
+            currency: String::from(HOME_CURRENCY),
+            institution: None,
+            last_four: None,
+            notes: None,
+            owner: None,
         },
     )
     .await?;
@@ -133,10 +160,16 @@ async fn add_accounts(db: &Database) -> anyhow::Result<()> {
     // Leave one account without statement_schema
     Account::create(
         db,
+        household_id,
         AccountFields {
             name: String::from("credit card"),
             account_type: AccountType::CreditCard,
             statement_schema_id: None,
+            currency: String::from(HOME_CURRENCY),
+            institution: None,
+            last_four: None,
+            notes: None,
+            owner: None,
         },
     )
     .await?;
@@ -154,8 +187,8 @@ async fn print_statement_schemas(db: &Database) -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn print_accounts(db: &Database) -> anyhow::Result<()> {
-    let accounts = match Account::fetch_all(&db).await {
+async fn print_accounts(db: &Database, username: &str) -> anyhow::Result<()> {
+    let accounts = match Account::fetch_all(db, username, true).await {
         Ok(result) => result.accounts,
         Err(e) => panic!("{}", e),
     };
@@ -168,9 +201,10 @@ async fn print_accounts(db: &Database) -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn add_budget(db: &Database) -> anyhow::Result<()> {
+async fn add_budget(db: &Database, household_id: ID) -> anyhow::Result<()> {
     let car_id = BudgetCategory::create(
         &db,
+        household_id,
         2025,
         BudgetCategoryFields {
             name: String::from("Car"),
@@ -181,6 +215,7 @@ async fn add_budget(db: &Database) -> anyhow::Result<()> {
 
     let shopping_id = BudgetCategory::create(
         &db,
+        household_id,
         2025,
         BudgetCategoryFields {
             name: String::from("Shopping"),
@@ -191,6 +226,7 @@ async fn add_budget(db: &Database) -> anyhow::Result<()> {
 
     let ignored_id = BudgetCategory::create(
         &db,
+        household_id,
         2025,
         BudgetCategoryFields {
             name: String::from("Ignored"),
@@ -206,7 +242,7 @@ async fn add_budget(db: &Database) -> anyhow::Result<()> {
             name: String::from("Fuel"),
             budget_only: false,
             fund_id: None,
-            allowance: Some(Allowance::Weekly(50 * 100)),
+            allowance: Some(Allowance::Weekly(Money::from_cents(50 * 100))),
         },
     )
     .await?;
@@ -218,7 +254,7 @@ async fn add_budget(db: &Database) -> anyhow::Result<()> {
             name: String::from("Loan"),
             budget_only: false,
             fund_id: None,
-            allowance: Some(Allowance::Monthly(300 * 100)),
+            allowance: Some(Allowance::Monthly(Money::from_cents(300 * 100))),
         },
     )
     .await?;
@@ -230,7 +266,7 @@ async fn add_budget(db: &Database) -> anyhow::Result<()> {
             name: String::from("Insurance"),
             budget_only: false,
             fund_id: None,
-            allowance: Some(Allowance::Yearly(1000 * 100)),
+            allowance: Some(Allowance::Yearly(Money::from_cents(1000 * 100))),
         },
     )
     .await?;
@@ -242,7 +278,7 @@ async fn add_budget(db: &Database) -> anyhow::Result<()> {
             name: String::from("Downpayment"),
             budget_only: false,
             fund_id: None,
-            allowance: Some(Allowance::Yearly(1000 * 100)),
+            allowance: Some(Allowance::Yearly(Money::from_cents(1000 * 100))),
         },
     )
     .await?;
@@ -254,7 +290,7 @@ async fn add_budget(db: &Database) -> anyhow::Result<()> {
             name: String::from("Groceries"),
             budget_only: false,
             fund_id: None,
-            allowance: Some(Allowance::Weekly(100 * 100)),
+            allowance: Some(Allowance::Weekly(Money::from_cents(100 * 100))),
         },
     )
     .await?;
@@ -266,7 +302,7 @@ async fn add_budget(db: &Database) -> anyhow::Result<()> {
             name: String::from("Clothing"),
             budget_only: false,
             fund_id: None,
-            allowance: Some(Allowance::Monthly(200 * 100)),
+            allowance: Some(Allowance::Monthly(Money::from_cents(200 * 100))),
         },
     )
     .await?;
@@ -298,8 +334,8 @@ async fn add_budget(db: &Database) -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn print_budget(db: &Database) -> anyhow::Result<()> {
-    let budget = Budget::fetch(&db, 2025).await?;
+async fn print_budget(db: &Database, username: &str) -> anyhow::Result<()> {
+    let budget = Budget::fetch(db, username, 2025).await?;
 
     println!("{:?}", budget);
 
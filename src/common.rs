@@ -1 +1,13 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 pub const TS_FILE: &str = "types.ts";
+
+// millis since epoch, as stored in write_log and sessions
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+        .try_into()
+        .unwrap()
+}
@@ -1,30 +1,83 @@
 use rocket::serde::json::Json;
-use rocket::{delete, get, post, State};
+use rocket::{delete, get, post, put, State};
 
+use crate::access::{can_edit, household_for_create};
+use crate::credentials::Credentials;
 use crate::database::{Database, ID};
+use crate::guards::user::User;
 use crate::guards::write_log::WriteLogEntry;
 use crate::response::ApiResponse;
-use crate::schema::account::{Account, AccountFields};
+use crate::schema::account::{looks_like_last_four, Account, AccountClosing, AccountFields};
+use crate::schema::balance::Reconciliation;
+use crate::schema::currency::looks_like_currency_code;
+use crate::schema::datetime::looks_like_valid_date;
 use crate::schema::expense::Expense;
+use crate::schema::household::HouseholdRole;
 
-#[get("/accounts")]
-pub async fn get_accounts(db: &State<Database>) -> ApiResponse {
-    match Account::fetch_all(&db).await {
+async fn validate_account_fields(
+    db: &Database,
+    fields: &AccountFields,
+) -> anyhow::Result<Result<(), String>> {
+    if !looks_like_currency_code(&fields.currency) {
+        let message = format!(
+            "Incorrect currency '{}', expected code like 'EUR'",
+            fields.currency
+        );
+        return Ok(Err(message));
+    }
+
+    if let Some(last_four) = &fields.last_four {
+        if !looks_like_last_four(last_four) {
+            let message = format!("Incorrect last digits '{}', expected 4 digits", last_four);
+            return Ok(Err(message));
+        }
+    }
+
+    if let Some(owner) = &fields.owner {
+        if Credentials::fetch_by_username(db, owner).await?.is_none() {
+            return Ok(Err(format!("User '{}' could not be found.", owner)));
+        }
+    }
+
+    Ok(Ok(()))
+}
+
+// Closed accounts are left out unless include_closed is set
+#[get("/accounts?<include_closed>")]
+pub async fn get_accounts(
+    db: &State<Database>,
+    user: &User,
+    include_closed: Option<bool>,
+) -> ApiResponse {
+    match Account::fetch_all(db, &user.username, include_closed.unwrap_or(false)).await {
         Ok(value) => ApiResponse::data(value),
         Err(e) => ApiResponse::error(e),
     }
 }
 
-#[post("/accounts", format = "json", data = "<request>")]
+#[post("/accounts?<household_id>", format = "json", data = "<request>")]
 pub async fn create_account(
     db: &State<Database>,
+    user: &User,
     log_entry: &WriteLogEntry,
+    household_id: Option<ID>,
     request: Json<AccountFields>,
 ) -> ApiResponse {
     let fields = request.into_inner();
     log_entry.set_content(&fields);
 
-    match Account::create(&db, fields).await {
+    let household_id = match household_for_create(db, &user.username, household_id).await {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
+
+    match validate_account_fields(db, &fields).await {
+        Ok(Ok(())) => (),
+        Ok(Err(message)) => return ApiResponse::bad(&message),
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    match Account::create(db, household_id, fields).await {
         Ok(_) => ApiResponse::ok(),
         Err(e) => ApiResponse::error(e),
     }
@@ -33,6 +86,7 @@ pub async fn create_account(
 #[put("/accounts/<id>", format = "json", data = "<request>")]
 pub async fn update_account(
     db: &State<Database>,
+    user: &User,
     log_entry: &WriteLogEntry,
     id: ID,
     request: Json<AccountFields>,
@@ -40,6 +94,34 @@ pub async fn update_account(
     let fields = request.into_inner();
     log_entry.set_content(&fields);
 
+    let role = HouseholdRole::fetch_for_account(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    match validate_account_fields(db, &fields).await {
+        Ok(Ok(())) => (),
+        Ok(Err(message)) => return ApiResponse::bad(&message),
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    let account = match Account::fetch_by_id(db, id).await {
+        Ok(value) => value,
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    // expenses were already converted with rates of the old currency
+    if account.fields.currency != fields.currency {
+        match Expense::any_has_account_id(db, id).await {
+            Ok(false) => (),
+            Ok(true) => {
+                let message = "Can't change currency of account that has expenses attached.";
+                return ApiResponse::bad(message);
+            }
+            Err(e) => return ApiResponse::error(e),
+        };
+    }
+
     match Account::update(&db, id, fields).await {
         Ok(_) => ApiResponse::ok(),
         Err(e) => ApiResponse::error(e),
@@ -49,9 +131,15 @@ pub async fn update_account(
 #[delete("/accounts/<id>")]
 pub async fn delete_account(
     db: &State<Database>,
+    user: &User,
     _log_entry: &WriteLogEntry,
     id: ID,
 ) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_account(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
     match Expense::any_has_account_id(db, id).await {
         Ok(false) => (),
         Ok(true) => {
@@ -61,8 +149,65 @@ pub async fn delete_account(
         Err(e) => return ApiResponse::error(e),
     };
 
+    match Reconciliation::any_has_account_id(db, id).await {
+        Ok(false) => (),
+        Ok(true) => {
+            let message = "Can't delete account with reconciled statements.";
+            return ApiResponse::bad(message);
+        }
+        Err(e) => return ApiResponse::error(e),
+    };
+
     match Account::delete(db, id).await {
         Ok(_) => ApiResponse::ok(),
         Err(e) => ApiResponse::error(e),
     }
 }
+
+#[put("/accounts/<id>/closed_date", format = "json", data = "<request>")]
+pub async fn close_account(
+    db: &State<Database>,
+    user: &User,
+    log_entry: &WriteLogEntry,
+    id: ID,
+    request: Json<AccountClosing>,
+) -> ApiResponse {
+    let closing = request.into_inner();
+    log_entry.set_content(&closing);
+
+    let role = HouseholdRole::fetch_for_account(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    if !looks_like_valid_date(&closing.closed_date) {
+        let message = format!(
+            "Incorrect date '{}', expected 'yyyy-MM-dd'",
+            closing.closed_date
+        );
+        return ApiResponse::bad(&message);
+    }
+
+    match Account::update_closed_date(db, id, Some(&closing.closed_date)).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[delete("/accounts/<id>/closed_date")]
+pub async fn reopen_account(
+    db: &State<Database>,
+    user: &User,
+    _log_entry: &WriteLogEntry,
+    id: ID,
+) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_account(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    match Account::update_closed_date(db, id, None).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
@@ -0,0 +1,45 @@
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+
+use crate::api_tokens::{ApiToken, ApiTokenFields};
+use crate::database::{Database, ID};
+use crate::guards::user::User;
+use crate::response::ApiResponse;
+
+/* Tokens can't reach these routes, see token_allows, so they're managed only after logging in.
+Like sessions, they don't go to write log, and read-only users can have read tokens. */
+
+#[get("/tokens")]
+pub async fn get_tokens(db: &State<Database>, user: &User) -> ApiResponse {
+    match ApiToken::fetch_by_username(db, &user.username).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[post("/tokens", format = "json", data = "<request>")]
+pub async fn create_token(
+    db: &State<Database>,
+    user: &User,
+    request: Json<ApiTokenFields>,
+) -> ApiResponse {
+    let fields = request.into_inner();
+
+    if let Err(message) = fields.validate() {
+        return ApiResponse::bad(&message);
+    }
+
+    match ApiToken::create(db, &user.username, &fields).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[delete("/tokens/<id>")]
+pub async fn delete_token(db: &State<Database>, user: &User, id: ID) -> ApiResponse {
+    match ApiToken::delete(db, &user.username, id).await {
+        Ok(true) => ApiResponse::ok(),
+        Ok(false) => ApiResponse::not_found(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
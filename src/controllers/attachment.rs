@@ -0,0 +1,170 @@
+use rocket::data::{ByteUnit, Data, Limits, ToByteUnit};
+use rocket::http::{ContentType, Header};
+use rocket::{delete, get, post, Responder, State};
+
+use crate::access::{can_edit, can_view};
+use crate::attachments;
+use crate::database::{Database, ID};
+use crate::guards::user::User;
+use crate::guards::write_log::WriteLogEntry;
+use crate::response::ApiResponse;
+use crate::schema::household::HouseholdRole;
+
+use crate::schema::attachment::{Attachment, AttachmentFields};
+use crate::schema::expense::Expense;
+
+// Used when Rocket.toml does not set limits.attachment
+const DEFAULT_ATTACHMENT_LIMIT_MIB: usize = 10;
+
+fn attachment_limit(limits: &Limits) -> ByteUnit {
+    limits
+        .get("attachment")
+        .unwrap_or(DEFAULT_ATTACHMENT_LIMIT_MIB.mebibytes())
+}
+
+#[derive(Responder)]
+pub struct AttachmentDownload {
+    content: (ContentType, Vec<u8>),
+    disposition: Header<'static>,
+}
+
+#[get("/expenses/<id>/attachments")]
+pub async fn get_attachments(db: &State<Database>, user: &User, id: ID) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_expense(db, &user.username, id).await;
+    if let Err(response) = can_view(role) {
+        return response;
+    }
+
+    match Attachment::fetch_by_expense_id(db, id).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+/* File is sent as the raw request body, with its type in Content-Type header and the original
+name in the query string, e.g. POST /api/expenses/12/attachments?filename=receipt.pdf */
+#[post("/expenses/<id>/attachments?<filename>", data = "<data>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_attachment(
+    db: &State<Database>,
+    user: &User,
+    log_entry: &WriteLogEntry,
+    limits: &Limits,
+    content_type: Option<&ContentType>,
+    id: ID,
+    filename: &str,
+    data: Data<'_>,
+) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_expense(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    let content_type = match content_type {
+        Some(value) => format!("{}/{}", value.top(), value.sub()),
+        None => return ApiResponse::bad("Missing Content-Type of the attachment."),
+    };
+    if !attachments::is_allowed_content_type(&content_type) {
+        let message = format!(
+            "Attachments of type '{}' are not supported, expected one of: {}.",
+            content_type,
+            attachments::ALLOWED_CONTENT_TYPES.join(", ")
+        );
+        return ApiResponse::bad(&message);
+    }
+
+    if Expense::fetch_by_id(db, id).await.is_err() {
+        let message = format!("Expense with id {} could not be found.", id);
+        return ApiResponse::bad(&message);
+    }
+
+    let limit = attachment_limit(limits);
+    let content = match data.open(limit).into_bytes().await {
+        Ok(value) => value,
+        Err(e) => return ApiResponse::error(anyhow::anyhow!(e)),
+    };
+    if !content.is_complete() {
+        let message = format!("Attachment exceeds size limit of {}.", limit);
+        return ApiResponse::bad(&message);
+    }
+    if content.is_empty() {
+        return ApiResponse::bad("Can't attach empty file.");
+    }
+
+    let sha256 = match attachments::store(&content).await {
+        Ok(value) => value,
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    let fields = AttachmentFields {
+        expense_id: id,
+        filename: attachments::sanitize_filename(filename),
+        content_type,
+        size: content.len() as i64,
+        sha256,
+    };
+    log_entry.set_content(&fields);
+
+    match Attachment::create(db, fields).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[get("/attachments/<id>")]
+pub async fn download_attachment(
+    db: &State<Database>,
+    user: &User,
+    id: ID,
+) -> Result<AttachmentDownload, ApiResponse> {
+    can_view(HouseholdRole::fetch_for_attachment(db, &user.username, id).await)?;
+
+    let attachment = match Attachment::fetch_by_id(db, id).await {
+        Ok(value) => value,
+        Err(e) => return Err(ApiResponse::error(e)),
+    };
+
+    let content = match attachments::read(&attachment.fields.sha256).await {
+        Ok(value) => value,
+        Err(e) => return Err(ApiResponse::error(e)),
+    };
+
+    let content_type =
+        ContentType::parse_flexible(&attachment.fields.content_type).unwrap_or(ContentType::Binary);
+    let disposition = Header::new(
+        "Content-Disposition",
+        format!("inline; filename=\"{}\"", attachment.fields.filename),
+    );
+
+    Ok(AttachmentDownload {
+        content: (content_type, content),
+        disposition,
+    })
+}
+
+#[delete("/attachments/<id>")]
+pub async fn delete_attachment(
+    db: &State<Database>,
+    user: &User,
+    _log_entry: &WriteLogEntry,
+    id: ID,
+) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_attachment(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    let attachment = match Attachment::fetch_by_id(db, id).await {
+        Ok(value) => value,
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    if let Err(e) = Attachment::delete(db, id).await {
+        return ApiResponse::error(e);
+    }
+
+    match attachments::remove_if_unreferenced(db, &attachment.fields.sha256).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
@@ -0,0 +1,168 @@
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, State};
+
+use crate::access::{can_edit, can_view};
+use crate::database::{Database, ID};
+use crate::guards::user::User;
+use crate::guards::write_log::WriteLogEntry;
+use crate::response::ApiResponse;
+use crate::schema::balance::{
+    is_locked, AccountBalance, BalanceGaps, OpeningBalance, Reconciliation, ReconciliationRequest,
+    ReconciliationResult,
+};
+use crate::schema::datetime::looks_like_valid_date;
+use crate::schema::household::HouseholdRole;
+
+#[get("/accounts/<id>/balance")]
+pub async fn get_balance(db: &State<Database>, user: &User, id: ID) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_account(db, &user.username, id).await;
+    if let Err(response) = can_view(role) {
+        return response;
+    }
+
+    match AccountBalance::fetch_by_account_id(db, id).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[get("/accounts/<id>/balance_gaps")]
+pub async fn get_balance_gaps(db: &State<Database>, user: &User, id: ID) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_account(db, &user.username, id).await;
+    if let Err(response) = can_view(role) {
+        return response;
+    }
+
+    match BalanceGaps::fetch_by_account_id(db, id).await {
+        Ok(Ok(value)) => ApiResponse::data(value),
+        Ok(Err(message)) => ApiResponse::bad(&message),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[put("/accounts/<id>/opening_balance", format = "json", data = "<request>")]
+pub async fn update_opening_balance(
+    db: &State<Database>,
+    user: &User,
+    log_entry: &WriteLogEntry,
+    id: ID,
+    request: Json<OpeningBalance>,
+) -> ApiResponse {
+    let opening = request.into_inner();
+    log_entry.set_content(&opening);
+
+    let role = HouseholdRole::fetch_for_account(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    if let Some(date) = &opening.opening_date {
+        if !looks_like_valid_date(date) {
+            let message = format!("Incorrect date '{}', expected 'yyyy-MM-dd'", date);
+            return ApiResponse::bad(&message);
+        }
+    }
+
+    // would silently change balances which were already reconciled
+    match Reconciliation::any_has_account_id(db, id).await {
+        Ok(false) => (),
+        Ok(true) => {
+            let message = "Can't change opening balance of account with reconciled statements.";
+            return ApiResponse::bad(message);
+        }
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    match OpeningBalance::update(db, id, opening).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[get("/accounts/<id>/reconciliations")]
+pub async fn get_reconciliations(db: &State<Database>, user: &User, id: ID) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_account(db, &user.username, id).await;
+    if let Err(response) = can_view(role) {
+        return response;
+    }
+
+    match Reconciliation::fetch_by_account_id(db, id).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+/* Compares statement closing balance with balance computed from expenses. Only when they match,
+the statement is saved as reconciled, locking all expenses up to its date. */
+#[post("/accounts/<id>/reconciliations", format = "json", data = "<request>")]
+pub async fn reconcile_statement(
+    db: &State<Database>,
+    user: &User,
+    log_entry: &WriteLogEntry,
+    id: ID,
+    request: Json<ReconciliationRequest>,
+) -> ApiResponse {
+    let request = request.into_inner();
+    log_entry.set_content(&request);
+
+    let role = HouseholdRole::fetch_for_account(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    if !looks_like_valid_date(&request.statement_date) {
+        let message = format!(
+            "Incorrect date '{}', expected 'yyyy-MM-dd'",
+            request.statement_date
+        );
+        return ApiResponse::bad(&message);
+    }
+
+    match Reconciliation::fetch_locked_until(db, id).await {
+        Ok(locked_until) if is_locked(&request.statement_date, locked_until.as_deref()) => {
+            let message = format!(
+                "Statements up to {} are already reconciled.",
+                locked_until.unwrap()
+            );
+            return ApiResponse::bad(&message);
+        }
+        Ok(_) => (),
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    let computed_balance =
+        match AccountBalance::fetch_at_date(db, id, &request.statement_date).await {
+            Ok(value) => value,
+            Err(e) => return ApiResponse::error(e),
+        };
+
+    let result = match ReconciliationResult::new(request.statement_balance, computed_balance) {
+        Ok(value) => value,
+        Err(message) => return ApiResponse::bad(&message),
+    };
+    if result.reconciled {
+        if let Err(e) = Reconciliation::create(db, id, &request, log_entry.id).await {
+            return ApiResponse::error(e);
+        }
+    }
+
+    ApiResponse::data(result)
+}
+
+#[delete("/reconciliations/<id>")]
+pub async fn delete_reconciliation(
+    db: &State<Database>,
+    user: &User,
+    _log_entry: &WriteLogEntry,
+    id: ID,
+) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_reconciliation(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    match Reconciliation::delete(db, id).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
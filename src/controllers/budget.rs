@@ -3,8 +3,12 @@ use rocket::{get, post, State};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
+use crate::access::household_for_create;
 use crate::common::TS_FILE;
-use crate::database::Database;
+use crate::database::{Database, ID};
+use crate::guards::role::{Admin, RequireRole};
+use crate::guards::user::User;
+use crate::guards::write_log::WriteLogEntry;
 use crate::response::ApiResponse;
 use crate::schema::budget::Budget;
 use crate::schema::category::BudgetCategory;
@@ -12,8 +16,8 @@ use crate::schema::item::{BudgetItem, BudgetItemWithSpend};
 use crate::schema::spending_data::SpendingDataPoint;
 
 #[get("/budget/<year>")]
-pub async fn get_budget(db: &State<Database>, year: i32) -> ApiResponse {
-    match Budget::fetch(&db, year).await {
+pub async fn get_budget(db: &State<Database>, user: &User, year: i32) -> ApiResponse {
+    match Budget::fetch(db, &user.username, year).await {
         Ok(value) => ApiResponse::data(value),
         Err(e) => ApiResponse::error(e),
     }
@@ -26,14 +30,28 @@ pub struct BudgetCloneRequest {
     to_year: i32,
 }
 
-#[post("/budget/clone", format = "json", data = "<json>")]
-pub async fn clone_budget(db: &State<Database>, json: Json<BudgetCloneRequest>) -> ApiResponse {
+// Clones budget of one household, the only one the user can edit when household_id isn't given
+#[post("/budget/clone?<household_id>", format = "json", data = "<json>")]
+pub async fn clone_budget(
+    db: &State<Database>,
+    user: &User,
+    _role: RequireRole<Admin>,
+    log_entry: &WriteLogEntry,
+    household_id: Option<ID>,
+    json: Json<BudgetCloneRequest>,
+) -> ApiResponse {
     let request = json.into_inner();
+    log_entry.set_content(&request);
 
     let from_year = request.from_year;
     let to_year = request.to_year;
 
-    match BudgetCategory::any_has_year(db, to_year).await {
+    let household_id = match household_for_create(db, &user.username, household_id).await {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
+
+    match BudgetCategory::any_has_household_id_and_year(db, household_id, to_year).await {
         Ok(false) => (),
         Ok(true) => {
             let message = format!("Target year {} already has data!", to_year);
@@ -42,7 +60,7 @@ pub async fn clone_budget(db: &State<Database>, json: Json<BudgetCloneRequest>)
         Err(e) => return ApiResponse::error(e),
     }
 
-    match Budget::clone(db, from_year, to_year).await {
+    match Budget::clone(db, household_id, from_year, to_year).await {
         Ok(_) => ApiResponse::ok(),
         Err(e) => ApiResponse::error(e),
     }
@@ -52,22 +70,29 @@ pub async fn clone_budget(db: &State<Database>, json: Json<BudgetCloneRequest>)
 #[ts(export_to = TS_FILE)]
 pub struct SpendingData {
     data: Vec<SpendingDataPoint>,
+    committed: Vec<SpendingDataPoint>, // planned expenses, not yet reconciled with actual ones
     fund_items: Vec<BudgetItemWithSpend>,
 }
 
 #[get("/spending/<year>")]
-pub async fn get_spending(db: &State<Database>, year: i32) -> ApiResponse {
-    let data = match SpendingDataPoint::fetch_by_year(db, year).await {
+pub async fn get_spending(db: &State<Database>, user: &User, year: i32) -> ApiResponse {
+    let data = match SpendingDataPoint::fetch_by_year(db, &user.username, year).await {
+        Ok(result) => result,
+        Err(e) => return ApiResponse::error(e),
+    };
+    let committed = match SpendingDataPoint::fetch_committed_by_year(db, &user.username, year).await
+    {
         Ok(result) => result,
         Err(e) => return ApiResponse::error(e),
     };
-    let fund_items = match BudgetItem::fetch_all_fund_items(db).await {
+    let fund_items = match BudgetItem::fetch_all_fund_items(db, &user.username).await {
         Ok(result) => result,
         Err(e) => return ApiResponse::error(e),
     };
 
     let result = SpendingData {
         data: data,
+        committed,
         fund_items: fund_items,
     };
 
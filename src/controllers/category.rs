@@ -1,23 +1,37 @@
 use rocket::serde::json::Json;
 use rocket::{delete, post, put, State};
 
+use crate::access::{can_edit, household_for_create};
 use crate::database::{Database, ID};
+use crate::guards::user::User;
 use crate::guards::write_log::WriteLogEntry;
 use crate::response::ApiResponse;
 use crate::schema::category::{BudgetCategory, BudgetCategoryFields};
+use crate::schema::household::HouseholdRole;
 use crate::schema::item::BudgetItem;
 
-#[post("/budget_categories/<year>", format = "json", data = "<request>")]
+#[post(
+    "/budget_categories/<year>?<household_id>",
+    format = "json",
+    data = "<request>"
+)]
 pub async fn create_budget_category(
     db: &State<Database>,
+    user: &User,
     log_entry: &WriteLogEntry,
     year: i32,
+    household_id: Option<ID>,
     request: Json<BudgetCategoryFields>,
 ) -> ApiResponse {
     let fields = request.into_inner();
     log_entry.set_content(&fields);
 
-    match BudgetCategory::create(&db, year, fields).await {
+    let household_id = match household_for_create(db, &user.username, household_id).await {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
+
+    match BudgetCategory::create(db, household_id, year, fields).await {
         Ok(_) => ApiResponse::ok(),
         Err(e) => ApiResponse::error(e),
     }
@@ -26,6 +40,7 @@ pub async fn create_budget_category(
 #[put("/budget_categories/<id>", format = "json", data = "<request>")]
 pub async fn update_budget_category(
     db: &State<Database>,
+    user: &User,
     log_entry: &WriteLogEntry,
     id: ID,
     request: Json<BudgetCategoryFields>,
@@ -33,7 +48,12 @@ pub async fn update_budget_category(
     let fields = request.into_inner();
     log_entry.set_content(&fields);
 
-    match BudgetCategory::update(&db, id, fields).await {
+    let role = HouseholdRole::fetch_for_category(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    match BudgetCategory::update(db, id, fields, log_entry.id).await {
         Ok(_) => ApiResponse::ok(),
         Err(e) => ApiResponse::error(e),
     }
@@ -42,9 +62,15 @@ pub async fn update_budget_category(
 #[delete("/budget_categories/<id>")]
 pub async fn delete_budget_category(
     db: &State<Database>,
+    user: &User,
     _log_entry: &WriteLogEntry,
     id: ID,
 ) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_category(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
     match BudgetItem::any_has_category_id(db, id).await {
         Ok(false) => (),
         Ok(true) => return ApiResponse::bad("Can't delete category that has items attached."),
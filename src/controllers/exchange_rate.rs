@@ -0,0 +1,14 @@
+use rocket::{get, State};
+
+use crate::database::Database;
+use crate::guards::user::User;
+use crate::response::ApiResponse;
+use crate::schema::currency::ExchangeRate;
+
+#[get("/exchange_rates")]
+pub async fn get_exchange_rates(db: &State<Database>, _user: &User) -> ApiResponse {
+    match ExchangeRate::fetch_all(db).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
+}
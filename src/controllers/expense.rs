@@ -1,20 +1,39 @@
 use regex::Regex;
+use rocket::futures::StreamExt;
+use rocket::http::ContentType;
+use rocket::response::stream::TextStream;
 use rocket::serde::json::Json;
 use rocket::{delete, post, State};
 use serde::{Deserialize, Serialize};
-use ts_rs::TS;
+use serde_json::json;
 
-use crate::common::TS_FILE;
+use crate::access::{can_edit, can_view};
+use crate::attachments;
 use crate::database::{Database, ID};
+use crate::guards::request_id::RequestId;
+use crate::guards::role::{Admin, RequireRole};
+use crate::guards::user::User;
 use crate::guards::write_log::WriteLogEntry;
-use crate::response::ApiResponse;
+use crate::response::{ApiResponse, Problem};
+use crate::schema::household::HouseholdRole;
 
 use crate::schema::account::{Account, AccountType};
+use crate::schema::attachment::Attachment;
+use crate::schema::balance::{is_locked, Reconciliation};
+use crate::schema::currency::{convert_to_home_currency, ExchangeRate, HOME_CURRENCY};
 use crate::schema::expense::{Expense, ExpenseCategory, ExpenseFields, ExpenseNotes};
+use crate::schema::expense_change::{
+    prepare_converted_edit, validate_expense_edit, ExpenseChange, ExpenseHistory,
+};
+use crate::schema::expense_query::ExpensesQuery;
+use crate::schema::expense_search::ExpensesSearch;
+use crate::schema::item::BudgetItem;
+use crate::schema::money::Money;
+use crate::schema::planned_expense::PlannedExpense;
 
 fn to_simple_csv_row(
     transaction_date: &str,
-    amount: i32,
+    amount: Money,
     description: &str,
 ) -> anyhow::Result<String> {
     let mut wtr = csv::WriterBuilder::new()
@@ -33,15 +52,30 @@ async fn is_cash_account(db: &Database, account_id: ID) -> anyhow::Result<bool>
     Ok(account.fields.account_type == AccountType::Cash)
 }
 
+// Expenses up to the latest reconciled statement can't be added, removed or changed
+async fn is_locked_date(db: &Database, account_id: ID, date: &str) -> anyhow::Result<bool> {
+    let locked_until = Reconciliation::fetch_locked_until(db, account_id).await?;
+
+    Ok(is_locked(date, locked_until.as_deref()))
+}
+
+const LOCKED_MESSAGE: &str = "Expense falls into already reconciled statement period.";
+
 #[post("/expenses", format = "json", data = "<json>")]
 pub async fn create_expense(
     db: &State<Database>,
+    user: &User,
     log_entry: &WriteLogEntry,
     json: Json<ExpenseFields>,
 ) -> ApiResponse {
     let mut request = json.into_inner();
     log_entry.set_content(&request);
 
+    let role = HouseholdRole::fetch_for_account(db, &user.username, request.account_id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
     match is_cash_account(&db, request.account_id).await {
         Ok(true) => (),
         Ok(false) => {
@@ -51,6 +85,12 @@ pub async fn create_expense(
         Err(e) => return ApiResponse::error(e),
     };
 
+    match is_locked_date(db, request.account_id, &request.transaction_date).await {
+        Ok(false) => (),
+        Ok(true) => return ApiResponse::bad(LOCKED_MESSAGE),
+        Err(e) => return ApiResponse::error(e),
+    };
+
     let raw_csv = match to_simple_csv_row(
         &request.transaction_date,
         request.amount,
@@ -60,8 +100,44 @@ pub async fn create_expense(
         Err(e) => return ApiResponse::error(e),
     };
     request.raw_csv = Some(raw_csv);
+    request.balance = None;
+    request.original_currency = None;
+    request.original_amount = None;
+
+    // cash in foreign currency, e.g. while travelling, is entered as paid
+    let account = match Account::fetch_by_id(db, request.account_id).await {
+        Ok(value) => value,
+        Err(e) => return ApiResponse::error(e),
+    };
+    if account.is_closed() {
+        let message = format!("Account '{}' is closed.", account.fields.name);
+        return ApiResponse::bad(&message);
+    }
 
-    match Expense::create(&db, request).await {
+    if account.fields.currency != HOME_CURRENCY {
+        let currency = &account.fields.currency;
+        match ExchangeRate::fetch_rate(db, currency, &request.transaction_date).await {
+            Ok(Some(rate)) => {
+                if let Err(message) = convert_to_home_currency(&mut request, currency, rate) {
+                    return ApiResponse::bad(&message);
+                }
+            }
+            Ok(None) => {
+                let message = format!(
+                    "No exchange rate for {} on or before {}.",
+                    currency, request.transaction_date
+                );
+                return ApiResponse::bad(&message);
+            }
+            Err(e) => return ApiResponse::error(e),
+        };
+    }
+
+    if let Err(e) = Expense::create(db, request).await {
+        return ApiResponse::error(e);
+    }
+
+    match PlannedExpense::reconcile_all(db).await {
         Ok(_) => ApiResponse::ok(),
         Err(e) => ApiResponse::error(e),
     }
@@ -70,9 +146,15 @@ pub async fn create_expense(
 #[delete("/expenses/<id>")]
 pub async fn delete_expense(
     db: &State<Database>,
+    user: &User,
     _log_entry: &WriteLogEntry,
     id: ID,
 ) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_expense(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
     // The extra validation here is to prevent manually deleting imported expenses,
     // which will later be impossible to import through normal flow if surrounded by
     // existing expenses; UI will only allow for triggering this operation for existing
@@ -91,15 +173,123 @@ pub async fn delete_expense(
         Err(e) => return ApiResponse::error(e),
     };
 
-    match Expense::delete(&db, id).await {
+    let account_id = expense.fields.account_id;
+    match is_locked_date(db, account_id, &expense.fields.transaction_date).await {
+        Ok(false) => (),
+        Ok(true) => return ApiResponse::bad(LOCKED_MESSAGE),
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    let hashes = match Attachment::fetch_sha256_by_expense_id(db, id).await {
+        Ok(value) => value,
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    if let Err(e) = Expense::delete(db, id).await {
+        return ApiResponse::error(e);
+    }
+
+    match attachments::remove_all_if_unreferenced(db, &hashes).await {
         Ok(_) => ApiResponse::ok(),
         Err(e) => ApiResponse::error(e),
     }
 }
 
+#[put("/expenses/<id>", format = "json", data = "<json>")]
+pub async fn update_expense(
+    db: &State<Database>,
+    user: &User,
+    log_entry: &WriteLogEntry,
+    id: ID,
+    json: Json<ExpenseFields>,
+) -> ApiResponse {
+    let mut request = json.into_inner();
+    log_entry.set_content(&request);
+
+    let role = HouseholdRole::fetch_for_expense(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    let expense = match Expense::fetch_by_id(db, id).await {
+        Ok(value) => value,
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    let account = match Account::fetch_by_id(db, expense.fields.account_id).await {
+        Ok(value) => value,
+        Err(e) => return ApiResponse::error(e),
+    };
+    if account.is_closed() {
+        let message = format!("Account '{}' is closed.", account.fields.name);
+        return ApiResponse::bad(&message);
+    }
+
+    if let Err(message) =
+        validate_expense_edit(&account.fields.account_type, &expense.fields, &request)
+    {
+        return ApiResponse::bad(&message);
+    }
+
+    // description can still be fixed, as it doesn't change the balance
+    let changes_balance = request.amount != expense.fields.account_amount()
+        || request.transaction_date != expense.fields.transaction_date;
+    if changes_balance {
+        for date in [&expense.fields.transaction_date, &request.transaction_date] {
+            match is_locked_date(db, account.id, date).await {
+                Ok(false) => (),
+                Ok(true) => return ApiResponse::bad(LOCKED_MESSAGE),
+                Err(e) => return ApiResponse::error(e),
+            };
+        }
+    }
+
+    request.original_currency = None;
+    request.original_amount = None;
+    if account.fields.currency != HOME_CURRENCY
+        && prepare_converted_edit(&expense.fields, &mut request)
+    {
+        let currency = &account.fields.currency;
+        match ExchangeRate::fetch_rate(db, currency, &request.transaction_date).await {
+            Ok(Some(rate)) => {
+                if let Err(message) = convert_to_home_currency(&mut request, currency, rate) {
+                    return ApiResponse::bad(&message);
+                }
+            }
+            Ok(None) => {
+                let message = format!(
+                    "No exchange rate for {} on or before {}.",
+                    currency, request.transaction_date
+                );
+                return ApiResponse::bad(&message);
+            }
+            Err(e) => return ApiResponse::error(e),
+        };
+    }
+
+    match ExpenseChange::apply(db, id, log_entry.id, &expense.fields, &request).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[get("/expenses/<id>/history")]
+pub async fn get_expense_history(db: &State<Database>, user: &User, id: ID) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_expense(db, &user.username, id).await;
+    if let Err(response) = can_view(role) {
+        return response;
+    }
+
+    match ExpenseHistory::fetch_by_expense_id(db, id).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
 #[put("/expenses/<id>/category", format = "json", data = "<json>")]
 pub async fn update_expense_category(
     db: &State<Database>,
+    user: &User,
     log_entry: &WriteLogEntry,
     id: ID,
     json: Json<ExpenseCategory>,
@@ -107,7 +297,31 @@ pub async fn update_expense_category(
     let request = json.into_inner();
     log_entry.set_content(&request);
 
-    match Expense::update_budget_item_id(&db, id, request.budget_item_id).await {
+    let role = HouseholdRole::fetch_for_expense(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    if let Some(budget_item_id) = request.budget_item_id {
+        let role = HouseholdRole::fetch_for_budget_item(db, &user.username, budget_item_id).await;
+        if let Err(response) = can_view(role) {
+            return response;
+        }
+
+        match BudgetItem::is_in_household_of_expense(db, budget_item_id, id).await {
+            Ok(true) => (),
+            Ok(false) => {
+                let message = format!(
+                    "Budget item {} belongs to another household than the expense.",
+                    budget_item_id
+                );
+                return ApiResponse::bad(&message);
+            }
+            Err(e) => return ApiResponse::error(e),
+        };
+    }
+
+    match Expense::update_budget_item_id(db, id, request.budget_item_id, log_entry.id).await {
         Ok(_) => ApiResponse::ok(),
         Err(e) => ApiResponse::error(e),
     }
@@ -116,6 +330,7 @@ pub async fn update_expense_category(
 #[put("/expenses/<id>/notes", format = "json", data = "<json>")]
 pub async fn update_expense_notes(
     db: &State<Database>,
+    user: &User,
     log_entry: &WriteLogEntry,
     id: ID,
     json: Json<ExpenseNotes>,
@@ -123,64 +338,91 @@ pub async fn update_expense_notes(
     let request = json.into_inner();
     log_entry.set_content(&request);
 
-    match Expense::update_notes(&db, id, request.notes).await {
+    let role = HouseholdRole::fetch_for_expense(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    match Expense::update_notes(db, id, request.notes, log_entry.id).await {
         Ok(_) => ApiResponse::ok(),
         Err(e) => ApiResponse::error(e),
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, TS)]
-#[serde(tag = "variant")]
-#[ts(export_to = TS_FILE, tag = "variant")]
-pub enum ExpensesQuerySelector {
-    AllNotIgnored,
-    Uncategorized,
-    Account { id: ID },
-    BudgetItem { id: ID },
-    BudgetCategory { id: ID },
-}
-
-#[derive(Debug, Deserialize, Serialize, TS)]
-#[ts(export_to = TS_FILE)]
-pub struct ExpensesQuery {
-    period: String, // expected format YYYY or YYYY-mm
-    selector: ExpensesQuerySelector,
-}
-
-fn looks_like_valid_period(period: &str) -> bool {
-    let re = Regex::new(r"^20\d\d(-(0\d|1[012]))?$").unwrap();
+#[post("/expenses/query", format = "json", data = "<json>")]
+pub async fn query_expenses(
+    db: &State<Database>,
+    user: &User,
+    json: Json<ExpensesQuery>,
+) -> ApiResponse {
+    let query = json.into_inner();
+    if let Err(message) = query.validate() {
+        return ApiResponse::bad(&message);
+    }
 
-    re.is_match(period)
+    match query.fetch(db, &user.username).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
 }
 
-#[post("/expenses/query", format = "json", data = "<json>")]
-pub async fn query_expenses(db: &State<Database>, json: Json<ExpensesQuery>) -> ApiResponse {
+// Same as query_expenses, but ignores the page and streams all expenses as newline delimited
+// JSON, one expense per line, without having to keep them all in memory.
+#[post("/expenses/query/stream", format = "json", data = "<json>")]
+pub async fn stream_expenses(
+    db: &State<Database>,
+    user: &User,
+    request_id: RequestId,
+    json: Json<ExpensesQuery>,
+) -> Result<(ContentType, TextStream![String]), ApiResponse> {
     let query = json.into_inner();
-    if !looks_like_valid_period(&query.period) {
-        let message = format!("Incorrect period: '{}'. Expected 'YYYY[-mm]'", query.period);
-        return ApiResponse::bad(&message);
+    if let Err(message) = query.validate() {
+        return Err(ApiResponse::bad(&message));
     }
 
-    let period = query.period;
-    let expenses = match query.selector {
-        ExpensesQuerySelector::AllNotIgnored => {
-            Expense::fetch_all_not_ignored_by_period(&db, period).await
-        }
-        ExpensesQuerySelector::Uncategorized => {
-            Expense::fetch_uncategorized_by_period(&db, period).await
-        }
-        ExpensesQuerySelector::Account { id } => {
-            Expense::fetch_by_account_id_and_period(&db, id, period).await
-        }
-        ExpensesQuerySelector::BudgetItem { id } => {
-            Expense::fetch_by_budget_item_id_and_period(&db, id, period).await
-        }
-        ExpensesQuerySelector::BudgetCategory { id } => {
-            Expense::fetch_by_budget_category_id_and_period(&db, id, period).await
+    let mut conn = match db.acquire_db_conn().await {
+        Ok(value) => value,
+        Err(e) => return Err(ApiResponse::error(e)),
+    };
+
+    let username = user.username.clone();
+    let stream = TextStream! {
+        let mut builder = query.build_select(&username);
+        let mut rows = builder.build_query_as::<Expense>().fetch(&mut *conn);
+
+        while let Some(row) = rows.next().await {
+            let line = match row.map_err(anyhow::Error::from) {
+                Ok(expense) => serde_json::to_string(&expense).map_err(anyhow::Error::from),
+                Err(e) => Err(e),
+            };
+
+            match line {
+                Ok(value) => yield format!("{}\n", value),
+                Err(e) => {
+                    // headers are already sent, so the error can only be reported in the body
+                    let problem = Problem::internal(&request_id.0, &e);
+                    yield format!("{}\n", json!(problem));
+                    break;
+                }
+            }
         }
     };
 
-    match expenses {
+    Ok((ContentType::new("application", "x-ndjson"), stream))
+}
+
+#[post("/expenses/search", format = "json", data = "<json>")]
+pub async fn search_expenses(
+    db: &State<Database>,
+    user: &User,
+    json: Json<ExpensesSearch>,
+) -> ApiResponse {
+    let search = json.into_inner();
+    if let Err(message) = search.validate() {
+        return ApiResponse::bad(&message);
+    }
+
+    match search.fetch(db, &user.username).await {
         Ok(value) => ApiResponse::data(value),
         Err(e) => ApiResponse::error(e),
     }
@@ -195,12 +437,19 @@ pub struct DeleteExpensesRequest {
 #[post("/expenses/bulk_delete", format = "json", data = "<json>")]
 pub async fn delete_expenses(
     db: &State<Database>,
+    user: &User,
+    _role: RequireRole<Admin>,
     log_entry: &WriteLogEntry,
     json: Json<DeleteExpensesRequest>,
 ) -> ApiResponse {
     let request = json.into_inner();
     log_entry.set_content(&request);
 
+    let role = HouseholdRole::fetch_for_account(db, &user.username, request.account_id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
     let date = request.newer_than_date;
     let re = Regex::new(r"^20\d\d-[01]\d-[0123]\d$").unwrap();
     if !re.is_match(&date) {
@@ -208,7 +457,34 @@ pub async fn delete_expenses(
         return ApiResponse::bad(&message);
     }
 
-    match Expense::delete_by_account_id_and_date(&db, request.account_id, &date).await {
+    match Reconciliation::fetch_locked_until(db, request.account_id).await {
+        Ok(Some(locked_until)) if locked_until > date => {
+            let message = format!(
+                "Expenses up to {} are reconciled and can't be deleted.",
+                locked_until
+            );
+            return ApiResponse::bad(&message);
+        }
+        Ok(_) => (),
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    let hashes = match Attachment::fetch_sha256_by_account_id_and_date(
+        db,
+        request.account_id,
+        &date,
+    )
+    .await
+    {
+        Ok(value) => value,
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    if let Err(e) = Expense::delete_by_account_id_and_date(db, request.account_id, &date).await {
+        return ApiResponse::error(e);
+    }
+
+    match attachments::remove_all_if_unreferenced(db, &hashes).await {
         Ok(_) => ApiResponse::ok(),
         Err(e) => ApiResponse::error(e),
     }
@@ -3,11 +3,14 @@ use rocket::{delete, post, put, State};
 use serde::Serialize;
 use ts_rs::TS;
 
+use crate::access::{can_edit, household_for_create};
 use crate::common::TS_FILE;
 use crate::database::{Database, ID};
+use crate::guards::user::User;
 use crate::guards::write_log::WriteLogEntry;
 use crate::response::ApiResponse;
 use crate::schema::fund::{Fund, FundFields};
+use crate::schema::household::HouseholdRole;
 use crate::schema::item::{BudgetItem, BudgetItemWithSpend};
 
 #[derive(Debug, Serialize, TS)]
@@ -23,8 +26,8 @@ pub struct FundItems {
 }
 
 #[get("/funds")]
-pub async fn get_funds(db: &State<Database>) -> ApiResponse {
-    let funds = match Fund::fetch_all(db).await {
+pub async fn get_funds(db: &State<Database>, user: &User) -> ApiResponse {
+    let funds = match Fund::fetch_all(db, &user.username).await {
         Ok(result) => result,
         Err(e) => return ApiResponse::error(e),
     };
@@ -35,8 +38,8 @@ pub async fn get_funds(db: &State<Database>) -> ApiResponse {
 }
 
 #[get("/funds/items")]
-pub async fn get_items(db: &State<Database>) -> ApiResponse {
-    let items = match BudgetItem::fetch_all_fund_items(db).await {
+pub async fn get_items(db: &State<Database>, user: &User) -> ApiResponse {
+    let items = match BudgetItem::fetch_all_fund_items(db, &user.username).await {
         Ok(result) => result,
         Err(e) => return ApiResponse::error(e),
     };
@@ -46,16 +49,23 @@ pub async fn get_items(db: &State<Database>) -> ApiResponse {
     ApiResponse::data(result)
 }
 
-#[post("/funds", format = "json", data = "<request>")]
+#[post("/funds?<household_id>", format = "json", data = "<request>")]
 pub async fn create_fund(
     db: &State<Database>,
+    user: &User,
     log_entry: &WriteLogEntry,
+    household_id: Option<ID>,
     request: Json<FundFields>,
 ) -> ApiResponse {
     let fields = request.into_inner();
     log_entry.set_content(&fields);
 
-    match Fund::create(&db, fields).await {
+    let household_id = match household_for_create(db, &user.username, household_id).await {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
+
+    match Fund::create(db, household_id, fields).await {
         Ok(_) => ApiResponse::ok(),
         Err(e) => ApiResponse::error(e),
     }
@@ -64,6 +74,7 @@ pub async fn create_fund(
 #[put("/funds/<id>", format = "json", data = "<request>")]
 pub async fn update_fund(
     db: &State<Database>,
+    user: &User,
     log_entry: &WriteLogEntry,
     id: ID,
     request: Json<FundFields>,
@@ -71,14 +82,29 @@ pub async fn update_fund(
     let fields = request.into_inner();
     log_entry.set_content(&fields);
 
-    match Fund::update(&db, id, fields).await {
+    let role = HouseholdRole::fetch_for_fund(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    match Fund::update(db, id, fields, log_entry.id).await {
         Ok(_) => ApiResponse::ok(),
         Err(e) => ApiResponse::error(e),
     }
 }
 
 #[delete("/funds/<id>")]
-pub async fn delete_fund(db: &State<Database>, _log_entry: &WriteLogEntry, id: ID) -> ApiResponse {
+pub async fn delete_fund(
+    db: &State<Database>,
+    user: &User,
+    _log_entry: &WriteLogEntry,
+    id: ID,
+) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_fund(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
     match BudgetItem::any_has_fund_id(db, id).await {
         Ok(false) => (),
         Ok(true) => return ApiResponse::bad("Can't delete fund that has items attached."),
@@ -0,0 +1,155 @@
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, State};
+
+use crate::access::{can_manage, can_view};
+use crate::credentials::Credentials;
+use crate::database::{Database, ID};
+use crate::guards::user::User;
+use crate::guards::write_log::WriteLogEntry;
+use crate::response::ApiResponse;
+use crate::schema::household::{
+    Household, HouseholdFields, HouseholdMember, HouseholdMembers, HouseholdRole,
+};
+
+// Household can't be left without anyone to manage it
+fn is_last_owner(members: &HouseholdMembers, username: &str) -> bool {
+    members
+        .members
+        .iter()
+        .all(|member| member.username == username || member.role != HouseholdRole::Owner)
+}
+
+#[get("/households")]
+pub async fn get_households(db: &State<Database>, user: &User) -> ApiResponse {
+    match Household::fetch_by_username(db, &user.username).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[post("/households", format = "json", data = "<request>")]
+pub async fn create_household(
+    db: &State<Database>,
+    user: &User,
+    log_entry: &WriteLogEntry,
+    request: Json<HouseholdFields>,
+) -> ApiResponse {
+    let fields = request.into_inner();
+    log_entry.set_content(&fields);
+
+    if fields.name.trim().is_empty() {
+        return ApiResponse::bad("Household name can't be empty.");
+    }
+
+    match Household::create(db, &user.username, fields).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[put("/households/<id>", format = "json", data = "<request>")]
+pub async fn update_household(
+    db: &State<Database>,
+    user: &User,
+    log_entry: &WriteLogEntry,
+    id: ID,
+    request: Json<HouseholdFields>,
+) -> ApiResponse {
+    let fields = request.into_inner();
+    log_entry.set_content(&fields);
+
+    let role = HouseholdRole::fetch_for_household(db, &user.username, id).await;
+    if let Err(response) = can_manage(role) {
+        return response;
+    }
+
+    if fields.name.trim().is_empty() {
+        return ApiResponse::bad("Household name can't be empty.");
+    }
+
+    match Household::update(db, id, fields).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[get("/households/<id>/members")]
+pub async fn get_household_members(db: &State<Database>, user: &User, id: ID) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_household(db, &user.username, id).await;
+    if let Err(response) = can_view(role) {
+        return response;
+    }
+
+    match HouseholdMember::fetch_by_household_id(db, id).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[put("/households/<id>/members", format = "json", data = "<request>")]
+pub async fn update_household_member(
+    db: &State<Database>,
+    user: &User,
+    log_entry: &WriteLogEntry,
+    id: ID,
+    request: Json<HouseholdMember>,
+) -> ApiResponse {
+    let member = request.into_inner();
+    log_entry.set_content(&member);
+
+    let role = HouseholdRole::fetch_for_household(db, &user.username, id).await;
+    if let Err(response) = can_manage(role) {
+        return response;
+    }
+
+    match Credentials::fetch_by_username(db, &member.username).await {
+        Ok(Some(_)) => (),
+        Ok(None) => {
+            let message = format!("User '{}' could not be found.", member.username);
+            return ApiResponse::bad(&message);
+        }
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    let members = match HouseholdMember::fetch_by_household_id(db, id).await {
+        Ok(value) => value,
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    if member.role != HouseholdRole::Owner && is_last_owner(&members, &member.username) {
+        return ApiResponse::bad("Household needs at least one owner.");
+    }
+
+    match HouseholdMember::upsert(db, id, &member).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[delete("/households/<id>/members/<username>")]
+pub async fn delete_household_member(
+    db: &State<Database>,
+    user: &User,
+    _log_entry: &WriteLogEntry,
+    id: ID,
+    username: &str,
+) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_household(db, &user.username, id).await;
+    if let Err(response) = can_manage(role) {
+        return response;
+    }
+
+    let members = match HouseholdMember::fetch_by_household_id(db, id).await {
+        Ok(value) => value,
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    if is_last_owner(&members, username) {
+        return ApiResponse::bad("Household needs at least one owner.");
+    }
+
+    match HouseholdMember::delete(db, id, username).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
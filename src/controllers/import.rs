@@ -1,12 +1,18 @@
 use rocket::form::{Form, FromForm};
 use rocket::fs::TempFile;
 use rocket::{post, State};
+use std::time::Instant;
 use tokio::fs::remove_file;
+use tracing::info;
 
+use crate::access::can_edit;
 use crate::database::{Database, ID};
+use crate::guards::user::User;
 use crate::guards::write_log::WriteLogEntry;
-use crate::import::{read_expenses, save_expenses, STATEMENT_UPLOAD_PATH};
+use crate::import::{read_expenses, save_expenses, ImportError, STATEMENT_UPLOAD_PATH};
+use crate::metrics::{ImportOutcome, Metrics};
 use crate::response::ApiResponse;
+use crate::schema::household::HouseholdRole;
 
 use crate::schema::account::Account;
 use crate::schema::statement_schema::StatementSchema;
@@ -20,6 +26,8 @@ pub struct UploadStatementForm<'f> {
 #[post("/expenses/import", data = "<form>")]
 pub async fn import_expenses(
     db: &State<Database>,
+    metrics: &State<Metrics>,
+    user: &User,
     log_entry: &WriteLogEntry,
     mut form: Form<UploadStatementForm<'_>>,
 ) -> ApiResponse {
@@ -30,6 +38,11 @@ pub async fn import_expenses(
     }
 
     let account_id = form.account_id;
+    let role = HouseholdRole::fetch_for_account(db, &user.username, account_id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
     let account = match Account::fetch_by_id(db, account_id).await {
         Ok(value) => value,
         Err(_) => {
@@ -38,6 +51,11 @@ pub async fn import_expenses(
         }
     };
 
+    if account.is_closed() {
+        let message = format!("Account '{}' is closed.", account.fields.name);
+        return ApiResponse::bad(&message);
+    }
+
     let statement_schema_id = match account.fields.statement_schema_id {
         Some(value) => value,
         None => {
@@ -62,6 +80,7 @@ pub async fn import_expenses(
         return ApiResponse::error(anyhow::anyhow!(e));
     };
 
+    let started = Instant::now();
     let expenses_or_import_error = read_expenses(
         account_id,
         String::from(STATEMENT_UPLOAD_PATH),
@@ -75,11 +94,35 @@ pub async fn import_expenses(
     // ImportError is always user error and contains a message to display in UI
     let expenses = match expenses_or_import_error {
         Ok(value) => value,
-        Err(e) => return ApiResponse::bad(&e.message),
+        Err(e) => {
+            metrics.record_import(ImportOutcome::Rejected, started.elapsed(), 0);
+            return ApiResponse::import_error(&e.message);
+        }
     };
 
+    let rows = expenses.len();
     match save_expenses(account_id, expenses, &db).await {
-        Ok(_) => ApiResponse::ok(),
-        Err(e) => ApiResponse::error(e),
+        Ok(summary) => {
+            let duration = started.elapsed();
+            metrics.record_import(ImportOutcome::Imported, duration, summary.imported);
+            info!(
+                account_id,
+                rows,
+                imported = summary.imported,
+                duration_ms = duration.as_millis() as u64,
+                "statement imported"
+            );
+            ApiResponse::data(summary)
+        }
+        Err(e) => match e.downcast_ref::<ImportError>() {
+            Some(import_error) => {
+                metrics.record_import(ImportOutcome::Rejected, started.elapsed(), 0);
+                ApiResponse::import_error(&import_error.message)
+            }
+            None => {
+                metrics.record_import(ImportOutcome::Failed, started.elapsed(), 0);
+                ApiResponse::error(e)
+            }
+        },
     }
 }
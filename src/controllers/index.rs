@@ -1,5 +1,5 @@
 use rocket::fs::NamedFile;
-use rocket::get;
+use rocket::{catch, get};
 
 use crate::response::ApiResponse;
 
@@ -8,7 +8,38 @@ pub async fn index() -> Result<NamedFile, std::io::Error> {
     NamedFile::open("www/index.html").await
 }
 
-#[get("/404")]
+/* Catchers answer requests no route took, so that api clients get problem+json for these too
+instead of Rocket's html pages. Body that can't be parsed, or parameters of wrong type, end up as
+422 in Rocket, it's reported as 400 validation error like other invalid input. */
+
+#[catch(400)]
+pub async fn bad_request() -> ApiResponse {
+    ApiResponse::bad("Request is malformed.")
+}
+
+#[catch(401)]
+pub async fn unauthorized_catcher() -> ApiResponse {
+    ApiResponse::unauthorized("Not logged in.")
+}
+
+#[catch(403)]
+pub async fn forbidden() -> ApiResponse {
+    ApiResponse::forbidden("User role or token scope doesn't allow this.")
+}
+
+#[catch(404)]
 pub async fn not_found() -> ApiResponse {
     ApiResponse::not_found()
 }
+
+#[catch(422)]
+pub async fn unprocessable() -> ApiResponse {
+    ApiResponse::bad("Request body or parameters are invalid.")
+}
+
+#[catch(500)]
+pub async fn internal_error() -> ApiResponse {
+    ApiResponse::error(anyhow::anyhow!(
+        "request failed before reaching the handler"
+    ))
+}
@@ -1,21 +1,44 @@
 use rocket::serde::json::Json;
 use rocket::{delete, post, put, State};
 
+use crate::access::can_edit;
 use crate::database::{Database, ID};
+use crate::guards::user::User;
 use crate::guards::write_log::WriteLogEntry;
 use crate::response::ApiResponse;
 use crate::schema::expense::Expense;
+use crate::schema::household::HouseholdRole;
 use crate::schema::item::{BudgetItem, BudgetItemFields};
+use crate::schema::planned_expense::PlannedExpense;
+
+// Item can only be moved to category and fund the user can edit as well
+async fn can_edit_item_fields(
+    db: &Database,
+    username: &str,
+    fields: &BudgetItemFields,
+) -> Result<(), ApiResponse> {
+    can_edit(HouseholdRole::fetch_for_category(db, username, fields.category_id).await)?;
+    if let Some(fund_id) = fields.fund_id {
+        can_edit(HouseholdRole::fetch_for_fund(db, username, fund_id).await)?;
+    }
+
+    Ok(())
+}
 
 #[post("/budget_items", format = "json", data = "<request>")]
 pub async fn create_budget_item(
     db: &State<Database>,
+    user: &User,
     log_entry: &WriteLogEntry,
     request: Json<BudgetItemFields>,
 ) -> ApiResponse {
     let fields = request.into_inner();
     log_entry.set_content(&fields);
 
+    if let Err(response) = can_edit_item_fields(db, &user.username, &fields).await {
+        return response;
+    }
+
     match BudgetItem::create(db, fields).await {
         Ok(_) => ApiResponse::ok(),
         Err(e) => ApiResponse::error(e),
@@ -25,6 +48,7 @@ pub async fn create_budget_item(
 #[put("/budget_items/<id>", format = "json", data = "<request>")]
 pub async fn update_budget_item(
     db: &State<Database>,
+    user: &User,
     log_entry: &WriteLogEntry,
     id: ID,
     request: Json<BudgetItemFields>,
@@ -32,6 +56,11 @@ pub async fn update_budget_item(
     let fields = request.into_inner();
     log_entry.set_content(&fields);
 
+    let role = HouseholdRole::fetch_for_budget_item(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
     if fields.budget_only {
         match Expense::any_has_budget_item_id(db, id).await {
             Ok(false) => (),
@@ -43,7 +72,11 @@ pub async fn update_budget_item(
         };
     }
 
-    match BudgetItem::update(db, id, fields).await {
+    if let Err(response) = can_edit_item_fields(db, &user.username, &fields).await {
+        return response;
+    }
+
+    match BudgetItem::update(db, id, fields, log_entry.id).await {
         Ok(_) => ApiResponse::ok(),
         Err(e) => ApiResponse::error(e),
     }
@@ -52,9 +85,15 @@ pub async fn update_budget_item(
 #[delete("/budget_items/<id>")]
 pub async fn delete_budget_item(
     db: &State<Database>,
+    user: &User,
     _log_entry: &WriteLogEntry,
     id: ID,
 ) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_budget_item(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
     match Expense::any_has_budget_item_id(db, id).await {
         Ok(false) => (),
         Ok(true) => {
@@ -64,6 +103,15 @@ pub async fn delete_budget_item(
         Err(e) => return ApiResponse::error(e),
     };
 
+    match PlannedExpense::any_has_budget_item_id(db, id).await {
+        Ok(false) => (),
+        Ok(true) => {
+            let message = "Can't delete budget item with planned expenses.";
+            return ApiResponse::bad(message);
+        }
+        Err(e) => return ApiResponse::error(e),
+    };
+
     match BudgetItem::delete(db, id).await {
         Ok(_) => ApiResponse::ok(),
         Err(e) => ApiResponse::error(e),
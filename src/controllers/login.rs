@@ -1,19 +1,135 @@
 use rocket::form::{Form, FromForm};
 use rocket::http::{Cookie, CookieJar};
+use rocket::time::Duration;
 use rocket::{post, State};
+use serde::Serialize;
+use ts_rs::TS;
 
+use crate::auth_log::{
+    fetch_login_wait_ms, record_auth_event, AuthEvent, LoginAttempt, LoginAttempts,
+};
+use crate::common::{now, TS_FILE};
 use crate::credentials::Credentials;
-use crate::crypto::verify_password;
+use crate::crypto::{hash_password, needs_rehash, verify_password, PwhashParams};
 use crate::database::Database;
+use crate::guards::client::ClientInfo;
 use crate::guards::user::User;
 use crate::response::ApiResponse;
+use crate::sessions::{Session, ABSOLUTE_EXPIRY_MS, SESSION_COOKIE};
+use crate::totp::TotpState;
+
+/* Users with TOTP log in in two steps. Correct password gives just a pending login cookie, which
+is exchanged for a session together with a code from the authenticator app, or a recovery code.
+Both steps are throttled and recorded in auth log, see auth_log.rs. */
+
+const PENDING_LOGIN_COOKIE: &str = "pending_login";
+const PENDING_LOGIN_MS: i64 = 5 * 60 * 1000;
+
+const CONCURRENT_WAIT_MS: i64 = 1000;
+
+// Same for unknown user, wrong password or code, not to reveal which users exist
+const WRONG_CREDENTIALS: &str = "Wrong username, password or code.";
+
+#[derive(Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct TotpRequired {
+    totp_required: bool,
+}
+
+// Cookie value is "<timestamp>:<username>", it's private so the client can't forge it
+fn parse_pending_login(value: &str, now: i64) -> Option<String> {
+    let (ts, username) = value.split_once(':')?;
+    let ts: i64 = ts.parse().ok()?;
+
+    if now - ts > PENDING_LOGIN_MS {
+        return None;
+    }
+
+    Some(username.to_string())
+}
+
+fn throttled(wait_ms: i64) -> ApiResponse {
+    let seconds = (wait_ms + 999) / 1000;
+    let message = format!("Too many failed logins, try again in {} seconds.", seconds);
+
+    ApiResponse::too_many_requests(&message)
+}
+
+/* Rejects the attempt before any credentials are checked, when there were too many failures, or
+another attempt of the username or IP is still running. Returned attempt has to be kept until
+its failure or success is recorded. */
+async fn check_throttle<'a>(
+    db: &Database,
+    attempts: &'a LoginAttempts,
+    username: &str,
+    client: &ClientInfo,
+) -> Result<LoginAttempt<'a>, ApiResponse> {
+    let wait_ms = match attempts.begin(username, client) {
+        Some(attempt) => match fetch_login_wait_ms(db, username, client).await {
+            Ok(None) => return Ok(attempt),
+            Ok(Some(value)) => value,
+            Err(e) => return Err(ApiResponse::error(e)),
+        },
+        // checking the password of the running attempt takes about a second
+        None => CONCURRENT_WAIT_MS,
+    };
+
+    match record_auth_event(db, AuthEvent::Throttled, username, client).await {
+        Ok(_) => Err(throttled(wait_ms)),
+        Err(e) => Err(ApiResponse::error(e)),
+    }
+}
+
+async fn reject(
+    db: &Database,
+    event: AuthEvent,
+    username: &str,
+    client: &ClientInfo,
+) -> ApiResponse {
+    match record_auth_event(db, event, username, client).await {
+        Ok(_) => ApiResponse::unauthorized(WRONG_CREDENTIALS),
+        Err(e) => ApiResponse::error(e),
+    }
+}
 
 #[get("/me")]
-pub async fn me(user: Option<&User>) -> ApiResponse {
-    match user {
-        Some(user) => ApiResponse::data(user),
-        None => ApiResponse::not_found(),
+pub async fn me(user: &User) -> ApiResponse {
+    ApiResponse::data(user)
+}
+
+async fn start_session(
+    cookies: &CookieJar<'_>,
+    db: &Database,
+    client: &ClientInfo,
+    creds: Credentials,
+) -> ApiResponse {
+    if let Err(e) = record_auth_event(db, AuthEvent::LoginSucceeded, &creds.username, client).await
+    {
+        return ApiResponse::error(e);
     }
+
+    // good enough time to clean up, logins are rare
+    if let Err(e) = Session::delete_expired(db).await {
+        return ApiResponse::error(e);
+    }
+
+    let (session_id, token) = match Session::create(db, &creds.username, client).await {
+        Ok(value) => value,
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    let cookie =
+        Cookie::build((SESSION_COOKIE, token)).max_age(Duration::milliseconds(ABSOLUTE_EXPIRY_MS));
+    cookies.add_private(cookie);
+
+    let user = User {
+        username: creds.username,
+        role: creds.role,
+        session_id: Some(session_id),
+        api_token_id: None,
+    };
+
+    ApiResponse::data(user)
 }
 
 #[derive(FromForm)]
@@ -26,30 +142,168 @@ pub struct LoginForm {
 pub async fn login(
     cookies: &CookieJar<'_>,
     db: &State<Database>,
+    attempts: &State<LoginAttempts>,
+    client: ClientInfo,
     form: Form<LoginForm>,
 ) -> ApiResponse {
-    let creds = match Credentials::fetch_by_username(db, &form.username).await {
+    let _attempt = match check_throttle(db, attempts, &form.username, &client).await {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
+
+    let mut creds = match Credentials::fetch_by_username(db, &form.username).await {
         Ok(Some(value)) => value,
-        _ => return ApiResponse::not_found(),
+        Ok(None) => return reject(db, AuthEvent::PasswordFailed, &form.username, &client).await,
+        Err(e) => return ApiResponse::error(e),
     };
 
     if verify_password(&creds.pwhash, &form.password).is_err() {
-        return ApiResponse::not_found();
+        return reject(db, AuthEvent::PasswordFailed, &form.username, &client).await;
     }
 
-    let cookie = Cookie::new("user", creds.username.clone());
-    cookies.add_private(cookie);
+    // only now we know the password, to upgrade hash made with outdated parameters
+    let params = match PwhashParams::from_env() {
+        Ok(value) => value,
+        Err(e) => return ApiResponse::error(anyhow::anyhow!(e)),
+    };
+    if needs_rehash(&creds.pwhash, &params) {
+        creds.pwhash = match hash_password(&form.password, &params) {
+            Ok(value) => value,
+            Err(_) => return ApiResponse::error(anyhow::anyhow!("Hashing password failed.")),
+        };
 
-    let user = User {
-        username: creds.username,
+        if let Err(e) = creds.update(db).await {
+            return ApiResponse::error(e);
+        }
+    }
+
+    match TotpState::fetch_by_username(db, &creds.username).await {
+        Ok(None) => (),
+        Ok(Some(_)) => {
+            let value = format!("{}:{}", now(), creds.username);
+            let cookie = Cookie::build((PENDING_LOGIN_COOKIE, value))
+                .max_age(Duration::milliseconds(PENDING_LOGIN_MS));
+            cookies.add_private(cookie);
+
+            return ApiResponse::data(TotpRequired {
+                totp_required: true,
+            });
+        }
+        Err(e) => return ApiResponse::error(e),
     };
 
-    ApiResponse::data(user)
+    start_session(cookies, db, &client, creds).await
+}
+
+#[derive(FromForm)]
+pub struct TotpForm {
+    code: String,
+}
+
+#[post("/login/totp", data = "<form>")]
+pub async fn login_totp(
+    cookies: &CookieJar<'_>,
+    db: &State<Database>,
+    attempts: &State<LoginAttempts>,
+    client: ClientInfo,
+    form: Form<TotpForm>,
+) -> ApiResponse {
+    let ts = now();
+
+    let username = match cookies
+        .get_private(PENDING_LOGIN_COOKIE)
+        .and_then(|cookie| parse_pending_login(cookie.value(), ts))
+    {
+        Some(value) => value,
+        None => return ApiResponse::unauthorized("Login expired, enter the password again."),
+    };
+
+    let _attempt = match check_throttle(db, attempts, &username, &client).await {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
+
+    let state = match TotpState::fetch_by_username(db, &username).await {
+        Ok(Some(value)) => value,
+        Ok(None) => return ApiResponse::unauthorized(WRONG_CREDENTIALS),
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    match state.claim_attempt(db, ts).await {
+        Ok(true) => (),
+        Ok(false) => {
+            return ApiResponse::too_many_requests("Too many wrong codes, try again later.")
+        }
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    let code = form.code.trim();
+    let step = state.matching_step(code, ts);
+    let accepted = match step {
+        Some(_) => true,
+        None => match state.use_recovery_code(db, code, ts).await {
+            Ok(value) => value,
+            Err(e) => return ApiResponse::error(e),
+        },
+    };
+
+    if !accepted {
+        return reject(db, AuthEvent::CodeFailed, &username, &client).await;
+    }
+
+    match state.record_success(db, step).await {
+        Ok(true) => (),
+        Ok(false) => return reject(db, AuthEvent::CodeFailed, &username, &client).await,
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    let creds = match Credentials::fetch_by_username(db, &username).await {
+        Ok(Some(value)) => value,
+        Ok(None) => return ApiResponse::unauthorized(WRONG_CREDENTIALS),
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    cookies.remove_private(PENDING_LOGIN_COOKIE);
+    start_session(cookies, db, &client, creds).await
 }
 
 #[get("/logout")]
-pub async fn logout(cookies: &CookieJar<'_>) -> ApiResponse {
-    cookies.remove_private("user");
+pub async fn logout(
+    cookies: &CookieJar<'_>,
+    db: &State<Database>,
+    user: Option<&User>,
+) -> ApiResponse {
+    cookies.remove_private(SESSION_COOKIE);
+
+    if let Some(user) = user {
+        if let Some(session_id) = user.session_id {
+            if let Err(e) = Session::delete(db, &user.username, session_id).await {
+                return ApiResponse::error(e);
+            }
+        }
+    }
 
     ApiResponse::ok()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pending_login() {
+        let ts = 1_000_000_000_000;
+        let value = format!("{}:tester", ts);
+        assert_eq!(
+            parse_pending_login(&value, ts),
+            Some(String::from("tester"))
+        );
+        assert_eq!(
+            parse_pending_login(&value, ts + PENDING_LOGIN_MS),
+            Some(String::from("tester"))
+        );
+        assert_eq!(parse_pending_login(&value, ts + PENDING_LOGIN_MS + 1), None);
+        assert_eq!(parse_pending_login("tester", ts), None);
+        assert_eq!(parse_pending_login("abc:tester", ts), None);
+    }
+}
@@ -0,0 +1,17 @@
+use rocket::http::ContentType;
+use rocket::{get, State};
+
+use crate::database::Database;
+use crate::guards::metrics_token::MetricsAccess;
+use crate::metrics::Metrics;
+
+#[get("/metrics")]
+pub async fn get_metrics(
+    db: &State<Database>,
+    metrics: &State<Metrics>,
+    _access: MetricsAccess,
+) -> (ContentType, String) {
+    let content_type = ContentType::new("text", "plain").with_params(("version", "0.0.4"));
+
+    (content_type, metrics.render(&db.pool_stats()))
+}
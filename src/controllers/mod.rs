@@ -1,10 +1,22 @@
 pub mod account;
+pub mod api_token;
+pub mod attachment;
+pub mod balance;
 pub mod budget;
 pub mod category;
+pub mod exchange_rate;
 pub mod expense;
 pub mod fund;
+pub mod household;
 pub mod import;
 pub mod index;
 pub mod item;
 pub mod login;
+pub mod metrics;
+pub mod planned_expense;
+pub mod recurring;
+pub mod reimbursement;
+pub mod session;
 pub mod statement_schema;
+pub mod tag;
+pub mod write_log;
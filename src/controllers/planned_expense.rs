@@ -0,0 +1,140 @@
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, State};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::access::{can_edit, can_view};
+use crate::common::TS_FILE;
+use crate::database::{Database, ID};
+use crate::guards::user::User;
+use crate::guards::write_log::WriteLogEntry;
+use crate::response::ApiResponse;
+use crate::schema::household::HouseholdRole;
+use crate::schema::planned_expense::{
+    validate_planned_expense, PlannedExpense, PlannedExpenseFields,
+};
+
+#[get("/planned_expenses/<year>")]
+pub async fn get_planned_expenses(db: &State<Database>, user: &User, year: i32) -> ApiResponse {
+    match PlannedExpense::fetch_by_year(db, &user.username, year).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[post("/planned_expenses", format = "json", data = "<request>")]
+pub async fn create_planned_expense(
+    db: &State<Database>,
+    user: &User,
+    log_entry: &WriteLogEntry,
+    request: Json<PlannedExpenseFields>,
+) -> ApiResponse {
+    let fields = request.into_inner();
+    log_entry.set_content(&fields);
+
+    let role =
+        HouseholdRole::fetch_for_budget_item(db, &user.username, fields.budget_item_id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    if let Err(message) = validate_planned_expense(&fields) {
+        return ApiResponse::bad(&message);
+    }
+
+    if let Err(e) = PlannedExpense::create(db, fields).await {
+        return ApiResponse::error(e);
+    }
+
+    // expense for it might have been imported already
+    match PlannedExpense::reconcile_all(db).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[put("/planned_expenses/<id>", format = "json", data = "<request>")]
+pub async fn update_planned_expense(
+    db: &State<Database>,
+    user: &User,
+    log_entry: &WriteLogEntry,
+    id: ID,
+    request: Json<PlannedExpenseFields>,
+) -> ApiResponse {
+    let fields = request.into_inner();
+    log_entry.set_content(&fields);
+
+    let role = HouseholdRole::fetch_for_planned_expense(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    if let Err(message) = validate_planned_expense(&fields) {
+        return ApiResponse::bad(&message);
+    }
+
+    let role =
+        HouseholdRole::fetch_for_budget_item(db, &user.username, fields.budget_item_id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    match PlannedExpense::update(db, id, fields).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[delete("/planned_expenses/<id>")]
+pub async fn delete_planned_expense(
+    db: &State<Database>,
+    user: &User,
+    _log_entry: &WriteLogEntry,
+    id: ID,
+) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_planned_expense(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    match PlannedExpense::delete(db, id).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct PlannedExpenseLink {
+    expense_id: Option<ID>,
+}
+
+// For when automatic reconciliation picked the wrong expense, or none at all
+#[put("/planned_expenses/<id>/expense", format = "json", data = "<request>")]
+pub async fn update_planned_expense_link(
+    db: &State<Database>,
+    user: &User,
+    log_entry: &WriteLogEntry,
+    id: ID,
+    request: Json<PlannedExpenseLink>,
+) -> ApiResponse {
+    let link = request.into_inner();
+    log_entry.set_content(&link);
+
+    let role = HouseholdRole::fetch_for_planned_expense(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    if let Some(expense_id) = link.expense_id {
+        let role = HouseholdRole::fetch_for_expense(db, &user.username, expense_id).await;
+        if let Err(response) = can_view(role) {
+            return response;
+        }
+    }
+
+    match PlannedExpense::update_expense_id(db, id, link.expense_id).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
@@ -0,0 +1,30 @@
+use rocket::{get, State};
+
+use crate::database::Database;
+use crate::guards::user::User;
+use crate::response::ApiResponse;
+use crate::schema::recurring::{RecurringForecast, RecurringSeriesList, MAX_FORECAST_MONTHS};
+
+#[get("/recurring")]
+pub async fn get_recurring(db: &State<Database>, user: &User) -> ApiResponse {
+    match RecurringSeriesList::fetch(db, &user.username).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[get("/recurring/forecast/<months>")]
+pub async fn get_recurring_forecast(db: &State<Database>, user: &User, months: u32) -> ApiResponse {
+    if months == 0 || months > MAX_FORECAST_MONTHS {
+        let message = format!(
+            "Forecast has to be between 1 and {} months",
+            MAX_FORECAST_MONTHS
+        );
+        return ApiResponse::bad(&message);
+    }
+
+    match RecurringForecast::fetch(db, &user.username, months).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
+}
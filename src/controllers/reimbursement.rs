@@ -0,0 +1,133 @@
+use rocket::serde::json::Json;
+use rocket::{get, put, State};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::access::{can_edit, can_view};
+use crate::common::TS_FILE;
+use crate::database::{Database, ID};
+use crate::guards::user::User;
+use crate::guards::write_log::WriteLogEntry;
+use crate::response::ApiResponse;
+use crate::schema::expense::Expense;
+use crate::schema::household::HouseholdRole;
+use crate::schema::reimbursement::{
+    validate_reimbursements, PendingReimbursement, PendingReimbursementFields, Reimbursement,
+    ReimbursementFields,
+};
+
+#[get("/expenses/<id>/reimbursements")]
+pub async fn get_reimbursements(db: &State<Database>, user: &User, id: ID) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_expense(db, &user.username, id).await;
+    if let Err(response) = can_view(role) {
+        return response;
+    }
+
+    match Reimbursement::fetch_by_expense_id(db, id).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct UpdateReimbursementsRequest {
+    reimbursements: Vec<ReimbursementFields>,
+}
+
+// Replaces all links of reimbursement expense <id>, empty list unlinks it completely.
+#[put("/expenses/<id>/reimbursements", format = "json", data = "<json>")]
+pub async fn update_reimbursements(
+    db: &State<Database>,
+    user: &User,
+    log_entry: &WriteLogEntry,
+    id: ID,
+    json: Json<UpdateReimbursementsRequest>,
+) -> ApiResponse {
+    let request = json.into_inner();
+    log_entry.set_content(&request);
+
+    let role = HouseholdRole::fetch_for_expense(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    let reimbursement = match Expense::fetch_by_id(db, id).await {
+        Ok(value) => value,
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    if let Err(message) = validate_reimbursements(&reimbursement, &request.reimbursements) {
+        return ApiResponse::bad(&message);
+    }
+
+    // Only one level of linking is allowed, otherwise netting the spend would need to follow
+    // the chain of reimbursements.
+    match Reimbursement::any_has_expense_id(db, id).await {
+        Ok(false) => (),
+        Ok(true) => {
+            let message = "Expense is already reimbursed, so it can't be a reimbursement itself.";
+            return ApiResponse::bad(message);
+        }
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    for link in request.reimbursements.iter() {
+        // linked expenses are netted in their household's spending, so they're edited too
+        let role = HouseholdRole::fetch_for_expense(db, &user.username, link.expense_id).await;
+        if let Err(response) = can_edit(role) {
+            return response;
+        }
+
+        match Reimbursement::any_has_reimbursement_id(db, link.expense_id).await {
+            Ok(false) => (),
+            Ok(true) => {
+                let message = format!(
+                    "Expense {} is a reimbursement, it can't be reimbursed itself.",
+                    link.expense_id
+                );
+                return ApiResponse::bad(&message);
+            }
+            Err(e) => return ApiResponse::error(e),
+        };
+    }
+
+    match Reimbursement::replace(db, id, request.reimbursements).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[put(
+    "/expenses/<id>/pending_reimbursement",
+    format = "json",
+    data = "<json>"
+)]
+pub async fn update_pending_reimbursement(
+    db: &State<Database>,
+    user: &User,
+    log_entry: &WriteLogEntry,
+    id: ID,
+    json: Json<PendingReimbursementFields>,
+) -> ApiResponse {
+    let request = json.into_inner();
+    log_entry.set_content(&request);
+
+    let role = HouseholdRole::fetch_for_expense(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    match PendingReimbursement::update(db, id, request).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[get("/reimbursements/pending")]
+pub async fn get_pending_reimbursements(db: &State<Database>, user: &User) -> ApiResponse {
+    match PendingReimbursement::fetch_all(db, &user.username).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
+}
@@ -0,0 +1,56 @@
+use rocket::http::CookieJar;
+use rocket::{delete, get, State};
+
+use crate::database::{Database, ID};
+use crate::guards::user::User;
+use crate::response::ApiResponse;
+use crate::sessions::{Session, Sessions, SESSION_COOKIE};
+
+/* Users manage only their own sessions. These don't go to write log, so read-only users can log
+out of other devices too. */
+
+#[get("/sessions")]
+pub async fn get_sessions(db: &State<Database>, user: &User) -> ApiResponse {
+    match Session::fetch_by_username(db, &user.username).await {
+        Ok(value) => ApiResponse::data(Sessions {
+            sessions: value,
+            current_id: user.session_id,
+        }),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[delete("/sessions/<id>")]
+pub async fn delete_session(
+    cookies: &CookieJar<'_>,
+    db: &State<Database>,
+    user: &User,
+    id: ID,
+) -> ApiResponse {
+    match Session::delete(db, &user.username, id).await {
+        Ok(true) => (),
+        Ok(false) => return ApiResponse::not_found(),
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    if Some(id) == user.session_id {
+        cookies.remove_private(SESSION_COOKIE);
+    }
+
+    ApiResponse::ok()
+}
+
+// Log out everywhere, including this session
+#[delete("/sessions")]
+pub async fn delete_all_sessions(
+    cookies: &CookieJar<'_>,
+    db: &State<Database>,
+    user: &User,
+) -> ApiResponse {
+    cookies.remove_private(SESSION_COOKIE);
+
+    match Session::delete_by_username(db, &user.username).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
@@ -2,6 +2,8 @@ use rocket::serde::json::Json;
 use rocket::{delete, get, post, State};
 
 use crate::database::{Database, ID};
+use crate::guards::role::{Admin, RequireRole};
+use crate::guards::user::User;
 use crate::guards::write_log::WriteLogEntry;
 use crate::response::ApiResponse;
 use crate::schema::account::Account;
@@ -9,7 +11,7 @@ use crate::schema::statement_schema::{StatementSchema, StatementSchemaFields};
 use crate::schema::statement_schema_test::TestSchemaRequest;
 
 #[get("/schemas")]
-pub async fn get_schemas(db: &State<Database>) -> ApiResponse {
+pub async fn get_schemas(db: &State<Database>, _user: &User) -> ApiResponse {
     match StatementSchema::fetch_all(&db).await {
         Ok(value) => ApiResponse::data(value),
         Err(e) => ApiResponse::error(e),
@@ -19,6 +21,7 @@ pub async fn get_schemas(db: &State<Database>) -> ApiResponse {
 #[post("/schemas", format = "json", data = "<request>")]
 pub async fn create_schema(
     db: &State<Database>,
+    _role: RequireRole<Admin>,
     log_entry: &WriteLogEntry,
     request: Json<StatementSchemaFields>,
 ) -> ApiResponse {
@@ -34,6 +37,7 @@ pub async fn create_schema(
 #[put("/schemas/<id>", format = "json", data = "<request>")]
 pub async fn update_schema(
     db: &State<Database>,
+    _role: RequireRole<Admin>,
     log_entry: &WriteLogEntry,
     id: ID,
     request: Json<StatementSchemaFields>,
@@ -41,7 +45,7 @@ pub async fn update_schema(
     let fields = request.into_inner();
     log_entry.set_content(&fields);
 
-    match StatementSchema::update(&db, id, fields).await {
+    match StatementSchema::update(db, id, fields, log_entry.id).await {
         Ok(_) => ApiResponse::ok(),
         Err(e) => ApiResponse::error(e),
     }
@@ -50,6 +54,7 @@ pub async fn update_schema(
 #[delete("/schemas/<id>")]
 pub async fn delete_schema(
     db: &State<Database>,
+    _role: RequireRole<Admin>,
     _log_entry: &WriteLogEntry,
     id: ID,
 ) -> ApiResponse {
@@ -66,7 +71,7 @@ pub async fn delete_schema(
 }
 
 #[post("/schemas/test", format = "json", data = "<request>")]
-pub async fn test_schema(request: Json<TestSchemaRequest>) -> ApiResponse {
+pub async fn test_schema(_user: &User, request: Json<TestSchemaRequest>) -> ApiResponse {
     let test_schema_request = request.into_inner();
     let test_schema_response = test_schema_request.process();
 
@@ -0,0 +1,176 @@
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, State};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::access::{can_edit, household_for_create};
+use crate::common::TS_FILE;
+use crate::database::{Database, ID};
+use crate::guards::user::User;
+use crate::guards::write_log::WriteLogEntry;
+use crate::response::ApiResponse;
+use crate::schema::household::HouseholdRole;
+use crate::schema::tag::{Tag, TagFields, TagSpendingDataPoint};
+
+#[get("/tags")]
+pub async fn get_tags(db: &State<Database>, user: &User) -> ApiResponse {
+    match Tag::fetch_all(db, &user.username).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[post("/tags?<household_id>", format = "json", data = "<request>")]
+pub async fn create_tag(
+    db: &State<Database>,
+    user: &User,
+    log_entry: &WriteLogEntry,
+    household_id: Option<ID>,
+    request: Json<TagFields>,
+) -> ApiResponse {
+    let fields = request.into_inner();
+    log_entry.set_content(&fields);
+
+    let household_id = match household_for_create(db, &user.username, household_id).await {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
+
+    match Tag::create(db, household_id, fields).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[put("/tags/<id>", format = "json", data = "<request>")]
+pub async fn update_tag(
+    db: &State<Database>,
+    user: &User,
+    log_entry: &WriteLogEntry,
+    id: ID,
+    request: Json<TagFields>,
+) -> ApiResponse {
+    let fields = request.into_inner();
+    log_entry.set_content(&fields);
+
+    let role = HouseholdRole::fetch_for_tag(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    match Tag::update(db, id, fields, log_entry.id).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[delete("/tags/<id>")]
+pub async fn delete_tag(
+    db: &State<Database>,
+    user: &User,
+    _log_entry: &WriteLogEntry,
+    id: ID,
+) -> ApiResponse {
+    let role = HouseholdRole::fetch_for_tag(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    match Tag::any_has_expenses(db, id).await {
+        Ok(false) => (),
+        Ok(true) => return ApiResponse::bad("Can't delete tag attached to expenses."),
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    match Tag::delete(db, id).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct TagExpensesRequest {
+    expense_ids: Vec<ID>,
+}
+
+#[post("/tags/<id>/tag", format = "json", data = "<json>")]
+pub async fn tag_expenses(
+    db: &State<Database>,
+    user: &User,
+    log_entry: &WriteLogEntry,
+    id: ID,
+    json: Json<TagExpensesRequest>,
+) -> ApiResponse {
+    let request = json.into_inner();
+    log_entry.set_content(&request);
+
+    let role = HouseholdRole::fetch_for_tag(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    for expense_id in request.expense_ids.iter() {
+        let role = HouseholdRole::fetch_for_expense(db, &user.username, *expense_id).await;
+        if let Err(response) = can_edit(role) {
+            return response;
+        }
+
+        match Tag::is_in_household_of_expense(db, id, *expense_id).await {
+            Ok(true) => (),
+            Ok(false) => {
+                let message = format!("Expense {} is in other household than the tag.", expense_id);
+                return ApiResponse::bad(&message);
+            }
+            Err(e) => return ApiResponse::error(e),
+        };
+    }
+
+    match Tag::add_to_expenses(db, id, &request.expense_ids).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[post("/tags/<id>/untag", format = "json", data = "<json>")]
+pub async fn untag_expenses(
+    db: &State<Database>,
+    user: &User,
+    log_entry: &WriteLogEntry,
+    id: ID,
+    json: Json<TagExpensesRequest>,
+) -> ApiResponse {
+    let request = json.into_inner();
+    log_entry.set_content(&request);
+
+    let role = HouseholdRole::fetch_for_tag(db, &user.username, id).await;
+    if let Err(response) = can_edit(role) {
+        return response;
+    }
+
+    for expense_id in request.expense_ids.iter() {
+        let role = HouseholdRole::fetch_for_expense(db, &user.username, *expense_id).await;
+        if let Err(response) = can_edit(role) {
+            return response;
+        }
+    }
+
+    match Tag::remove_from_expenses(db, id, &request.expense_ids).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct TagSpendingData {
+    data: Vec<TagSpendingDataPoint>,
+}
+
+#[get("/spending/<year>/tags")]
+pub async fn get_tag_spending(db: &State<Database>, user: &User, year: i32) -> ApiResponse {
+    match TagSpendingDataPoint::fetch_by_year(db, &user.username, year).await {
+        Ok(data) => ApiResponse::data(TagSpendingData { data }),
+        Err(e) => ApiResponse::error(e),
+    }
+}
@@ -0,0 +1,116 @@
+use rocket::{get, post, State};
+use sqlx::Connection;
+
+use crate::database::{Database, ID};
+use crate::guards::role::{Admin, RequireRole};
+use crate::guards::write_log::WriteLogEntry;
+use crate::response::ApiResponse;
+use crate::schema::snapshot::Snapshot;
+use crate::write_log::{record_entities, WriteLogFilter, ENTITIES};
+
+/* Write log spans all households, so only admins can read it and undo its entries */
+
+#[get("/write_log?<filter..>")]
+pub async fn get_write_log(
+    db: &State<Database>,
+    _role: RequireRole<Admin>,
+    filter: WriteLogFilter,
+) -> ApiResponse {
+    if let Err(message) = filter.validate() {
+        return ApiResponse::bad(&message);
+    }
+
+    match filter.fetch(db, None).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+// Everything that touched the entity, e.g. /write_log/expenses/1234
+#[get("/write_log/<entity>/<id>?<filter..>")]
+pub async fn get_entity_history(
+    db: &State<Database>,
+    _role: RequireRole<Admin>,
+    entity: &str,
+    id: ID,
+    filter: WriteLogFilter,
+) -> ApiResponse {
+    if !ENTITIES.contains(&entity) {
+        return ApiResponse::not_found();
+    }
+
+    if let Err(message) = filter.validate() {
+        return ApiResponse::bad(&message);
+    }
+
+    match filter.fetch(db, Some((entity, id))).await {
+        Ok(value) => ApiResponse::data(value),
+        Err(e) => ApiResponse::error(e),
+    }
+}
+
+// Reverts changes of entry <id>, as a new entry
+#[post("/write_log/<id>/undo")]
+pub async fn undo_write_log_entry(
+    db: &State<Database>,
+    _role: RequireRole<Admin>,
+    log_entry: &WriteLogEntry,
+    id: ID,
+) -> ApiResponse {
+    let snapshots = match Snapshot::fetch_by_write_log_id(db, id).await {
+        Ok(value) => value,
+        Err(e) => return ApiResponse::error(e),
+    };
+
+    if snapshots.is_empty() {
+        let message = format!("Entry {} has no recorded changes to undo.", id);
+        return ApiResponse::bad(&message);
+    }
+
+    // rows are checked and restored in one transaction, so that a write in between can't be
+    // reverted without notice, and failed undo doesn't leave some of the rows restored
+    let mut conn = match db.acquire_db_conn().await {
+        Ok(value) => value,
+        Err(e) => return ApiResponse::error(e),
+    };
+    let mut tx = match conn.begin().await {
+        Ok(value) => value,
+        Err(e) => return ApiResponse::error(anyhow::anyhow!(e)),
+    };
+
+    for snapshot in snapshots.iter() {
+        match snapshot.is_current(&mut tx).await {
+            Ok(true) => (),
+            Ok(false) => {
+                let message = format!(
+                    "{} {} was changed or deleted since entry {}.",
+                    snapshot.kind.entity(),
+                    snapshot.entity_id,
+                    id
+                );
+                return ApiResponse::conflict(&message);
+            }
+            Err(e) => return ApiResponse::error(e),
+        };
+    }
+
+    let mut entities = vec![];
+    for snapshot in snapshots.iter().rev() {
+        match snapshot.restore(&mut tx, log_entry.id).await {
+            Ok(Ok(_)) => (),
+            Ok(Err(message)) => return ApiResponse::bad(&message),
+            Err(e) => return ApiResponse::error(e),
+        };
+        entities.push((snapshot.kind.entity(), snapshot.entity_id));
+    }
+
+    if let Err(e) = tx.commit().await {
+        return ApiResponse::error(anyhow::anyhow!(e));
+    }
+
+    // undo of e.g. expense 5 belongs to its history, though it's not in the uri
+    match record_entities(db, log_entry.id, &entities).await {
+        Ok(_) => ApiResponse::ok(),
+        Err(e) => ApiResponse::error(e),
+    }
+}
@@ -1,11 +1,13 @@
 use sqlx::FromRow;
 
 use crate::database::Database;
+use crate::schema::user_role::UserRole;
 
 #[derive(Debug, FromRow)]
 pub struct Credentials {
     pub username: String,
     pub pwhash: String,
+    pub role: UserRole,
 }
 
 #[allow(dead_code)]
@@ -35,7 +37,7 @@ impl Credentials {
     pub async fn fetch_all(db: &Database) -> anyhow::Result<Vec<Credentials>> {
         let mut conn = db.acquire_db_conn().await?;
         let result = sqlx::query_as::<_, Credentials>(
-            "SELECT username, pwhash FROM credentials ORDER BY username",
+            "SELECT username, pwhash, role FROM credentials ORDER BY username",
         )
         .fetch_all(&mut *conn)
         .await?;
@@ -49,7 +51,7 @@ impl Credentials {
     ) -> anyhow::Result<Option<Credentials>> {
         let mut conn = db.acquire_db_conn().await?;
         let result = sqlx::query_as::<_, Credentials>(
-            "SELECT username, pwhash FROM credentials WHERE username = ?1",
+            "SELECT username, pwhash, role FROM credentials WHERE username = ?1",
         )
         .bind(username)
         .fetch_optional(&mut *conn)
@@ -70,4 +72,17 @@ impl Credentials {
 
         Ok(())
     }
+
+    pub async fn update_role(&self, db: &Database) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query!(
+            "UPDATE credentials SET role = ?2 WHERE username = ?1",
+            self.username,
+            self.role,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
 }
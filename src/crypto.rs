@@ -1,11 +1,80 @@
 use libsodium_sys as ffi;
+use std::env;
 
 const STRBYTES: usize = ffi::crypto_pwhash_STRBYTES as usize;
-const OPSLIMIT_INTERACTIVE: u64 = ffi::crypto_pwhash_OPSLIMIT_INTERACTIVE as u64;
-const MEMLIMIT_INTERACTIVE: usize = ffi::crypto_pwhash_MEMLIMIT_INTERACTIVE as usize;
+const SHA256BYTES: usize = ffi::crypto_hash_sha256_BYTES as usize;
+
+const OPSLIMIT_ENV_VAR: &str = "PWHASH_OPSLIMIT";
+const MEMLIMIT_ENV_VAR: &str = "PWHASH_MEMLIMIT";
+
+/* Argon2id cost of new password hashes. Defaults are libsodium's interactive limits, and can be
+raised with env variables as hardware gets faster; hashes made with different parameters get
+rehashed on the next login, see needs_rehash. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PwhashParams {
+    pub opslimit: u64,
+    pub memlimit: usize, // bytes
+}
+
+impl Default for PwhashParams {
+    fn default() -> PwhashParams {
+        PwhashParams {
+            opslimit: ffi::crypto_pwhash_argon2id_OPSLIMIT_INTERACTIVE as u64,
+            memlimit: ffi::crypto_pwhash_argon2id_MEMLIMIT_INTERACTIVE as usize,
+        }
+    }
+}
+
+fn parse_limit(name: &str, value: Option<String>, min: u64, default: u64) -> Result<u64, String> {
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(default),
+    };
+
+    match value.parse::<u64>() {
+        Ok(limit) if limit >= min => Ok(limit),
+        _ => Err(format!(
+            "{} has to be a number of at least {}, got '{}'",
+            name, min, value
+        )),
+    }
+}
+
+impl PwhashParams {
+    pub fn from_env() -> Result<PwhashParams, String> {
+        let default = PwhashParams::default();
+
+        let opslimit = parse_limit(
+            OPSLIMIT_ENV_VAR,
+            env::var(OPSLIMIT_ENV_VAR).ok(),
+            ffi::crypto_pwhash_argon2id_OPSLIMIT_MIN as u64,
+            default.opslimit,
+        )?;
+
+        let memlimit = parse_limit(
+            MEMLIMIT_ENV_VAR,
+            env::var(MEMLIMIT_ENV_VAR).ok(),
+            ffi::crypto_pwhash_argon2id_MEMLIMIT_MIN as u64,
+            default.memlimit as u64,
+        )?;
+
+        let max_memlimit = unsafe { ffi::crypto_pwhash_argon2id_memlimit_max() };
+        let memlimit = match usize::try_from(memlimit) {
+            Ok(value) if value <= max_memlimit => value,
+            _ => {
+                return Err(format!(
+                    "{} can be at most {}",
+                    MEMLIMIT_ENV_VAR, max_memlimit
+                ))
+            }
+        };
+
+        Ok(PwhashParams { opslimit, memlimit })
+    }
+}
 
 /*
-pub fn crypto_pwhash_str(
+pub fn crypto_pwhash_argon2id_str(
     out: *mut libc::c_char,
     passwd: *const libc::c_char,
     passwdlen: libc::c_ulonglong,
@@ -13,16 +82,16 @@ pub fn crypto_pwhash_str(
     memlimit: usize,
 ) -> libc::c_int;
 */
-fn pwhash(password: &[u8]) -> Result<[u8; STRBYTES], ()> {
+fn pwhash(password: &[u8], params: &PwhashParams) -> Result<[u8; STRBYTES], ()> {
     let mut hash: [u8; STRBYTES] = [0; STRBYTES];
 
     if unsafe {
-        ffi::crypto_pwhash_str(
+        ffi::crypto_pwhash_argon2id_str(
             hash.as_mut_ptr() as *mut _,
             password.as_ptr() as *const _,
             password.len() as u64,
-            OPSLIMIT_INTERACTIVE,
-            MEMLIMIT_INTERACTIVE,
+            params.opslimit,
+            params.memlimit,
         )
     } == 0
     {
@@ -83,8 +152,8 @@ fn string_to_u8_array(string: &str) -> [u8; STRBYTES] {
     padded
 }
 
-pub fn hash_password(password: &str) -> Result<String, ()> {
-    let hashed_password_bytes = pwhash(password.as_bytes())?;
+pub fn hash_password(password: &str, params: &PwhashParams) -> Result<String, ()> {
+    let hashed_password_bytes = pwhash(password.as_bytes(), params)?;
     let hashed_password = u8_array_to_string(&hashed_password_bytes)?;
 
     Ok(hashed_password)
@@ -95,6 +164,88 @@ pub fn verify_password(hash: &str, password: &str) -> Result<(), ()> {
     pwhash_verify(&hash_bytes, password.as_bytes())
 }
 
+/*
+pub fn crypto_pwhash_argon2id_str_needs_rehash(
+    str_: *const libc::c_char,
+    opslimit: libc::c_ulonglong,
+    memlimit: usize,
+) -> libc::c_int;
+*/
+// True also for hashes of other algorithms, e.g. argon2i used by older libsodium
+pub fn needs_rehash(hash: &str, params: &PwhashParams) -> bool {
+    let hash_bytes = string_to_u8_array(hash);
+
+    unsafe {
+        ffi::crypto_pwhash_argon2id_str_needs_rehash(
+            hash_bytes.as_ptr() as *const _,
+            params.opslimit,
+            params.memlimit,
+        ) != 0
+    }
+}
+
+/*
+pub fn crypto_hash_sha256(
+    out: *mut libc::c_uchar,
+    in_: *const libc::c_uchar,
+    inlen: libc::c_ulonglong,
+) -> libc::c_int;
+*/
+pub fn sha256_hex(data: &[u8]) -> String {
+    let mut hash: [u8; SHA256BYTES] = [0; SHA256BYTES];
+
+    // can't fail, always returns 0
+    unsafe {
+        ffi::crypto_hash_sha256(hash.as_mut_ptr(), data.as_ptr(), data.len() as u64);
+    }
+
+    hash.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/*
+pub fn sodium_memcmp(
+    b1_: *const libc::c_void,
+    b2_: *const libc::c_void,
+    len: usize,
+) -> libc::c_int;
+*/
+// Constant time comparison of secrets, both are hashed first, so that length doesn't leak either
+pub fn secrets_equal(a: &[u8], b: &[u8]) -> bool {
+    let mut hash_a: [u8; SHA256BYTES] = [0; SHA256BYTES];
+    let mut hash_b: [u8; SHA256BYTES] = [0; SHA256BYTES];
+
+    unsafe {
+        ffi::crypto_hash_sha256(hash_a.as_mut_ptr(), a.as_ptr(), a.len() as u64);
+        ffi::crypto_hash_sha256(hash_b.as_mut_ptr(), b.as_ptr(), b.len() as u64);
+
+        ffi::sodium_memcmp(
+            hash_a.as_ptr() as *const _,
+            hash_b.as_ptr() as *const _,
+            SHA256BYTES,
+        ) == 0
+    }
+}
+
+/*
+pub fn randombytes_buf(buf: *mut libc::c_void, size: usize);
+*/
+pub fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes: Vec<u8> = vec![0; len];
+
+    unsafe {
+        ffi::randombytes_buf(bytes.as_mut_ptr() as *mut _, bytes.len());
+    }
+
+    bytes
+}
+
+pub fn random_hex(len: usize) -> String {
+    random_bytes(len)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
 pub fn init_crypto() -> Result<(), ()> {
     if unsafe { ffi::sodium_init() } >= 0 {
         Ok(())
@@ -107,10 +258,16 @@ pub fn init_crypto() -> Result<(), ()> {
 mod tests {
     use super::*;
 
+    // cheapest allowed, so that tests don't take long
+    const TEST_PARAMS: PwhashParams = PwhashParams {
+        opslimit: ffi::crypto_pwhash_argon2id_OPSLIMIT_MIN as u64,
+        memlimit: ffi::crypto_pwhash_argon2id_MEMLIMIT_MIN as usize,
+    };
+
     #[test]
     fn test_hash_password() {
         let password = "not very good password";
-        let result = hash_password(password);
+        let result = hash_password(password, &TEST_PARAMS);
         assert!(result.is_ok());
 
         let hash = result.unwrap();
@@ -118,14 +275,46 @@ mod tests {
         assert!(!hash.contains("\0"));
     }
 
+    #[test]
+    fn test_secrets_equal() {
+        assert!(init_crypto().is_ok());
+        assert!(secrets_equal(b"metrics token", b"metrics token"));
+        assert!(!secrets_equal(b"metrics token", b"metrics tokem"));
+        assert!(!secrets_equal(b"metrics token", b"metrics"));
+        assert!(!secrets_equal(b"", b"metrics token"));
+    }
+
     #[test]
     fn test_verify_hashed_password() {
         let password = "another not very good password";
-        let hash = hash_password(password).unwrap();
+        let hash = hash_password(password, &TEST_PARAMS).unwrap();
         assert!(verify_password(&hash, password).is_ok());
         assert!(verify_password(&hash, "wrong password").is_err());
     }
 
+    #[test]
+    fn test_needs_rehash() {
+        assert!(init_crypto().is_ok());
+        let hash = hash_password("password", &TEST_PARAMS).unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(!needs_rehash(&hash, &TEST_PARAMS));
+
+        let stronger = PwhashParams {
+            opslimit: TEST_PARAMS.opslimit + 1,
+            ..TEST_PARAMS
+        };
+        assert!(needs_rehash(&hash, &stronger));
+        assert!(needs_rehash("not a hash", &TEST_PARAMS));
+    }
+
+    #[test]
+    fn test_parse_limit() {
+        assert_eq!(parse_limit("X", None, 1, 2), Ok(2));
+        assert_eq!(parse_limit("X", Some(String::from("3")), 1, 2), Ok(3));
+        assert!(parse_limit("X", Some(String::from("0")), 1, 2).is_err());
+        assert!(parse_limit("X", Some(String::from("lots")), 1, 2).is_err());
+    }
+
     #[test]
     #[allow(non_snake_case)]
     fn test_string_to_u8_array_pads_with_zero_bytes_to_STRBYTES_len() {
@@ -138,4 +327,26 @@ mod tests {
             assert_eq!(result[i], 0);
         }
     }
+
+    #[test]
+    fn test_sha256_hex() {
+        assert!(init_crypto().is_ok());
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn test_random_hex() {
+        assert!(init_crypto().is_ok());
+        let token = random_hex(32);
+        assert_eq!(token.len(), 64);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(token, random_hex(32));
+    }
 }
@@ -3,6 +3,7 @@ use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
 use sqlx::Sqlite;
 use std::env;
 use std::str::FromStr;
+use tracing::warn;
 
 const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
 
@@ -20,7 +21,7 @@ async fn get_db_pool() -> SqlitePool {
         Ok(value) => value,
         Err(_) => {
             let fallback_database_url = get_fallback_database_url();
-            println!(
+            warn!(
                 "{} env variable unset, falling back to {}.",
                 DATABASE_URL_ENV_VAR, fallback_database_url,
             );
@@ -48,6 +49,13 @@ pub struct Database {
     pool: SqlitePool,
 }
 
+#[derive(Debug)]
+pub struct PoolStats {
+    pub size: u32,
+    pub idle: usize,
+    pub max: u32,
+}
+
 impl Database {
     pub async fn init() -> Database {
         Database {
@@ -55,6 +63,14 @@ impl Database {
         }
     }
 
+    pub fn pool_stats(&self) -> PoolStats {
+        PoolStats {
+            size: self.pool.size(),
+            idle: self.pool.num_idle(),
+            max: self.pool.options().get_max_connections(),
+        }
+    }
+
     pub async fn acquire_db_conn(&self) -> Result<DatabaseConnection, anyhow::Error> {
         let conn = self.pool.acquire().await?;
 
@@ -1,7 +1,9 @@
 use rocket::fairing::{Fairing, Info, Kind};
 use rocket::{Request, Response, State};
+use tracing::error;
 
 use crate::database::Database;
+use crate::fairings::request_logger::request_id;
 use crate::guards::write_log::WriteLogEntry;
 
 pub struct WriteLogger {}
@@ -24,10 +26,13 @@ impl Fairing for WriteLogger {
 
         let db = request.guard::<&State<Database>>().await.unwrap();
         let status = response.status().code.to_string();
-        if log_entry.log_result(&db, &status).await.is_err() {
-            println!(
-                "CRITICAL: DB update failed for [{:?}], status {}",
-                log_entry, status
+        if let Err(e) = log_entry.log_result(db, &status).await {
+            error!(
+                request_id = request_id(request),
+                write_log_id = log_entry.id,
+                status,
+                error = %e,
+                "write log update failed"
             );
         }
     }
@@ -1,2 +1,2 @@
-pub mod gatekeeper;
 pub mod logger;
+pub mod request_logger;
@@ -0,0 +1,107 @@
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::{Data, Request, Response};
+use std::time::Instant;
+use tracing::info;
+
+use crate::crypto::random_hex;
+use crate::metrics::Metrics;
+
+/* Every request gets an id, taken from X-Request-Id header when proxy in front of the server sets
+one, so that log lines of the request can be found together. The id is sent back in the response
+header too, which lets users report it along with an error. */
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+const REQUEST_ID_BYTES: usize = 8;
+const MAX_REQUEST_ID_LENGTH: usize = 64;
+
+struct RequestStart {
+    id: String,
+    started: Instant,
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LENGTH
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn request_start<'r>(request: &'r Request<'_>) -> &'r RequestStart {
+    request.local_cache(|| {
+        let id = match request.headers().get_one(REQUEST_ID_HEADER) {
+            Some(value) if is_valid_request_id(value) => value.to_string(),
+            _ => random_hex(REQUEST_ID_BYTES),
+        };
+
+        RequestStart {
+            id,
+            started: Instant::now(),
+        }
+    })
+}
+
+pub fn request_id<'r>(request: &'r Request<'_>) -> &'r str {
+    &request_start(request).id
+}
+
+pub struct RequestLogger {}
+
+#[rocket::async_trait]
+impl Fairing for RequestLogger {
+    fn info(&self) -> Info {
+        Info {
+            name: "RequestLogger: log requests and collect metrics",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        request_start(request);
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let start = request_start(request);
+        let duration = start.started.elapsed();
+        let method = request.method().as_str();
+        let status = response.status().code;
+
+        // template, not the actual path, e.g. /api/expenses/<id>
+        let route = match request.route() {
+            Some(route) => route.uri.path().to_string(),
+            None => String::from("unmatched"),
+        };
+
+        info!(
+            request_id = %start.id,
+            method = %method,
+            route = %route,
+            status,
+            latency_ms = duration.as_secs_f64() * 1000.0,
+            "request"
+        );
+
+        if let Some(metrics) = request.rocket().state::<Metrics>() {
+            metrics.record_request(method, &route, status, duration);
+        }
+
+        response.set_header(Header::new(REQUEST_ID_HEADER, start.id.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_request_id() {
+        assert!(is_valid_request_id("4f9c2a01d3b7e6aa"));
+        assert!(is_valid_request_id("req-123_abc"));
+        assert!(!is_valid_request_id(""));
+        assert!(!is_valid_request_id("has space"));
+        assert!(!is_valid_request_id("line\nbreak"));
+        assert!(!is_valid_request_id(&"a".repeat(MAX_REQUEST_ID_LENGTH + 1)));
+    }
+}
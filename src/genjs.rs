@@ -3,33 +3,67 @@ use std::fs;
 use std::io;
 use ts_rs::{ExportError, TS};
 
+use crate::api_tokens::{ApiTokenFields, ApiTokens, CreatedApiToken};
 use crate::controllers::budget::BudgetCloneRequest;
 use crate::controllers::budget::SpendingData;
-use crate::controllers::expense::ExpensesQuery;
 use crate::controllers::fund::{FundItems, Funds};
+use crate::controllers::planned_expense::PlannedExpenseLink;
+use crate::controllers::reimbursement::UpdateReimbursementsRequest;
+use crate::controllers::tag::{TagExpensesRequest, TagSpendingData};
 
+use crate::response::Problem;
 use crate::schema::account::{AccountFields, Accounts};
+use crate::schema::attachment::Attachments;
+use crate::schema::balance::{
+    AccountBalance, BalanceGaps, OpeningBalance, ReconciliationRequest, ReconciliationResult,
+    Reconciliations,
+};
 use crate::schema::budget::Budget;
 use crate::schema::category::BudgetCategoryFields;
+use crate::schema::currency::ExchangeRates;
 use crate::schema::expense::Expenses;
+use crate::schema::expense_change::ExpenseHistory;
+use crate::schema::expense_query::{ExpensesQuery, ExpensesQueryResult};
+use crate::schema::expense_search::{ExpensesSearch, ExpensesSearchResults};
 use crate::schema::fund::FundFields;
 use crate::schema::item::BudgetItemFields;
+use crate::schema::planned_expense::{PlannedExpenseFields, PlannedExpenses};
+use crate::schema::record_mapping::ImportSummary;
+use crate::schema::recurring::{RecurringForecast, RecurringSeriesList};
+use crate::schema::reimbursement::{
+    PendingReimbursementFields, PendingReimbursements, Reimbursements,
+};
 use crate::schema::statement_schema::{StatementSchemaFields, StatementSchemas};
 use crate::schema::statement_schema_test::{TestSchemaRequest, TestSchemaResponse};
+use crate::schema::tag::{TagFields, Tags};
+use crate::sessions::Sessions;
+use crate::write_log::WriteLogRecords;
 
 fn export() -> Result<(), ExportError> {
     // exports type with all dependencies, see https://docs.rs/ts-rs/latest/src/ts_rs/lib.rs.html
     AccountFields::export_all()?;
     Accounts::export_all()?;
 
+    Attachments::export_all()?;
+
+    AccountBalance::export_all()?;
+    BalanceGaps::export_all()?;
+    OpeningBalance::export_all()?;
+    ReconciliationRequest::export_all()?;
+    ReconciliationResult::export_all()?;
+    Reconciliations::export_all()?;
+
     Budget::export_all()?;
     BudgetCategoryFields::export_all()?;
     BudgetItemFields::export_all()?;
 
+    ExchangeRates::export_all()?;
     Expenses::export_all()?;
+    ExpenseHistory::export_all()?;
 
     SpendingData::export_all()?;
 
+    ImportSummary::export_all()?;
     StatementSchemaFields::export_all()?;
     StatementSchemas::export_all()?;
 
@@ -39,11 +73,40 @@ fn export() -> Result<(), ExportError> {
     BudgetCloneRequest::export_all()?;
 
     ExpensesQuery::export_all()?;
+    ExpensesQueryResult::export_all()?;
+    ExpensesSearch::export_all()?;
+    ExpensesSearchResults::export_all()?;
 
     Funds::export_all()?;
     FundItems::export_all()?;
     FundFields::export_all()?;
 
+    Reimbursements::export_all()?;
+    UpdateReimbursementsRequest::export_all()?;
+    PendingReimbursementFields::export_all()?;
+    PendingReimbursements::export_all()?;
+
+    PlannedExpenses::export_all()?;
+    PlannedExpenseFields::export_all()?;
+    PlannedExpenseLink::export_all()?;
+
+    RecurringSeriesList::export_all()?;
+    RecurringForecast::export_all()?;
+
+    Tags::export_all()?;
+    TagFields::export_all()?;
+    TagExpensesRequest::export_all()?;
+    TagSpendingData::export_all()?;
+
+    Sessions::export_all()?;
+
+    ApiTokens::export_all()?;
+    ApiTokenFields::export_all()?;
+    CreatedApiToken::export_all()?;
+    WriteLogRecords::export_all()?;
+
+    Problem::export_all()?;
+
     Ok(())
 }
 
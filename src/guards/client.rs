@@ -0,0 +1,20 @@
+use rocket::request::{FromRequest, Outcome, Request};
+
+// What we know about the client, shown in session list so user can tell their sessions apart
+#[derive(Debug)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientInfo {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(ClientInfo {
+            user_agent: request.headers().get_one("User-Agent").map(String::from),
+            ip: request.client_ip().map(|ip| ip.to_string()),
+        })
+    }
+}
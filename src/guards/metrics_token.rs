@@ -0,0 +1,40 @@
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use std::env;
+
+use crate::crypto::secrets_equal;
+
+const METRICS_TOKEN_ENV_VAR: &str = "METRICS_TOKEN";
+
+/* Scrapers don't log in, so without METRICS_TOKEN env variable /metrics is only served to
+loopback, e.g. a scraper or a proxy on the same host. When it's set, the token has to be sent as
+`Authorization: Bearer <token>` from anywhere. */
+pub struct MetricsAccess {}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for MetricsAccess {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let token = match env::var(METRICS_TOKEN_ENV_VAR) {
+            Ok(value) if !value.is_empty() => value,
+            _ => {
+                return match request.client_ip() {
+                    Some(ip) if ip.is_loopback() => Outcome::Success(MetricsAccess {}),
+                    _ => Outcome::Error((Status::Forbidden, ())),
+                }
+            }
+        };
+
+        let sent = request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match sent {
+            Some(value) if secrets_equal(value.as_bytes(), token.as_bytes()) => {
+                Outcome::Success(MetricsAccess {})
+            }
+            _ => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
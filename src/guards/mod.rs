@@ -1,2 +1,6 @@
+pub mod client;
+pub mod metrics_token;
+pub mod request_id;
+pub mod role;
 pub mod user;
 pub mod write_log;
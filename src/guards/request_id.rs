@@ -0,0 +1,16 @@
+use rocket::request::{FromRequest, Outcome, Request};
+
+use crate::fairings::request_logger::request_id;
+
+// Id of the request, for handlers that report errors themselves, e.g. in the middle of a stream
+#[derive(Debug)]
+pub struct RequestId(pub String);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestId {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(RequestId(request_id(request).to_string()))
+    }
+}
@@ -0,0 +1,48 @@
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use std::marker::PhantomData;
+
+use crate::guards::user::User;
+use crate::schema::user_role::UserRole;
+
+/* Route declares the role it requires by taking `RequireRole<Admin>` argument. Users with lesser
+role get Forbidden, without the route running at all. */
+
+pub trait RequiredRole: Send + Sync {
+    const ROLE: UserRole;
+}
+
+pub struct Editor;
+
+impl RequiredRole for Editor {
+    const ROLE: UserRole = UserRole::Editor;
+}
+
+pub struct Admin;
+
+impl RequiredRole for Admin {
+    const ROLE: UserRole = UserRole::Admin;
+}
+
+pub struct RequireRole<R: RequiredRole> {
+    _role: PhantomData<R>,
+}
+
+#[rocket::async_trait]
+impl<'r, R: RequiredRole> FromRequest<'r> for RequireRole<R> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let user = match request.guard::<&User>().await {
+            Outcome::Success(value) => value,
+            Outcome::Error(e) => return Outcome::Error(e),
+            Outcome::Forward(status) => return Outcome::Forward(status),
+        };
+
+        if !user.role.allows(R::ROLE) {
+            return Outcome::Error((Status::Forbidden, ()));
+        }
+
+        Outcome::Success(RequireRole { _role: PhantomData })
+    }
+}
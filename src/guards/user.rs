@@ -1,47 +1,106 @@
-use rocket::http::Status;
+use rocket::http::{Method, Status};
 use rocket::request::{FromRequest, Outcome, Request};
 use rocket::State;
 use serde::Serialize;
 use ts_rs::TS;
 
+use crate::api_tokens::{normalized_path, token_allows, ApiToken};
 use crate::common::TS_FILE;
 use crate::credentials::Credentials;
-use crate::database::Database;
+use crate::database::{Database, ID};
+use crate::schema::user_role::UserRole;
+use crate::sessions::{Session, SESSION_COOKIE};
 
 // returned by /me and /login, don't just add shit here without thinking about those
 #[derive(Serialize, TS, Debug)]
 #[ts(export_to = TS_FILE)]
 pub struct User {
     pub username: String,
+    pub role: UserRole,
+    #[serde(skip)]
+    #[ts(skip)]
+    pub session_id: Option<ID>, // to revoke current session, no need to show it
+    #[serde(skip)]
+    #[ts(skip)]
+    pub api_token_id: Option<ID>, // set instead of session for scripts, see api_tokens.rs
 }
 
+// Token has to allow the request, not just be valid. Valid token without the scope is refused
+// with 403, so that scripts can tell it apart from a token that was revoked or mistyped.
+async fn user_from_token(
+    request: &Request<'_>,
+    db: &Database,
+    authorization: &str,
+) -> Result<User, Status> {
+    let token = authorization
+        .strip_prefix("Bearer ")
+        .ok_or(Status::Unauthorized)?;
+
+    let api_token = match ApiToken::fetch_by_token(db, token.trim()).await {
+        Ok(Some(value)) => value,
+        _ => return Err(Status::Unauthorized),
+    };
+
+    let is_get = request.method() == Method::Get;
+    if !token_allows(&api_token.scopes, is_get, &normalized_path(request.uri())) {
+        return Err(Status::Forbidden);
+    }
+
+    match Credentials::fetch_by_username(db, &api_token.username).await {
+        Ok(Some(creds)) => Ok(User {
+            username: creds.username,
+            role: creds.role,
+            session_id: None,
+            api_token_id: Some(api_token.id),
+        }),
+        _ => Err(Status::Unauthorized),
+    }
+}
+
+/* Every api route except login takes the user, or a guard built on it. Requests without one are
+forwarded, so they end up in 401 catcher, unless some other route takes them. */
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for &'r User {
-    type Error = std::convert::Infallible;
+    type Error = ();
 
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
         let user = request
             .local_cache_async(async {
-                let username = match request.cookies().get_private("user") {
+                let db = match request.guard::<&State<Database>>().await {
+                    Outcome::Success(value) => value,
+                    _ => return Err(Status::Unauthorized),
+                };
+
+                if let Some(authorization) = request.headers().get_one("Authorization") {
+                    return user_from_token(request, db, authorization).await;
+                }
+
+                let token = match request.cookies().get_private(SESSION_COOKIE) {
                     Some(cookie) => String::from(cookie.value()),
-                    None => return Err(()),
+                    None => return Err(Status::Unauthorized),
                 };
 
-                let db = match request.guard::<&State<Database>>().await {
-                    Outcome::Success(value) => value,
-                    _ => return Err(()),
+                let session = match Session::fetch_by_token(db, &token).await {
+                    Ok(Some(value)) => value,
+                    _ => return Err(Status::Unauthorized),
                 };
 
-                match Credentials::fetch_by_username(db, &username).await {
-                    Ok(Some(_)) => Ok(User { username: username }),
-                    _ => Err(()),
+                match Credentials::fetch_by_username(db, &session.username).await {
+                    Ok(Some(creds)) => Ok(User {
+                        username: creds.username,
+                        role: creds.role,
+                        session_id: Some(session.id),
+                        api_token_id: None,
+                    }),
+                    _ => Err(Status::Unauthorized),
                 }
             })
             .await;
 
         match user {
             Ok(value) => Outcome::Success(value),
-            _ => Outcome::Forward(Status::Unauthorized),
+            Err(status) if *status == Status::Forbidden => Outcome::Error((*status, ())),
+            Err(status) => Outcome::Forward(*status),
         }
     }
 }
@@ -3,10 +3,12 @@ use rocket::request::{FromRequest, Outcome, Request};
 use rocket::State;
 use serde::Serialize;
 use std::sync::Mutex;
-use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::common::now;
 use crate::database::{Database, ID};
+use crate::guards::role::{Editor, RequireRole};
 use crate::guards::user::User;
+use crate::write_log::{extract_entities, record_entities};
 
 /*
 pub struct WriteLogEntry {
@@ -14,6 +16,7 @@ pub struct WriteLogEntry {
   uri: String,
   method: String,
   username: String,
+  api_token_id: Option<ID>,
   content: Option<String>,
   status: Option<String>,
   start_ts: i64,
@@ -24,6 +27,8 @@ pub struct WriteLogEntry {
 #[derive(Debug)]
 pub struct WriteLogEntry {
     pub id: ID,
+    pub uri: String,
+    pub method: String,
     pub content: Mutex<Option<String>>,
 }
 
@@ -36,7 +41,7 @@ impl WriteLogEntry {
 
     pub async fn create(
         db: &Database,
-        username: &str,
+        user: &User,
         request: &Request<'_>,
     ) -> anyhow::Result<WriteLogEntry> {
         let ts = now();
@@ -49,11 +54,13 @@ impl WriteLogEntry {
               uri,
               method,
               username,
+              api_token_id,
               start_ts
-            ) VALUES (?1, ?2, ?3, ?4) RETURNING id",
+            ) VALUES (?1, ?2, ?3, ?4, ?5) RETURNING id",
             uri,
             method,
-            username,
+            user.username,
+            user.api_token_id,
             ts,
         )
         .fetch_one(&mut *conn)
@@ -63,6 +70,8 @@ impl WriteLogEntry {
 
         Ok(WriteLogEntry {
             id: id,
+            uri: request.uri().to_string(),
+            method,
             content: Mutex::new(None),
         })
     }
@@ -86,15 +95,25 @@ impl WriteLogEntry {
         .execute(&mut *conn)
         .await?;
 
+        let entities = extract_entities(&self.method, &self.uri, content.as_deref());
+        record_entities(db, self.id, &entities).await?;
+
         Ok(())
     }
 }
 
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for &'r WriteLogEntry {
-    type Error = std::convert::Infallible;
+    type Error = ();
 
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        // read-only users can't write anything, so they don't get the entry at all
+        match request.guard::<RequireRole<Editor>>().await {
+            Outcome::Success(_) => (),
+            Outcome::Error(e) => return Outcome::Error(e),
+            Outcome::Forward(status) => return Outcome::Forward(status),
+        };
+
         let entry = request
             .local_cache_async(async {
                 let user = match request.guard::<&User>().await {
@@ -107,7 +126,7 @@ impl<'r> FromRequest<'r> for &'r WriteLogEntry {
                     _ => return Err(()),
                 };
 
-                match WriteLogEntry::create(db, &user.username, &request).await {
+                match WriteLogEntry::create(db, user, request).await {
                     Ok(value) => Ok(value),
                     _ => Err(()),
                 }
@@ -116,16 +135,7 @@ impl<'r> FromRequest<'r> for &'r WriteLogEntry {
 
         match entry {
             Ok(value) => Outcome::Success(value),
-            _ => Outcome::Forward(Status::NotFound),
+            _ => Outcome::Error((Status::InternalServerError, ())),
         }
     }
 }
-
-fn now() -> i64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_millis()
-        .try_into()
-        .unwrap()
-}
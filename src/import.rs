@@ -1,11 +1,16 @@
 use csv::Reader;
 use std::cmp::Ordering;
 use std::fmt;
+use tracing::trace;
 
 use crate::database::{Database, ID};
 
+use crate::schema::account::Account;
+use crate::schema::balance::{is_locked, BalanceGaps, Reconciliation};
+use crate::schema::currency::{convert_to_home_currency, ExchangeRate, HOME_CURRENCY};
 use crate::schema::expense::{Expense, ExpenseFields, LatestExpenses};
-use crate::schema::record_mapping::{ImportResult, RecordMapping};
+use crate::schema::planned_expense::PlannedExpense;
+use crate::schema::record_mapping::{ImportResult, ImportSummary, RecordMapping};
 
 pub const STATEMENT_UPLOAD_PATH: &str = "www/upload/tmp.csv";
 
@@ -20,6 +25,8 @@ impl fmt::Display for ImportError {
     }
 }
 
+impl std::error::Error for ImportError {}
+
 impl ImportError {
     pub fn new(msg: String) -> ImportError {
         ImportError { message: msg }
@@ -60,7 +67,7 @@ pub async fn read_expenses(
             }
         };
         row_index += 1;
-        println!("Processed row {}", row_index);
+        trace!(row = row_index, "processed statement row");
     }
 
     Ok(expenses)
@@ -70,7 +77,13 @@ pub async fn save_expenses(
     account_id: ID,
     expenses: Vec<ExpenseFields>,
     db: &Database,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<ImportSummary> {
+    let mut expenses = expenses;
+    let account = Account::fetch_by_id(db, account_id).await?;
+    if account.fields.currency != HOME_CURRENCY {
+        convert_expenses(db, &account.fields.currency, &mut expenses).await?;
+    }
+
     let mut deduplicated = match Expense::fetch_latest_expenses(&db, account_id).await? {
         Some(latest_transactions) => deduplicate_expenses(expenses, latest_transactions),
         None => expenses,
@@ -81,10 +94,67 @@ pub async fn save_expenses(
         other => other,
     });
 
+    // statement overlapping reconciled period is fine, as long as all expenses from that period
+    // are already imported and get deduplicated
+    let locked_until = Reconciliation::fetch_locked_until(db, account_id).await?;
+    let locked_count = deduplicated
+        .iter()
+        .filter(|expense| is_locked(&expense.transaction_date, locked_until.as_deref()))
+        .count();
+    if locked_count > 0 {
+        let message = format!(
+            "Import would add {} expenses to statements reconciled up to {}.",
+            locked_count,
+            locked_until.unwrap_or_default()
+        );
+        return Err(ImportError::new(message).into());
+    }
+
+    let imported = deduplicated.len();
+    let first_date = deduplicated.first().map(|e| e.transaction_date.clone());
     for expense in deduplicated {
         Expense::create(&db, expense).await?;
     }
 
+    PlannedExpense::reconcile_all(db).await?;
+
+    // only report gaps this import could have caused or uncovered, older ones are known already
+    let gaps = match first_date {
+        Some(date) => BalanceGaps::fetch_by_account_id(db, account_id)
+            .await?
+            .map_err(|message| {
+                let message = format!("{} Expenses were imported, gaps weren't checked.", message);
+                ImportError::new(message)
+            })?
+            .gaps
+            .into_iter()
+            .filter(|gap| gap.to_date >= date)
+            .collect(),
+        None => vec![],
+    };
+
+    Ok(ImportSummary { imported, gaps })
+}
+
+async fn convert_expenses(
+    db: &Database,
+    currency: &str,
+    expenses: &mut [ExpenseFields],
+) -> anyhow::Result<()> {
+    for expense in expenses.iter_mut() {
+        let rate = match ExchangeRate::fetch_rate(db, currency, &expense.transaction_date).await? {
+            Some(value) => value,
+            None => {
+                let message = format!(
+                    "No exchange rate for {} on or before {}, load rates first.",
+                    currency, expense.transaction_date
+                );
+                return Err(ImportError::new(message).into());
+            }
+        };
+        convert_to_home_currency(expense, currency, rate).map_err(ImportError::new)?;
+    }
+
     Ok(())
 }
 
@@ -119,11 +189,28 @@ fn deduplicate_expenses(
     new_expenses
 }
 
+// Expenses imported before statement schema mapped the balance column don't have it stored
+fn is_duplicate(new: &ExpenseFields, old: &ExpenseFields) -> bool {
+    let balance_matches = match (new.balance, old.balance) {
+        (Some(new_balance), Some(old_balance)) => new_balance == old_balance,
+        _ => true,
+    };
+
+    // converted amount depends on exchange rates loaded at the time, statement amount doesn't
+    balance_matches
+        && new.account_id == old.account_id
+        && new.transaction_date == old.transaction_date
+        && new.transaction_time == old.transaction_time
+        && new.description == old.description
+        && new.account_amount() == old.account_amount()
+        && new.raw_csv == old.raw_csv
+}
+
 fn remove_duplicates(mut new: Vec<ExpenseFields>, old: Vec<Expense>) -> Vec<ExpenseFields> {
     for old_expense in old {
         let mut dupe_index: Option<usize> = None;
         for (index, new_expense_fields) in new.iter().enumerate() {
-            if new_expense_fields == &old_expense.fields {
+            if is_duplicate(new_expense_fields, &old_expense.fields) {
                 dupe_index = Some(index);
                 break;
             }
@@ -141,19 +228,24 @@ fn remove_duplicates(mut new: Vec<ExpenseFields>, old: Vec<Expense>) -> Vec<Expe
 mod tests {
     use super::*;
     use crate::schema::expense::{ExpenseCategory, ExpenseNotes};
+    use crate::schema::expense_change::prepare_converted_edit;
+    use crate::schema::money::Money;
 
-    fn get_expense_fields(amount: i32) -> ExpenseFields {
+    fn get_expense_fields(amount: i64) -> ExpenseFields {
         ExpenseFields {
             account_id: 8,
             transaction_date: "2025-01-25".to_string(),
             transaction_time: None,
             description: "Some expense".to_string(),
-            amount: amount,
+            amount: Money::from_cents(amount),
             raw_csv: None,
+            balance: None,
+            original_currency: None,
+            original_amount: None,
         }
     }
 
-    fn get_expense(amount: i32) -> Expense {
+    fn get_expense(amount: i64) -> Expense {
         Expense {
             id: 2,
             fields: get_expense_fields(amount),
@@ -187,6 +279,35 @@ mod tests {
 
         let result = remove_duplicates(new, old);
         assert_eq!(result.len(), 1);
-        assert_eq!(result[0].amount, 1300);
+        assert_eq!(result[0].amount, Money::from_cents(1300));
+    }
+
+    #[test]
+    fn test_remove_duplicates_ignores_balance_missing_in_db() {
+        let mut new_expense = get_expense_fields(500);
+        new_expense.balance = Some(Money::from_cents(12000));
+        let new = vec![new_expense];
+
+        let old = vec![get_expense(500)];
+
+        assert_eq!(remove_duplicates(new, old).len(), 0);
+    }
+
+    #[test]
+    fn test_remove_duplicates_edited_converted_expense() {
+        let mut imported = get_expense_fields(1000);
+        convert_to_home_currency(&mut imported, "EUR", 1.1).unwrap();
+
+        // view_original_expenses gives the expense as imported, statement amount included
+        let mut original = get_expense(0);
+        original.fields = get_expense_fields(1000);
+        convert_to_home_currency(&mut original.fields, "EUR", 1.1).unwrap();
+
+        let mut edited = get_expense_fields(1200);
+        assert!(prepare_converted_edit(&original.fields, &mut edited));
+        convert_to_home_currency(&mut edited, "EUR", 1.1).unwrap();
+        assert_ne!(edited.account_amount(), imported.account_amount());
+
+        assert_eq!(remove_duplicates(vec![imported], vec![original]).len(), 0);
     }
 }
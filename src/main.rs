@@ -4,7 +4,14 @@ use rocket::fs::{relative, FileServer};
 use rocket::{Error as RocketError, Ignite, Rocket};
 
 use clap::{Parser, Subcommand};
+use std::io::{self, IsTerminal};
+use tracing::error;
+use tracing_subscriber::EnvFilter;
 
+mod access;
+mod api_tokens;
+mod attachments;
+mod auth_log;
 mod common;
 mod controllers;
 mod credentials;
@@ -14,16 +21,24 @@ mod fairings;
 mod genjs;
 mod guards;
 mod import;
+mod metrics;
 mod migration;
 mod passwords;
+mod rates;
 mod response;
 mod schema;
+mod sessions;
+mod totp;
+mod write_log;
 
-use crate::crypto::init_crypto;
+use crate::auth_log::LoginAttempts;
+use crate::crypto::{init_crypto, PwhashParams};
 use crate::database::Database;
-use crate::fairings::gatekeeper::GateKeeper;
 use crate::fairings::logger::WriteLogger;
+use crate::fairings::request_logger::RequestLogger;
+use crate::metrics::Metrics;
 use crate::passwords::Command as PasswordsCommand;
+use crate::rates::Command as RatesCommand;
 
 #[derive(Parser)]
 #[command(about)]
@@ -43,6 +58,12 @@ enum Command {
         command: PasswordsCommand,
     },
 
+    /// Manage exchange rates used to convert expenses into home currency
+    Rates {
+        #[command(subcommand)]
+        command: RatesCommand,
+    },
+
     /// Starts the server
     Server,
 
@@ -56,7 +77,7 @@ async fn run() -> Result<Rocket<Ignite>, RocketError> {
     rocket::build()
         .mount(
             "/",
-            routes![controllers::index::index, controllers::index::not_found,],
+            routes![controllers::index::index, controllers::metrics::get_metrics,],
         )
         .mount(
             "/api",
@@ -65,6 +86,18 @@ async fn run() -> Result<Rocket<Ignite>, RocketError> {
                 controllers::account::create_account,
                 controllers::account::update_account,
                 controllers::account::delete_account,
+                controllers::account::close_account,
+                controllers::account::reopen_account,
+                controllers::attachment::get_attachments,
+                controllers::attachment::upload_attachment,
+                controllers::attachment::download_attachment,
+                controllers::attachment::delete_attachment,
+                controllers::balance::get_balance,
+                controllers::balance::get_balance_gaps,
+                controllers::balance::update_opening_balance,
+                controllers::balance::get_reconciliations,
+                controllers::balance::reconcile_statement,
+                controllers::balance::delete_reconciliation,
                 controllers::budget::get_budget,
                 controllers::budget::clone_budget,
                 controllers::budget::get_spending,
@@ -77,37 +110,108 @@ async fn run() -> Result<Rocket<Ignite>, RocketError> {
                 controllers::expense::delete_expenses,
                 controllers::expense::create_expense,
                 controllers::expense::delete_expense,
+                controllers::expense::update_expense,
+                controllers::expense::get_expense_history,
                 controllers::expense::update_expense_category,
                 controllers::expense::update_expense_notes,
                 controllers::expense::query_expenses,
+                controllers::expense::stream_expenses,
+                controllers::expense::search_expenses,
+                controllers::exchange_rate::get_exchange_rates,
                 controllers::fund::get_funds,
                 controllers::fund::get_items,
                 controllers::fund::create_fund,
                 controllers::fund::update_fund,
                 controllers::fund::delete_fund,
+                controllers::household::get_households,
+                controllers::household::create_household,
+                controllers::household::update_household,
+                controllers::household::get_household_members,
+                controllers::household::update_household_member,
+                controllers::household::delete_household_member,
                 controllers::import::import_expenses,
                 controllers::login::me,
                 controllers::login::login,
+                controllers::login::login_totp,
                 controllers::login::logout,
+                controllers::session::get_sessions,
+                controllers::session::delete_session,
+                controllers::session::delete_all_sessions,
+                controllers::api_token::get_tokens,
+                controllers::api_token::create_token,
+                controllers::api_token::delete_token,
+                controllers::planned_expense::get_planned_expenses,
+                controllers::planned_expense::create_planned_expense,
+                controllers::planned_expense::update_planned_expense,
+                controllers::planned_expense::delete_planned_expense,
+                controllers::planned_expense::update_planned_expense_link,
+                controllers::recurring::get_recurring,
+                controllers::recurring::get_recurring_forecast,
+                controllers::reimbursement::get_reimbursements,
+                controllers::reimbursement::update_reimbursements,
+                controllers::reimbursement::update_pending_reimbursement,
+                controllers::reimbursement::get_pending_reimbursements,
                 controllers::statement_schema::get_schemas,
                 controllers::statement_schema::create_schema,
                 controllers::statement_schema::update_schema,
                 controllers::statement_schema::delete_schema,
                 controllers::statement_schema::test_schema,
+                controllers::tag::get_tags,
+                controllers::tag::create_tag,
+                controllers::tag::update_tag,
+                controllers::tag::delete_tag,
+                controllers::tag::tag_expenses,
+                controllers::tag::untag_expenses,
+                controllers::tag::get_tag_spending,
+                controllers::write_log::get_write_log,
+                controllers::write_log::get_entity_history,
+                controllers::write_log::undo_write_log_entry,
             ],
         )
         .mount("/static", FileServer::from(relative!("www/static")))
+        .register(
+            "/",
+            catchers![
+                controllers::index::bad_request,
+                controllers::index::unauthorized_catcher,
+                controllers::index::forbidden,
+                controllers::index::not_found,
+                controllers::index::unprocessable,
+                controllers::index::internal_error,
+            ],
+        )
         .manage(db)
-        .attach(GateKeeper {})
+        .manage(Metrics::default())
+        .manage(LoginAttempts::default())
+        .attach(RequestLogger {})
         .attach(WriteLogger {})
         .launch()
         .await
 }
 
+// Log level and targets come from RUST_LOG, e.g. RUST_LOG=budget=debug,sqlx=warn. By default
+// Rocket's own per-request lines are left out, as RequestLogger logs every request already.
+fn init_tracing() {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new("info,rocket::server=warn"));
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_ansi(io::stdout().is_terminal())
+        .init();
+}
+
 #[rocket::main]
 async fn main() -> () {
+    init_tracing();
+
     if init_crypto().is_err() {
-        println!("Error initializing crypto, aborting");
+        error!("Error initializing crypto, aborting");
+        return;
+    }
+
+    if let Err(e) = PwhashParams::from_env() {
+        error!("Invalid password hashing parameters: {}, aborting", e);
         return;
     }
 
@@ -120,12 +224,16 @@ async fn main() -> () {
             let database = Database::init().await;
             passwords::manage_passwords(database, command).await;
         }
+        Command::Rates { command } => {
+            let database = Database::init().await;
+            rates::manage_rates(database, command).await;
+        }
         Command::Server => {
             let _ = run().await;
         }
         Command::Migration => {
             if let Err(error) = migration::run().await {
-                println!("Migration failed: {:?}", error);
+                error!("Migration failed: {:?}", error);
             }
         }
     };
@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::database::PoolStats;
+
+/* Counters kept in memory for Prometheus to scrape from /metrics, so they start from zero on
+restart, which Prometheus handles. Routes are labelled by their template, e.g. /api/expenses/<id>,
+to keep the number of series small. */
+
+// seconds, upper bounds of histogram buckets
+const DURATION_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Debug, Default, Clone)]
+struct Histogram {
+    buckets: [u64; DURATION_BUCKETS.len()], // not cumulative, summed up when rendered
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        if let Some(i) = DURATION_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[i] += 1;
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        let mut cumulative = 0;
+        for (bound, count) in DURATION_BUCKETS.iter().zip(self.buckets.iter()) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{}{}le=\"{}\"}} {}",
+                name, labels, separator, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{}{}le=\"+Inf\"}} {}",
+            name, labels, separator, self.count
+        );
+        let labels = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{}}}", labels)
+        };
+        let _ = writeln!(out, "{}_sum{} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{} {}", name, labels, self.count);
+    }
+}
+
+#[derive(Debug, Default)]
+struct MetricsData {
+    requests: BTreeMap<(String, String, u16), u64>, // method, route, status
+    request_durations: BTreeMap<(String, String), Histogram>, // method, route
+    imports: BTreeMap<&'static str, u64>,           // by outcome
+    import_duration: Histogram,
+    imported_expenses: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    data: Mutex<MetricsData>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ImportOutcome {
+    Imported,
+    Rejected, // statement didn't match the schema, or overlaps reconciled period
+    Failed,
+}
+
+impl ImportOutcome {
+    fn name(self) -> &'static str {
+        match self {
+            ImportOutcome::Imported => "imported",
+            ImportOutcome::Rejected => "rejected",
+            ImportOutcome::Failed => "failed",
+        }
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl Metrics {
+    pub fn record_request(&self, method: &str, route: &str, status: u16, duration: Duration) {
+        let mut data = self.data.lock().unwrap();
+        let key = (method.to_string(), route.to_string(), status);
+        *data.requests.entry(key).or_insert(0) += 1;
+        data.request_durations
+            .entry((method.to_string(), route.to_string()))
+            .or_default()
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn record_import(&self, outcome: ImportOutcome, duration: Duration, imported: usize) {
+        let mut data = self.data.lock().unwrap();
+        *data.imports.entry(outcome.name()).or_insert(0) += 1;
+        data.import_duration.observe(duration.as_secs_f64());
+        data.imported_expenses += imported as u64;
+    }
+
+    // Prometheus text exposition format
+    pub fn render(&self, pool: &PoolStats) -> String {
+        let data = self.data.lock().unwrap();
+        let mut out = String::new();
+
+        out.push_str("# HELP http_requests_total Requests by method, route and status.\n");
+        out.push_str("# TYPE http_requests_total counter\n");
+        for ((method, route, status), count) in data.requests.iter() {
+            let _ = writeln!(
+                out,
+                "http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                method,
+                escape_label(route),
+                status,
+                count
+            );
+        }
+
+        out.push_str("# HELP http_request_duration_seconds Request latency by method and route.\n");
+        out.push_str("# TYPE http_request_duration_seconds histogram\n");
+        for ((method, route), histogram) in data.request_durations.iter() {
+            let labels = format!("method=\"{}\",route=\"{}\"", method, escape_label(route));
+            histogram.render(&mut out, "http_request_duration_seconds", &labels);
+        }
+
+        out.push_str("# HELP imports_total Statement imports by outcome.\n");
+        out.push_str("# TYPE imports_total counter\n");
+        for (outcome, count) in data.imports.iter() {
+            let _ = writeln!(out, "imports_total{{outcome=\"{}\"}} {}", outcome, count);
+        }
+
+        out.push_str("# HELP import_duration_seconds Time to read and save a statement.\n");
+        out.push_str("# TYPE import_duration_seconds histogram\n");
+        data.import_duration
+            .render(&mut out, "import_duration_seconds", "");
+
+        out.push_str("# HELP imported_expenses_total Expenses added by imports.\n");
+        out.push_str("# TYPE imported_expenses_total counter\n");
+        let _ = writeln!(out, "imported_expenses_total {}", data.imported_expenses);
+
+        out.push_str("# HELP db_pool_connections Open database connections.\n");
+        out.push_str("# TYPE db_pool_connections gauge\n");
+        let _ = writeln!(out, "db_pool_connections {}", pool.size);
+
+        out.push_str("# HELP db_pool_idle_connections Open database connections not in use.\n");
+        out.push_str("# TYPE db_pool_idle_connections gauge\n");
+        let _ = writeln!(out, "db_pool_idle_connections {}", pool.idle);
+
+        out.push_str("# HELP db_pool_max_connections Limit of database connections.\n");
+        out.push_str("# TYPE db_pool_max_connections gauge\n");
+        let _ = writeln!(out, "db_pool_max_connections {}", pool.max);
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_render() {
+        let mut histogram = Histogram::default();
+        histogram.observe(0.00390625);
+        histogram.observe(0.25);
+        histogram.observe(32.0);
+
+        let mut out = String::new();
+        histogram.render(&mut out, "x", "route=\"/\"");
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines[0], "x_bucket{route=\"/\",le=\"0.005\"} 1");
+        assert_eq!(lines[4], "x_bucket{route=\"/\",le=\"0.1\"} 1");
+        assert_eq!(lines[5], "x_bucket{route=\"/\",le=\"0.25\"} 2");
+        assert_eq!(lines[10], "x_bucket{route=\"/\",le=\"10\"} 2");
+        assert_eq!(lines[11], "x_bucket{route=\"/\",le=\"+Inf\"} 3");
+        assert_eq!(lines[12], "x_sum{route=\"/\"} 32.25390625");
+        assert_eq!(lines[13], "x_count{route=\"/\"} 3");
+    }
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::default();
+        metrics.record_request("GET", "/api/expenses/<id>", 200, Duration::from_millis(20));
+        metrics.record_request("GET", "/api/expenses/<id>", 200, Duration::from_millis(30));
+        metrics.record_import(ImportOutcome::Imported, Duration::from_secs(1), 12);
+
+        let pool = PoolStats {
+            size: 2,
+            idle: 1,
+            max: 10,
+        };
+        let out = metrics.render(&pool);
+
+        assert!(out.contains(
+            "http_requests_total{method=\"GET\",route=\"/api/expenses/<id>\",status=\"200\"} 2\n"
+        ));
+        assert!(out.contains(
+            "http_request_duration_seconds_count{method=\"GET\",route=\"/api/expenses/<id>\"} 2\n"
+        ));
+        assert!(out.contains("imports_total{outcome=\"imported\"} 1\n"));
+        assert!(out.contains("import_duration_seconds_bucket{le=\"1\"} 1\n"));
+        assert!(out.contains("imported_expenses_total 12\n"));
+        assert!(out.contains("db_pool_idle_connections 1\n"));
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("/a\"b\\c"), "/a\\\"b\\\\c");
+    }
+}
@@ -5,8 +5,17 @@ cases I expect following flow:
  commit 3: move everything to use new field, clean up data and remove contents of run() */
 use anyhow;
 
+use crate::database::Database;
+use crate::write_log::backfill_entities;
+
+// Links write log entries from before delta030 to their entities; safe to run repeatedly
 pub async fn run() -> anyhow::Result<()> {
-    println!("No migration pending at this commit");
+    let db = Database::init().await;
+    let count = backfill_entities(&db).await?;
+    println!(
+        "Checked {} write log entries without linked entities",
+        count
+    );
 
     Ok(())
 }
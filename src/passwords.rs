@@ -1,9 +1,17 @@
 use clap::Subcommand;
 use std::io;
 
+use crate::api_tokens::{ApiToken, ApiTokenFields};
+use crate::common::now;
 use crate::credentials::Credentials;
-use crate::crypto::{hash_password, verify_password};
-use crate::database::Database;
+use crate::crypto::{hash_password, verify_password, PwhashParams};
+use crate::database::{Database, ID};
+use crate::schema::token_scope::TokenScope;
+use crate::schema::user_role::UserRole;
+use crate::sessions::Session;
+use crate::totp::{
+    generate_recovery_codes, generate_secret, is_valid_code, otpauth_uri, TotpState,
+};
 
 #[derive(Subcommand)]
 pub enum Command {
@@ -11,8 +19,31 @@ pub enum Command {
     Set { username: String },
     /// Removes username from database
     Remove { username: String },
-    /// Lists users with active credentials
+    /// Lists users with active credentials and their roles
     ListUsernames,
+    /// Sets role of an existing user; new users are editors
+    SetRole {
+        username: String,
+        #[arg(value_enum)]
+        role: UserRole,
+    },
+    /// Enables TOTP second factor for a user, replacing the previous one if there was any
+    EnableTotp { username: String },
+    /// Disables TOTP second factor, so that password is enough to log in
+    DisableTotp { username: String },
+    /// Creates API token for scripts, and prints it; it can't be shown again later
+    CreateToken {
+        username: String,
+        name: String,
+        #[arg(long, value_enum, value_delimiter = ',', required = true)]
+        scopes: Vec<TokenScope>,
+        #[arg(long, default_value_t = 90)]
+        days: i64,
+    },
+    /// Lists API tokens of a user
+    ListTokens { username: String },
+    /// Revokes API token of a user
+    RevokeToken { username: String, id: ID },
 }
 
 pub async fn manage_passwords(db: Database, command: Command) {
@@ -26,10 +57,77 @@ pub async fn manage_passwords(db: Database, command: Command) {
         Command::ListUsernames => {
             list_usernames(db).await;
         }
+        Command::SetRole { username, role } => {
+            set_role(db, username, role).await;
+        }
+        Command::EnableTotp { username } => {
+            enable_totp(db, username).await;
+        }
+        Command::DisableTotp { username } => {
+            disable_totp(db, username).await;
+        }
+        Command::CreateToken {
+            username,
+            name,
+            scopes,
+            days,
+        } => {
+            let fields = ApiTokenFields {
+                name,
+                scopes,
+                expires_in_days: days,
+            };
+            create_token(db, username, fields).await;
+        }
+        Command::ListTokens { username } => {
+            list_tokens(db, username).await;
+        }
+        Command::RevokeToken { username, id } => {
+            revoke_token(db, username, id).await;
+        }
     };
 }
 
+const MIN_PASSWORD_LENGTH: usize = 12;
+const MAX_PASSWORD_LENGTH: usize = 1024;
+
+fn check_password_policy(username: &str, password: &str) -> Result<(), String> {
+    let length = password.chars().count();
+    if length < MIN_PASSWORD_LENGTH {
+        return Err(format!(
+            "Password has to be at least {} characters long.",
+            MIN_PASSWORD_LENGTH
+        ));
+    }
+
+    if length > MAX_PASSWORD_LENGTH {
+        return Err(format!(
+            "Password can be at most {} characters long.",
+            MAX_PASSWORD_LENGTH
+        ));
+    }
+
+    if password.to_lowercase().contains(&username.to_lowercase()) {
+        return Err(String::from("Password can't contain the username."));
+    }
+
+    let first = password.chars().next();
+    if password.chars().all(|c| Some(c) == first) {
+        return Err(String::from("Password can't be one character repeated."));
+    }
+
+    Ok(())
+}
+
 async fn set_password(db: Database, username: String) {
+    let params = match PwhashParams::from_env() {
+        Ok(value) => value,
+        Err(e) => {
+            println!("Something went wrong: {}", e);
+            return;
+        }
+    };
+
     let password = match read_password() {
         Ok(value) => value,
         Err(e) => {
@@ -38,7 +136,12 @@ async fn set_password(db: Database, username: String) {
         }
     };
 
-    let hash = match hash_password(&password) {
+    if let Err(e) = check_password_policy(&username, &password) {
+        println!("{}", e);
+        return;
+    }
+
+    let hash = match hash_password(&password, &params) {
         Ok(value) => value,
         Err(_) => {
             println!("Something went wrong: Hashing password failed.");
@@ -59,25 +162,50 @@ async fn set_password(db: Database, username: String) {
     }
 }
 
+// Typed in password isn't shown in terminal; piped input is read as it is
+fn read_line_without_echo() -> io::Result<String> {
+    let fd = libc::STDIN_FILENO;
+    let mut line = String::new();
+
+    if unsafe { libc::isatty(fd) } != 1 {
+        io::stdin().read_line(&mut line)?;
+        return Ok(line);
+    }
+
+    let mut original: libc::termios = unsafe { std::mem::zeroed() };
+    if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // newline is still echoed, so that next output starts on its own line
+    let mut silent = original;
+    silent.c_lflag &= !libc::ECHO;
+    silent.c_lflag |= libc::ECHONL;
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &silent) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let result = io::stdin().read_line(&mut line);
+    unsafe {
+        libc::tcsetattr(fd, libc::TCSANOW, &original);
+    }
+    result?;
+
+    Ok(line)
+}
+
 fn read_password() -> anyhow::Result<String> {
     println!("Type in new password:");
-    let mut password = String::new();
-    io::stdin().read_line(&mut password)?;
+    let password = read_line_without_echo()?;
 
     println!("Repeat the password:");
-    let mut repeat = String::new();
-    io::stdin().read_line(&mut repeat)?;
+    let repeat = read_line_without_echo()?;
 
     if password != repeat {
         return Err(anyhow::anyhow!("Provided passwords differ, aborting!"));
     }
 
-    let trimmed = match password.ends_with("\n") {
-        true => password[0..password.len() - 1].to_string(),
-        false => password,
-    };
-
-    Ok(trimmed)
+    Ok(password.trim_end_matches(['\r', '\n']).to_string())
 }
 
 async fn save_credentials(db: Database, username: String, pwhash: String) -> anyhow::Result<()> {
@@ -92,6 +220,14 @@ async fn save_credentials(db: Database, username: String, pwhash: String) -> any
 }
 
 async fn remove_password(db: Database, username: String) {
+    match Session::delete_by_username(&db, &username).await {
+        Ok(count) => println!("Logged out {} session(s) of user '{}'", count, username),
+        Err(e) => {
+            println!("Something went wrong: {}", e);
+            return;
+        }
+    };
+
     match Credentials::delete_by_username(&db, &username).await {
         Ok(_) => println!("Deleted login credentials for user '{}'", username),
         Err(e) => println!("Something went wrong: {}", e),
@@ -108,6 +244,146 @@ async fn list_usernames(db: Database) {
     };
 
     for creds in all {
-        println!("{}", creds.username);
+        println!("{} ({:?})", creds.username, creds.role);
+    }
+}
+
+async fn set_role(db: Database, username: String, role: UserRole) {
+    let mut creds = match Credentials::fetch_by_username(&db, &username).await {
+        Ok(Some(value)) => value,
+        Ok(None) => {
+            println!("User '{}' doesn't exist.", username);
+            return;
+        }
+        Err(e) => {
+            println!("Something went wrong: {}", e);
+            return;
+        }
+    };
+
+    creds.role = role;
+    match creds.update_role(&db).await {
+        Ok(_) => println!("User '{}' is now {:?}.", username, role),
+        Err(e) => println!("Something went wrong: {}", e),
+    }
+}
+
+async fn enable_totp(db: Database, username: String) {
+    match Credentials::fetch_by_username(&db, &username).await {
+        Ok(Some(_)) => (),
+        Ok(None) => {
+            println!("User '{}' doesn't exist.", username);
+            return;
+        }
+        Err(e) => {
+            println!("Something went wrong: {}", e);
+            return;
+        }
+    };
+
+    let secret = generate_secret();
+    println!("Add this to the authenticator app, e.g. as QR code:");
+    println!("{}", otpauth_uri(&username, &secret));
+    println!("Type in the code from the app:");
+
+    let mut code = String::new();
+    if let Err(e) = io::stdin().read_line(&mut code) {
+        println!("Something went wrong: {}", e);
+        return;
+    }
+
+    if !is_valid_code(&secret, code.trim(), now() / 1000) {
+        println!("Code doesn't match, TOTP stays as it was.");
+        return;
+    }
+
+    let recovery_codes = generate_recovery_codes();
+    if let Err(e) = TotpState::enable(&db, &username, &secret, &recovery_codes).await {
+        println!("Something went wrong: {}", e);
+        return;
+    }
+
+    println!("TOTP enabled. Recovery codes, each can be used once instead of a code:");
+    for code in recovery_codes {
+        println!("{}", code);
+    }
+}
+
+async fn disable_totp(db: Database, username: String) {
+    match TotpState::disable(&db, &username).await {
+        Ok(_) => println!("TOTP disabled for user '{}'.", username),
+        Err(e) => println!("Something went wrong: {}", e),
+    }
+}
+
+async fn create_token(db: Database, username: String, fields: ApiTokenFields) {
+    if let Err(e) = fields.validate() {
+        println!("{}", e);
+        return;
+    }
+
+    match Credentials::fetch_by_username(&db, &username).await {
+        Ok(Some(_)) => (),
+        Ok(None) => {
+            println!("User '{}' doesn't exist.", username);
+            return;
+        }
+        Err(e) => {
+            println!("Something went wrong: {}", e);
+            return;
+        }
+    };
+
+    match ApiToken::create(&db, &username, &fields).await {
+        Ok(created) => {
+            println!("Token {} created, it won't be shown again:", created.id);
+            println!("{}", created.token);
+        }
+        Err(e) => println!("Something went wrong: {}", e),
+    }
+}
+
+async fn list_tokens(db: Database, username: String) {
+    let all = match ApiToken::fetch_by_username(&db, &username).await {
+        Ok(value) => value,
+        Err(e) => {
+            println!("Something went wrong: {}", e);
+            return;
+        }
+    };
+
+    let ts = now();
+    for token in all.tokens {
+        let expired = if token.expires_ts <= ts {
+            ", expired"
+        } else {
+            ""
+        };
+        println!(
+            "{}: {} {:?}{}",
+            token.id, token.name, token.scopes.0, expired
+        );
+    }
+}
+
+async fn revoke_token(db: Database, username: String, id: ID) {
+    match ApiToken::delete(&db, &username, id).await {
+        Ok(true) => println!("Revoked token {} of user '{}'", id, username),
+        Ok(false) => println!("User '{}' has no token {}.", username, id),
+        Err(e) => println!("Something went wrong: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_password_policy() {
+        assert!(check_password_policy("tester", "correct horse battery").is_ok());
+        assert!(check_password_policy("tester", "short").is_err());
+        assert!(check_password_policy("tester", &"a".repeat(MAX_PASSWORD_LENGTH + 1)).is_err());
+        assert!(check_password_policy("tester", "my TESTER password").is_err());
+        assert!(check_password_policy("tester", "xxxxxxxxxxxxxxxx").is_err());
     }
 }
@@ -0,0 +1,106 @@
+use clap::Subcommand;
+use csv::Reader;
+
+use crate::database::Database;
+use crate::schema::currency::{looks_like_currency_code, ExchangeRate, HOME_CURRENCY};
+use crate::schema::datetime::looks_like_valid_date;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Loads rates from CSV file with header "currency,date,rate", replacing rates for same days
+    Load { path: String },
+    /// Lists all stored rates
+    List,
+}
+
+pub async fn manage_rates(db: Database, command: Command) {
+    match command {
+        Command::Load { path } => {
+            load_rates(db, path).await;
+        }
+        Command::List => {
+            list_rates(db).await;
+        }
+    };
+}
+
+async fn load_rates(db: Database, path: String) {
+    let rates = match read_rates(&path) {
+        Ok(value) => value,
+        Err(e) => {
+            println!("Something went wrong: {}", e);
+            return;
+        }
+    };
+
+    match ExchangeRate::upsert_all(&db, &rates).await {
+        Ok(_) => println!("Loaded {} rates.", rates.len()),
+        Err(e) => println!("Something went wrong: {}", e),
+    }
+}
+
+fn read_rates(path: &str) -> anyhow::Result<Vec<ExchangeRate>> {
+    let mut reader = Reader::from_path(path)?;
+    let mut rates = vec![];
+    for (index, result) in reader.deserialize().enumerate() {
+        let rate: ExchangeRate = result?;
+        validate_rate(&rate).map_err(|e| anyhow::anyhow!("{} in row {}", e, index))?;
+        rates.push(rate);
+    }
+
+    Ok(rates)
+}
+
+fn validate_rate(rate: &ExchangeRate) -> Result<(), String> {
+    if !looks_like_currency_code(&rate.currency) || rate.currency == HOME_CURRENCY {
+        return Err(format!("Incorrect currency '{}'", rate.currency));
+    }
+
+    if !looks_like_valid_date(&rate.date) {
+        return Err(format!(
+            "Incorrect date '{}', expected 'yyyy-MM-dd'",
+            rate.date
+        ));
+    }
+
+    if !rate.rate.is_finite() || rate.rate <= 0. {
+        return Err(format!("Incorrect rate '{}'", rate.rate));
+    }
+
+    Ok(())
+}
+
+async fn list_rates(db: Database) {
+    let rates = match ExchangeRate::fetch_all(&db).await {
+        Ok(value) => value.rates,
+        Err(e) => {
+            println!("Something went wrong: {}", e);
+            return;
+        }
+    };
+
+    for rate in rates {
+        println!("{} {} {}", rate.currency, rate.date, rate.rate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_rate(currency: &str, date: &str, rate: f64) -> ExchangeRate {
+        ExchangeRate {
+            currency: currency.to_string(),
+            date: date.to_string(),
+            rate,
+        }
+    }
+
+    #[test]
+    fn test_validate_rate() {
+        assert!(validate_rate(&get_rate("EUR", "2025-07-14", 1.0837)).is_ok());
+        assert!(validate_rate(&get_rate(HOME_CURRENCY, "2025-07-14", 1.)).is_err());
+        assert!(validate_rate(&get_rate("EUR", "14.7.2025", 1.0837)).is_err());
+        assert!(validate_rate(&get_rate("EUR", "2025-07-14", 0.)).is_err());
+    }
+}
@@ -2,23 +2,148 @@ use rocket::http::{ContentType, Status};
 use rocket::request::Request;
 use rocket::response::{self, Responder, Response};
 use serde::Serialize;
-use serde_json::{json, to_string};
+use serde_json::to_string;
+use sqlx::error::ErrorKind;
+use tracing::{debug, error};
 use ts_rs::TS;
 
-// private to prevent explicit creation of ApiResponse. This allows to mute server errors
-// later on to not expose internal details to the users.
+use crate::common::TS_FILE;
+use crate::fairings::request_logger::request_id;
+
+// private to prevent explicit creation of ApiResponse. Server errors are logged with their
+// details, while the client only gets a generic message with id of the request to report.
 enum ApiResponseKind {
     Success,
     Data { data: String },
-    NotFound,
-    BadRequest { message: String },
-    ServerError { message: String },
+    Error(ApiError),
 }
 
 pub struct ApiResponse {
     kind: ApiResponseKind,
 }
 
+// Stable codes for clients to tell errors apart, messages are for people and may change
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export_to = TS_FILE)]
+pub enum ErrorCode {
+    Unauthorized,
+    NotFound,
+    Forbidden,
+    Conflict,
+    Validation,
+    ImportError,
+    TooManyRequests,
+    Internal,
+}
+
+// problem+json body of every error response
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct Problem {
+    pub status: u16,
+    pub code: ErrorCode,
+    pub message: String,
+    pub correlation_id: String, // X-Request-Id of the request, to find it in server log
+}
+
+#[derive(Debug)]
+pub enum ApiError {
+    Unauthorized(String),
+    NotFound,
+    Forbidden(String),
+    Conflict(String),
+    Validation(String),
+    Import(String),
+    TooManyRequests(String),
+    Internal(anyhow::Error),
+}
+
+const NOT_FOUND_MESSAGE: &str = "Not found.";
+const INTERNAL_MESSAGE: &str = "Something went wrong on the server.";
+
+impl Problem {
+    // For errors after the response has started, e.g. in a stream, where ApiResponse can't be
+    // returned anymore. Logged like internal errors of ApiResponse, with the same body.
+    pub fn internal(correlation_id: &str, error: &anyhow::Error) -> Problem {
+        error!(request_id = %correlation_id, ?error, "request failed");
+
+        Problem {
+            status: Status::InternalServerError.code,
+            code: ErrorCode::Internal,
+            message: INTERNAL_MESSAGE.to_string(),
+            correlation_id: correlation_id.to_string(),
+        }
+    }
+}
+
+impl ApiError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            ApiError::Unauthorized(_) => ErrorCode::Unauthorized,
+            ApiError::NotFound => ErrorCode::NotFound,
+            ApiError::Forbidden(_) => ErrorCode::Forbidden,
+            ApiError::Conflict(_) => ErrorCode::Conflict,
+            ApiError::Validation(_) => ErrorCode::Validation,
+            ApiError::Import(_) => ErrorCode::ImportError,
+            ApiError::TooManyRequests(_) => ErrorCode::TooManyRequests,
+            ApiError::Internal(_) => ErrorCode::Internal,
+        }
+    }
+
+    fn status(&self) -> Status {
+        match self {
+            ApiError::Unauthorized(_) => Status::Unauthorized,
+            ApiError::NotFound => Status::NotFound,
+            ApiError::Forbidden(_) => Status::Forbidden,
+            ApiError::Conflict(_) => Status::Conflict,
+            ApiError::Validation(_) => Status::BadRequest,
+            ApiError::Import(_) => Status::UnprocessableEntity,
+            ApiError::TooManyRequests(_) => Status::TooManyRequests,
+            ApiError::Internal(_) => Status::InternalServerError,
+        }
+    }
+
+    fn message(&self) -> &str {
+        match self {
+            ApiError::NotFound => NOT_FOUND_MESSAGE,
+            ApiError::Unauthorized(message)
+            | ApiError::Forbidden(message)
+            | ApiError::Conflict(message)
+            | ApiError::Validation(message)
+            | ApiError::Import(message)
+            | ApiError::TooManyRequests(message) => message,
+            ApiError::Internal(_) => INTERNAL_MESSAGE,
+        }
+    }
+}
+
+/* Errors that reach the api as anyhow::Error are mostly bugs or database trouble, but some are
+caused by the request, e.g. deleting a row which is still referenced, or fetching id that doesn't
+exist. Those get their own code, without details of the query. */
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> ApiError {
+        let kind = match error.downcast_ref::<sqlx::Error>() {
+            Some(sqlx::Error::RowNotFound) => return ApiError::NotFound,
+            Some(sqlx::Error::Database(db_error)) => db_error.kind(),
+            _ => return ApiError::Internal(error),
+        };
+
+        match kind {
+            ErrorKind::UniqueViolation => ApiError::Conflict(String::from(
+                "Item with the same name or key already exists.",
+            )),
+            ErrorKind::ForeignKeyViolation => ApiError::Conflict(String::from(
+                "Item is still used elsewhere, or refers to an item that doesn't exist.",
+            )),
+            ErrorKind::NotNullViolation | ErrorKind::CheckViolation => {
+                ApiError::Validation(String::from("Some value is missing or not allowed."))
+            }
+            _ => ApiError::Internal(error),
+        }
+    }
+}
+
 impl ApiResponse {
     pub fn ok() -> ApiResponse {
         ApiResponse {
@@ -37,26 +162,42 @@ impl ApiResponse {
         }
     }
 
-    pub fn not_found() -> ApiResponse {
+    pub fn from_error(error: ApiError) -> ApiResponse {
         ApiResponse {
-            kind: ApiResponseKind::NotFound,
+            kind: ApiResponseKind::Error(error),
         }
     }
 
+    pub fn unauthorized(message: &str) -> ApiResponse {
+        ApiResponse::from_error(ApiError::Unauthorized(message.to_string()))
+    }
+
+    pub fn not_found() -> ApiResponse {
+        ApiResponse::from_error(ApiError::NotFound)
+    }
+
+    pub fn forbidden(message: &str) -> ApiResponse {
+        ApiResponse::from_error(ApiError::Forbidden(message.to_string()))
+    }
+
+    pub fn too_many_requests(message: &str) -> ApiResponse {
+        ApiResponse::from_error(ApiError::TooManyRequests(message.to_string()))
+    }
+
+    pub fn conflict(message: &str) -> ApiResponse {
+        ApiResponse::from_error(ApiError::Conflict(message.to_string()))
+    }
+
+    pub fn import_error(message: &str) -> ApiResponse {
+        ApiResponse::from_error(ApiError::Import(message.to_string()))
+    }
+
     pub fn error(error: anyhow::Error) -> ApiResponse {
-        ApiResponse {
-            kind: ApiResponseKind::ServerError {
-                message: error.to_string(),
-            },
-        }
+        ApiResponse::from_error(ApiError::from(error))
     }
 
     pub fn bad(message: &str) -> ApiResponse {
-        ApiResponse {
-            kind: ApiResponseKind::BadRequest {
-                message: message.to_string(),
-            },
-        }
+        ApiResponse::from_error(ApiError::Validation(message.to_string()))
     }
 }
 
@@ -73,25 +214,26 @@ impl<'r> Responder<'r, 'static> for ApiResponse {
                 .header(ContentType::JSON)
                 .ok(),
 
-            ApiResponseKind::NotFound => Response::build_from("{}".respond_to(req)?)
-                .status(Status::NotFound)
-                .header(ContentType::JSON)
-                .ok(),
+            ApiResponseKind::Error(error) => {
+                let correlation_id = request_id(req).to_string();
+                match &error {
+                    ApiError::Internal(details) => {
+                        error!(request_id = %correlation_id, error = ?details, "request failed")
+                    }
+                    _ => debug!(request_id = %correlation_id, ?error, "request rejected"),
+                };
 
-            ApiResponseKind::ServerError { message } => {
-                let error = wrap_error(&message);
+                let status = error.status();
+                let problem = Problem {
+                    status: status.code,
+                    code: error.code(),
+                    message: error.message().to_string(),
+                    correlation_id,
+                };
+                let body = to_string(&problem).map_err(|_| Status::InternalServerError)?;
 
-                Response::build_from(error.respond_to(req)?)
-                    .status(Status::InternalServerError)
-                    .header(ContentType::new("application", "problem+json"))
-                    .ok()
-            }
-
-            ApiResponseKind::BadRequest { message } => {
-                let error = wrap_error(&message);
-
-                Response::build_from(error.respond_to(req)?)
-                    .status(Status::BadRequest)
+                Response::build_from(body.respond_to(req)?)
+                    .status(status)
                     .header(ContentType::new("application", "problem+json"))
                     .ok()
             }
@@ -99,10 +241,28 @@ impl<'r> Responder<'r, 'static> for ApiResponse {
     }
 }
 
-fn wrap_error(error_message: &str) -> String {
-    let error = json!({
-      "error": error_message,
-    });
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_from_anyhow() {
+        let error = ApiError::from(anyhow::anyhow!(sqlx::Error::RowNotFound));
+        assert_eq!(error.code(), ErrorCode::NotFound);
+        assert_eq!(error.status(), Status::NotFound);
 
-    error.to_string()
+        let error = ApiError::from(anyhow::anyhow!("disk full at /var/lib/budget"));
+        assert_eq!(error.code(), ErrorCode::Internal);
+        assert_eq!(error.status(), Status::InternalServerError);
+        assert_eq!(error.message(), INTERNAL_MESSAGE);
+    }
+
+    #[test]
+    fn test_error_code_serialization() {
+        assert_eq!(
+            to_string(&ErrorCode::ImportError).unwrap(),
+            "\"import_error\""
+        );
+        assert_eq!(to_string(&ErrorCode::NotFound).unwrap(), "\"not_found\"");
+    }
 }
@@ -1,3 +1,4 @@
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use ts_rs::TS;
@@ -20,33 +21,69 @@ pub struct AccountFields {
     pub name: String,
     pub account_type: AccountType,
     pub statement_schema_id: Option<ID>,
+    pub currency: String, // ISO 4217 code
+    pub institution: Option<String>,
+    pub last_four: Option<String>, // last 4 digits of card or account number
+    pub notes: Option<String>,
+    pub owner: Option<String>, // username
 }
 
 #[derive(Debug, FromRow, Serialize, Deserialize, TS)]
 #[ts(export_to = TS_FILE)]
 pub struct Account {
     pub id: ID,
+    pub household_id: ID,
+    pub closed_date: Option<String>, // yyyy-MM-dd, None while account is open
     #[serde(flatten)]
     #[sqlx(flatten)]
     #[ts(flatten)]
     pub fields: AccountFields,
 }
 
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct AccountClosing {
+    pub closed_date: String, // yyyy-MM-dd
+}
+
 #[derive(Debug, Serialize, TS)]
 #[ts(export_to = TS_FILE)]
 pub struct Accounts {
     pub accounts: Vec<Account>,
 }
 
+pub fn looks_like_last_four(digits: &str) -> bool {
+    let re = Regex::new(r"^[0-9]{4}$").unwrap();
+
+    re.is_match(digits)
+}
+
 impl Account {
-    pub async fn create(db: &Database, fields: AccountFields) -> anyhow::Result<ID> {
+    pub fn is_closed(&self) -> bool {
+        self.closed_date.is_some()
+    }
+
+    pub async fn create(
+        db: &Database,
+        household_id: ID,
+        fields: AccountFields,
+    ) -> anyhow::Result<ID> {
         let mut conn = db.acquire_db_conn().await?;
         let id: ID = sqlx::query_scalar!(
-            "INSERT INTO accounts (name, account_type, statement_schema_id)
-            VALUES (?1, ?2, ?3) RETURNING id",
+            "INSERT INTO accounts (
+                name, account_type, statement_schema_id, currency, institution, last_four, notes,
+                owner, household_id
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9) RETURNING id",
             fields.name,
             fields.account_type,
             fields.statement_schema_id,
+            fields.currency,
+            fields.institution,
+            fields.last_four,
+            fields.notes,
+            fields.owner,
+            household_id,
         )
         .fetch_one(&mut *conn)
         .await?
@@ -63,12 +100,40 @@ impl Account {
             "UPDATE accounts SET
                 name = ?2,
                 account_type = ?3,
-                statement_schema_id = ?4
+                statement_schema_id = ?4,
+                currency = ?5,
+                institution = ?6,
+                last_four = ?7,
+                notes = ?8,
+                owner = ?9
             WHERE id = ?1",
             id,
             fields.name,
             fields.account_type,
-            fields.statement_schema_id
+            fields.statement_schema_id,
+            fields.currency,
+            fields.institution,
+            fields.last_four,
+            fields.notes,
+            fields.owner,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    // None reopens the account
+    pub async fn update_closed_date(
+        db: &Database,
+        id: ID,
+        closed_date: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query!(
+            "UPDATE accounts SET closed_date = ?2 WHERE id = ?1",
+            id,
+            closed_date,
         )
         .execute(&mut *conn)
         .await?;
@@ -85,11 +150,24 @@ impl Account {
         Ok(())
     }
 
-    pub async fn fetch_all(db: &Database) -> anyhow::Result<Accounts> {
+    // Accounts the user has access to; closed accounts are only listed when asked for
+    pub async fn fetch_all(
+        db: &Database,
+        username: &str,
+        include_closed: bool,
+    ) -> anyhow::Result<Accounts> {
         let mut conn = db.acquire_db_conn().await?;
         let results = sqlx::query_as::<_, Account>(
-            "SELECT id, name, account_type, statement_schema_id FROM accounts ORDER BY name",
+            "SELECT id, household_id, closed_date, name, account_type, statement_schema_id, currency,
+            institution, last_four, notes, owner
+            FROM accounts
+            WHERE
+              id IN (SELECT account_id FROM view_account_access WHERE username = ?1)
+              AND (?2 OR closed_date IS NULL)
+            ORDER BY name",
         )
+        .bind(username)
+        .bind(include_closed)
         .fetch_all(&mut *conn)
         .await?;
 
@@ -99,7 +177,9 @@ impl Account {
     pub async fn fetch_by_id(db: &Database, id: ID) -> anyhow::Result<Account> {
         let mut conn = db.acquire_db_conn().await?;
         let result = sqlx::query_as::<_, Account>(
-            "SELECT id, name, account_type, statement_schema_id FROM accounts WHERE id = ?1",
+            "SELECT id, household_id, closed_date, name, account_type, statement_schema_id, currency,
+            institution, last_four, notes, owner
+            FROM accounts WHERE id = ?1",
         )
         .bind(id)
         .fetch_one(&mut *conn)
@@ -124,3 +204,16 @@ impl Account {
         Ok(true)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_looks_like_last_four() {
+        assert!(looks_like_last_four("0042"));
+        assert!(!looks_like_last_four("042"));
+        assert!(!looks_like_last_four("4242 4242"));
+        assert!(!looks_like_last_four("abcd"));
+    }
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use ts_rs::TS;
+
+use crate::common::TS_FILE;
+use crate::database::{Database, ID};
+
+#[derive(Debug, FromRow, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct AttachmentFields {
+    pub expense_id: ID,
+    pub filename: String,
+    pub content_type: String,
+    #[ts(type = "number")]
+    pub size: i64,
+    pub sha256: String, // hex encoded, determines where the file is stored on disk
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct Attachment {
+    pub id: ID,
+    pub uploaded_at: String, // UTC, 'yyyy-MM-dd HH:mm:ss'
+
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    #[ts(flatten)]
+    pub fields: AttachmentFields,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct Attachments {
+    pub attachments: Vec<Attachment>,
+}
+
+impl Attachment {
+    pub async fn create(db: &Database, fields: AttachmentFields) -> anyhow::Result<ID> {
+        let mut conn = db.acquire_db_conn().await?;
+        let id: ID = sqlx::query_scalar!(
+            "INSERT INTO attachments (
+              expense_id,
+              filename,
+              content_type,
+              size,
+              sha256,
+              uploaded_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, datetime('now')) RETURNING id AS \"id!\"",
+            fields.expense_id,
+            fields.filename,
+            fields.content_type,
+            fields.size,
+            fields.sha256,
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .try_into()
+        .unwrap();
+
+        Ok(id)
+    }
+
+    pub async fn delete(db: &Database, id: ID) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query!("DELETE FROM attachments WHERE id = ?1", id)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch_by_id(db: &Database, id: ID) -> anyhow::Result<Attachment> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query_as::<_, Attachment>("SELECT * FROM attachments WHERE id = ?1")
+            .bind(id)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        Ok(result)
+    }
+
+    pub async fn fetch_by_expense_id(db: &Database, expense_id: ID) -> anyhow::Result<Attachments> {
+        let mut conn = db.acquire_db_conn().await?;
+        let results = sqlx::query_as::<_, Attachment>(
+            "SELECT * FROM attachments WHERE expense_id = ?1 ORDER BY id",
+        )
+        .bind(expense_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(Attachments {
+            attachments: results,
+        })
+    }
+
+    // Content of the expense's attachments, fetched before deleting it to clean up the files
+    pub async fn fetch_sha256_by_expense_id(
+        db: &Database,
+        expense_id: ID,
+    ) -> anyhow::Result<Vec<String>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let results = sqlx::query_scalar!(
+            "SELECT DISTINCT sha256 FROM attachments WHERE expense_id = ?1",
+            expense_id,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(results)
+    }
+
+    // Same as above for expenses deleted by Expense::delete_by_account_id_and_date
+    pub async fn fetch_sha256_by_account_id_and_date(
+        db: &Database,
+        account_id: ID,
+        newer_than_date: &str,
+    ) -> anyhow::Result<Vec<String>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let results = sqlx::query_scalar!(
+            "SELECT DISTINCT a.sha256 FROM attachments a
+            JOIN expenses e ON e.id = a.expense_id
+            WHERE e.account_id = ?1 AND e.transaction_date > ?2",
+            account_id,
+            newer_than_date,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(results)
+    }
+
+    pub async fn any_has_sha256(db: &Database, sha256: &str) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query_scalar!(
+            "SELECT EXISTS (SELECT 1 FROM attachments WHERE sha256 = ?1)",
+            sha256,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if result == 0 {
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+}
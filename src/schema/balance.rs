@@ -0,0 +1,520 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use ts_rs::TS;
+
+use crate::common::TS_FILE;
+use crate::database::{Database, ID};
+use crate::schema::expense::Expense;
+use crate::schema::money::Money;
+
+/* Balances follow the sign of expense amounts: spend (positive amount) lowers the balance, and
+refunds or income raise it. Credit card balance is negative while money is owed. Balances are in
+currency of the account, like the statements they're reconciled with. */
+#[derive(Debug, FromRow, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct OpeningBalance {
+    pub opening_balance: Money,
+    pub opening_date: Option<String>, // None counts all expenses of the account
+}
+
+#[derive(Debug, FromRow, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct BalancePoint {
+    pub date: String,
+    pub balance: Money, // at the end of the day
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct AccountBalance {
+    #[serde(flatten)]
+    #[ts(flatten)]
+    pub opening: OpeningBalance,
+    pub balance: Money,
+    pub locked_until: Option<String>, // latest reconciled statement date
+    pub history: Vec<BalancePoint>,
+}
+
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct ReconciliationRequest {
+    pub statement_date: String, // closing date of the statement, inclusive
+    pub statement_balance: Money,
+}
+
+#[derive(Debug, FromRow, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct Reconciliation {
+    pub id: ID,
+    pub account_id: ID,
+    pub statement_date: String,
+    pub statement_balance: Money,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct Reconciliations {
+    pub reconciliations: Vec<Reconciliation>,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct ReconciliationResult {
+    pub statement_balance: Money,
+    pub computed_balance: Money,
+    pub difference: Money, // statement - computed; period is only locked when this is 0
+    pub reconciled: bool,
+}
+
+/* Place where statement balances disagree with expenses in between, meaning some transactions
+are missing or were wrongly dropped as duplicates during import. */
+#[derive(Debug, Serialize, TS, PartialEq)]
+#[ts(export_to = TS_FILE)]
+pub struct BalanceGap {
+    pub from_date: String, // last date where balance agreed with expenses
+    pub to_date: String,
+    // statement - computed; None when expenses of to_date don't add up to its balances in any order
+    pub difference: Option<Money>,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct BalanceGaps {
+    pub gaps: Vec<BalanceGap>,
+}
+
+impl ReconciliationResult {
+    // Statement balance comes from the client, so the difference may not fit
+    pub fn new(
+        statement_balance: Money,
+        computed_balance: Money,
+    ) -> Result<ReconciliationResult, String> {
+        let difference = match statement_balance.checked_sub(computed_balance) {
+            Some(value) => value,
+            None => {
+                let message = format!("Statement balance {} is out of range.", statement_balance);
+                return Err(message);
+            }
+        };
+
+        Ok(ReconciliationResult {
+            statement_balance,
+            computed_balance,
+            difference,
+            reconciled: difference == Money::ZERO,
+        })
+    }
+}
+
+// Expenses on locked_until date are locked too
+pub fn is_locked(transaction_date: &str, locked_until: Option<&str>) -> bool {
+    match locked_until {
+        Some(date) => transaction_date <= date,
+        None => false,
+    }
+}
+
+/* Statements usually don't order transactions within a day, so each day is checked as a whole.
+Every expense moves the balance from (balance + amount) to balance; once these steps are chained,
+exactly one balance should remain at each end, the opening and closing balance of the day. */
+fn day_opening_closing(steps: &[(Money, Money)]) -> Option<(Money, Money)> {
+    let mut openings: Vec<Money> = steps.iter().map(|(before, _)| *before).collect();
+    let mut closings: Vec<Money> = vec![];
+    for (_, after) in steps {
+        match openings.iter().position(|before| before == after) {
+            Some(index) => {
+                openings.remove(index);
+            }
+            None => closings.push(*after),
+        }
+    }
+
+    match (openings.as_slice(), closings.as_slice()) {
+        ([opening], [closing]) => Some((*opening, *closing)),
+        // only zero amounts, balance didn't move
+        ([], []) => steps.first().map(|(_, after)| (*after, *after)),
+        _ => None,
+    }
+}
+
+fn out_of_range(date: &str) -> String {
+    format!("Balances or amounts on {} are too large to check.", date)
+}
+
+/* Expects expenses of one account, ordered by date. Expenses without statement balance, e.g.
+added by hand, count towards the gap between the surrounding days with balances, unless they
+share a day with expenses that have balance. Balances come from statements, so sums that don't
+fit are reported instead of checked. */
+pub fn detect_gaps(expenses: &[Expense]) -> Result<Vec<BalanceGap>, String> {
+    let mut gaps = vec![];
+    let mut previous: Option<(&str, Money)> = None; // last checked date and its closing balance
+    let mut amounts_between = Money::ZERO;
+
+    for day in expenses.chunk_by(|a, b| a.fields.transaction_date == b.fields.transaction_date) {
+        let date = day[0].fields.transaction_date.as_str();
+        let mut steps: Vec<(Money, Money)> = vec![];
+        for expense in day {
+            if let Some(balance) = expense.fields.balance {
+                let before = balance
+                    .checked_add(expense.fields.account_amount())
+                    .ok_or_else(|| out_of_range(date))?;
+                steps.push((before, balance));
+            }
+        }
+
+        if steps.is_empty() {
+            for expense in day {
+                amounts_between = amounts_between
+                    .checked_add(expense.fields.account_amount())
+                    .ok_or_else(|| out_of_range(date))?;
+            }
+            continue;
+        }
+
+        match day_opening_closing(&steps) {
+            Some((opening, closing)) => {
+                if let Some((from_date, previous_closing)) = previous {
+                    let expected = previous_closing
+                        .checked_sub(amounts_between)
+                        .ok_or_else(|| out_of_range(date))?;
+                    if opening != expected {
+                        let difference = opening
+                            .checked_sub(expected)
+                            .ok_or_else(|| out_of_range(date))?;
+                        gaps.push(BalanceGap {
+                            from_date: from_date.to_string(),
+                            to_date: date.to_string(),
+                            difference: Some(difference),
+                        });
+                    }
+                }
+                previous = Some((date, closing));
+            }
+            None => {
+                gaps.push(BalanceGap {
+                    from_date: date.to_string(),
+                    to_date: date.to_string(),
+                    difference: None,
+                });
+                previous = None;
+            }
+        }
+        amounts_between = Money::ZERO;
+    }
+
+    Ok(gaps)
+}
+
+impl OpeningBalance {
+    pub async fn update(
+        db: &Database,
+        account_id: ID,
+        opening: OpeningBalance,
+    ) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query!(
+            "UPDATE accounts SET opening_balance = ?2, opening_date = ?3 WHERE id = ?1",
+            account_id,
+            opening.opening_balance,
+            opening.opening_date,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch_by_account_id(db: &Database, account_id: ID) -> anyhow::Result<Self> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query_as::<_, OpeningBalance>(
+            "SELECT opening_balance, opening_date FROM accounts WHERE id = ?1",
+        )
+        .bind(account_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(result)
+    }
+}
+
+impl AccountBalance {
+    // Balance at the end of given date
+    pub async fn fetch_at_date(db: &Database, account_id: ID, date: &str) -> anyhow::Result<Money> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query_scalar::<_, Money>(
+            "SELECT accounts.opening_balance - COALESCE(SUM(COALESCE(expenses.original_amount, expenses.amount)), 0)
+            FROM accounts
+            LEFT JOIN expenses
+              ON (
+                expenses.account_id = accounts.id
+                AND expenses.transaction_date <= ?2
+                AND expenses.transaction_date >= COALESCE(accounts.opening_date, '')
+              )
+            WHERE accounts.id = ?1
+            GROUP BY accounts.id",
+        )
+        .bind(account_id)
+        .bind(date)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(result)
+    }
+
+    pub async fn fetch_by_account_id(db: &Database, account_id: ID) -> anyhow::Result<Self> {
+        let opening = OpeningBalance::fetch_by_account_id(db, account_id).await?;
+        let locked_until = Reconciliation::fetch_locked_until(db, account_id).await?;
+
+        let mut conn = db.acquire_db_conn().await?;
+        let history = sqlx::query_as::<_, BalancePoint>(
+            "SELECT
+              transaction_date AS `date`,
+              ?2 - SUM(SUM(COALESCE(original_amount, amount))) OVER (ORDER BY transaction_date) AS `balance`
+            FROM expenses
+            WHERE
+              account_id = ?1
+              AND transaction_date >= COALESCE(?3, '')
+            GROUP BY transaction_date
+            ORDER BY transaction_date",
+        )
+        .bind(account_id)
+        .bind(opening.opening_balance)
+        .bind(&opening.opening_date)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let balance = match history.last() {
+            Some(point) => point.balance,
+            None => opening.opening_balance,
+        };
+
+        Ok(AccountBalance {
+            opening,
+            balance,
+            locked_until,
+            history,
+        })
+    }
+}
+
+impl BalanceGaps {
+    // Inner error when balances are too large to check, see detect_gaps
+    pub async fn fetch_by_account_id(
+        db: &Database,
+        account_id: ID,
+    ) -> anyhow::Result<Result<Self, String>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let expenses = sqlx::query_as::<_, Expense>(
+            "SELECT * FROM expenses
+            WHERE account_id = ?1
+            ORDER BY transaction_date, transaction_time, id",
+        )
+        .bind(account_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(detect_gaps(&expenses).map(|gaps| BalanceGaps { gaps }))
+    }
+}
+
+impl Reconciliation {
+    pub async fn create(
+        db: &Database,
+        account_id: ID,
+        request: &ReconciliationRequest,
+        write_log_id: ID,
+    ) -> anyhow::Result<ID> {
+        let mut conn = db.acquire_db_conn().await?;
+        let id: ID = sqlx::query_scalar!(
+            "INSERT INTO reconciliations (
+              account_id,
+              statement_date,
+              statement_balance,
+              write_log_id
+            ) VALUES (?1, ?2, ?3, ?4) RETURNING id",
+            account_id,
+            request.statement_date,
+            request.statement_balance,
+            write_log_id,
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .expect("INSERT failed, likely FOREIGN KEY constraint")
+        .try_into()
+        .unwrap();
+
+        Ok(id)
+    }
+
+    pub async fn delete(db: &Database, id: ID) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query!("DELETE FROM reconciliations WHERE id = ?1", id)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch_by_account_id(
+        db: &Database,
+        account_id: ID,
+    ) -> anyhow::Result<Reconciliations> {
+        let mut conn = db.acquire_db_conn().await?;
+        let results = sqlx::query_as::<_, Reconciliation>(
+            "SELECT id, account_id, statement_date, statement_balance FROM reconciliations
+            WHERE account_id = ?1
+            ORDER BY statement_date",
+        )
+        .bind(account_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(Reconciliations {
+            reconciliations: results,
+        })
+    }
+
+    pub async fn fetch_locked_until(
+        db: &Database,
+        account_id: ID,
+    ) -> anyhow::Result<Option<String>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query_scalar::<_, Option<String>>(
+            "SELECT MAX(statement_date) FROM reconciliations WHERE account_id = ?1",
+        )
+        .bind(account_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(result)
+    }
+
+    pub async fn any_has_account_id(db: &Database, account_id: ID) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query_scalar!(
+            "SELECT EXISTS (SELECT 1 FROM reconciliations WHERE account_id = ?1)",
+            account_id,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if result == 0 {
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::expense::{ExpenseCategory, ExpenseFields, ExpenseNotes};
+
+    fn get_expense(date: &str, amount: i64, balance: Option<i64>) -> Expense {
+        Expense {
+            id: 1,
+            fields: ExpenseFields {
+                account_id: 1,
+                transaction_date: date.to_string(),
+                transaction_time: None,
+                description: String::from("Some expense"),
+                amount: Money::from_cents(amount),
+                raw_csv: None,
+                balance: balance.map(Money::from_cents),
+                original_currency: None,
+                original_amount: None,
+            },
+            category: ExpenseCategory {
+                budget_item_id: None,
+            },
+            notes: ExpenseNotes { notes: None },
+        }
+    }
+
+    #[test]
+    fn test_reconciliation_result() {
+        let result =
+            ReconciliationResult::new(Money::from_cents(-12500), Money::from_cents(-12500))
+                .unwrap();
+        assert_eq!(result.difference, Money::from_cents(0));
+        assert!(result.reconciled);
+
+        let result =
+            ReconciliationResult::new(Money::from_cents(100000), Money::from_cents(104599))
+                .unwrap();
+        assert_eq!(result.difference, Money::from_cents(-4599));
+        assert!(!result.reconciled);
+
+        let result = ReconciliationResult::new(Money::from_cents(i64::MAX), Money::from_cents(-1));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_is_locked() {
+        assert!(!is_locked("2025-03-31", None));
+        assert!(is_locked("2025-03-30", Some("2025-03-31")));
+        assert!(is_locked("2025-03-31", Some("2025-03-31")));
+        assert!(!is_locked("2025-04-01", Some("2025-03-31")));
+    }
+
+    #[test]
+    fn test_detect_gaps_consistent() {
+        // same day listed newest first, as most banks do
+        let expenses = [
+            get_expense("2025-05-01", 1000, Some(99000)),
+            get_expense("2025-05-02", 500, Some(97500)),
+            get_expense("2025-05-02", 1000, Some(98000)),
+            get_expense("2025-05-03", 250, None),
+            get_expense("2025-05-04", -2000, Some(99250)),
+        ];
+
+        assert_eq!(detect_gaps(&expenses), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_detect_gaps_missing_expense() {
+        let expenses = [
+            get_expense("2025-05-01", 1000, Some(99000)),
+            get_expense("2025-05-03", 500, Some(95500)),
+        ];
+
+        let expected = BalanceGap {
+            from_date: String::from("2025-05-01"),
+            to_date: String::from("2025-05-03"),
+            difference: Some(Money::from_cents(-3000)),
+        };
+        assert_eq!(detect_gaps(&expenses), Ok(vec![expected]));
+    }
+
+    #[test]
+    fn test_detect_gaps_within_day() {
+        let expenses = [
+            get_expense("2025-05-01", 1000, Some(99000)),
+            get_expense("2025-05-02", 500, Some(98500)),
+            get_expense("2025-05-02", 700, Some(97000)),
+            get_expense("2025-05-03", 100, Some(96900)),
+        ];
+
+        let expected = BalanceGap {
+            from_date: String::from("2025-05-02"),
+            to_date: String::from("2025-05-02"),
+            difference: None,
+        };
+        assert_eq!(detect_gaps(&expenses), Ok(vec![expected]));
+    }
+
+    #[test]
+    fn test_detect_gaps_overflow() {
+        let expenses = [get_expense("2025-05-01", 1000, Some(i64::MAX))];
+        assert!(detect_gaps(&expenses).is_err());
+
+        let expenses = [
+            get_expense("2025-05-01", 1000, Some(99000)),
+            get_expense("2025-05-02", i64::MAX, None),
+            get_expense("2025-05-02", i64::MAX, None),
+            get_expense("2025-05-03", 500, Some(95500)),
+        ];
+        assert!(detect_gaps(&expenses).is_err());
+    }
+}
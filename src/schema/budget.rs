@@ -3,7 +3,7 @@ use sqlx::Acquire;
 use ts_rs::TS;
 
 use crate::common::TS_FILE;
-use crate::database::Database;
+use crate::database::{Database, ID};
 use crate::schema::category::BudgetCategory;
 use crate::schema::item::BudgetItem;
 
@@ -16,9 +16,10 @@ pub struct Budget {
 }
 
 impl Budget {
-    pub async fn fetch(db: &Database, year: i32) -> anyhow::Result<Budget> {
-        let categories = BudgetCategory::fetch_by_year(&db, year).await?;
-        let items = BudgetItem::fetch_by_year(&db, year).await?;
+    // Budgets of all households the user is member of, combined
+    pub async fn fetch(db: &Database, username: &str, year: i32) -> anyhow::Result<Budget> {
+        let categories = BudgetCategory::fetch_by_year(db, username, year).await?;
+        let items = BudgetItem::fetch_by_year(db, username, year).await?;
 
         Ok(Budget {
             year: year,
@@ -27,7 +28,12 @@ impl Budget {
         })
     }
 
-    pub async fn clone(db: &Database, from_year: i32, to_year: i32) -> anyhow::Result<()> {
+    pub async fn clone(
+        db: &Database,
+        household_id: ID,
+        from_year: i32,
+        to_year: i32,
+    ) -> anyhow::Result<()> {
         let mut conn = db.acquire_db_conn().await?;
 
         // Start the transaction on this specific connection
@@ -35,7 +41,8 @@ impl Budget {
         let mut tx = conn.begin().await?;
 
         let categories = sqlx::query!(
-            "SELECT id, name, ignored FROM budget_categories WHERE year = ?1",
+            "SELECT id, name, ignored FROM budget_categories WHERE household_id = ?1 AND year = ?2",
+            household_id,
             from_year
         )
         .fetch_all(&mut *tx)
@@ -43,8 +50,9 @@ impl Budget {
 
         for cat in categories {
             let new_category_id = sqlx::query!(
-                "INSERT INTO budget_categories (name, ignored, year)
-                VALUES (?1, ?2, ?3) RETURNING id",
+                "INSERT INTO budget_categories (household_id, name, ignored, year)
+                VALUES (?1, ?2, ?3, ?4) RETURNING id",
+                household_id,
                 cat.name,
                 cat.ignored,
                 to_year
@@ -1,9 +1,10 @@
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{Acquire, FromRow, SqliteConnection};
 use ts_rs::TS;
 
 use crate::common::TS_FILE;
 use crate::database::{Database, ID};
+use crate::schema::snapshot::{Snapshot, SnapshotKind};
 
 #[derive(Debug, FromRow, Deserialize, Serialize, TS)]
 #[ts(export_to = TS_FILE)]
@@ -16,7 +17,8 @@ pub struct BudgetCategoryFields {
 #[ts(export_to = TS_FILE)]
 pub struct BudgetCategory {
     pub id: ID,
-    pub year: i32, // immutable after creation
+    pub household_id: ID, // immutable after creation
+    pub year: i32,        // immutable after creation
 
     #[serde(flatten)]
     #[sqlx(flatten)]
@@ -27,27 +29,53 @@ pub struct BudgetCategory {
 impl BudgetCategory {
     pub async fn create(
         db: &Database,
+        household_id: ID,
         year: i32,
         fields: BudgetCategoryFields,
     ) -> anyhow::Result<ID> {
         let mut conn = db.acquire_db_conn().await?;
 
         let id: ID = sqlx::query_scalar!(
-            "INSERT INTO budget_categories (year, name, ignored) VALUES (?1, ?2, ?3) RETURNING id",
+            "INSERT INTO budget_categories (household_id, year, name, ignored)
+            VALUES (?1, ?2, ?3, ?4) RETURNING id",
+            household_id,
             year,
             fields.name,
             fields.ignored,
         )
         .fetch_one(&mut *conn)
         .await?
+        .expect("INSERT failed, likely FOREIGN KEY constraint")
         .try_into()
         .unwrap();
 
         Ok(id)
     }
 
-    pub async fn update(db: &Database, id: ID, fields: BudgetCategoryFields) -> anyhow::Result<()> {
+    pub async fn update(
+        db: &Database,
+        id: ID,
+        fields: BudgetCategoryFields,
+        write_log_id: ID,
+    ) -> anyhow::Result<()> {
         let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        BudgetCategory::update_with_conn(&mut tx, id, fields, write_log_id).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // Same as above, in transaction of the caller, e.g. undo restoring several rows
+    pub async fn update_with_conn(
+        conn: &mut SqliteConnection,
+        id: ID,
+        fields: BudgetCategoryFields,
+        write_log_id: ID,
+    ) -> anyhow::Result<()> {
+        let kind = SnapshotKind::BudgetCategory;
+        let before = Snapshot::fetch_image(conn, kind, id).await?;
 
         sqlx::query!(
             "UPDATE budget_categories SET name = ?1, ignored = ?2 WHERE id = ?3",
@@ -58,6 +86,8 @@ impl BudgetCategory {
         .execute(&mut *conn)
         .await?;
 
+        Snapshot::record(conn, write_log_id, kind, id, before).await?;
+
         Ok(())
     }
 
@@ -70,23 +100,38 @@ impl BudgetCategory {
         Ok(())
     }
 
-    pub async fn fetch_by_year(db: &Database, year: i32) -> anyhow::Result<Vec<BudgetCategory>> {
+    // Categories of all households the user is member of
+    pub async fn fetch_by_year(
+        db: &Database,
+        username: &str,
+        year: i32,
+    ) -> anyhow::Result<Vec<BudgetCategory>> {
         let mut conn = db.acquire_db_conn().await?;
 
         let results = sqlx::query_as::<_, BudgetCategory>(
-            "SELECT * FROM budget_categories WHERE year = ?1 ORDER BY name",
+            "SELECT * FROM budget_categories
+            WHERE
+              year = ?1
+              AND household_id IN (SELECT household_id FROM household_members WHERE username = ?2)
+            ORDER BY name",
         )
         .bind(year)
+        .bind(username)
         .fetch_all(&mut *conn)
         .await?;
 
         Ok(results)
     }
 
-    pub async fn any_has_year(db: &Database, year: i32) -> anyhow::Result<bool> {
+    pub async fn any_has_household_id_and_year(
+        db: &Database,
+        household_id: ID,
+        year: i32,
+    ) -> anyhow::Result<bool> {
         let mut conn = db.acquire_db_conn().await?;
         let result = sqlx::query_scalar!(
-            "SELECT EXISTS (SELECT 1 FROM budget_categories WHERE year = ?1)",
+            "SELECT EXISTS (SELECT 1 FROM budget_categories WHERE household_id = ?1 AND year = ?2)",
+            household_id,
             year,
         )
         .fetch_one(&mut *conn)
@@ -0,0 +1,147 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::{Acquire, FromRow};
+use ts_rs::TS;
+
+use crate::common::TS_FILE;
+use crate::database::Database;
+use crate::schema::expense::ExpenseFields;
+
+/* Budgets, spending and funds are all kept in home currency. Expenses of accounts in other
+currencies are converted when they're added, keeping the statement amount next to it. */
+pub const HOME_CURRENCY: &str = "USD";
+
+#[derive(Debug, FromRow, Deserialize, Serialize, TS, PartialEq)]
+#[ts(export_to = TS_FILE)]
+pub struct ExchangeRate {
+    pub currency: String,
+    pub date: String, // yyyy-MM-dd
+    pub rate: f64,    // units of home currency for one unit of currency
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct ExchangeRates {
+    pub rates: Vec<ExchangeRate>,
+}
+
+// ISO 4217 codes, e.g. "EUR"
+pub fn looks_like_currency_code(currency: &str) -> bool {
+    let re = Regex::new(r"^[A-Z]{3}$").unwrap();
+
+    re.is_match(currency)
+}
+
+// Keeps the statement amount as original and replaces amount with its home currency value
+pub fn convert_to_home_currency(
+    fields: &mut ExpenseFields,
+    currency: &str,
+    rate: f64,
+) -> Result<(), String> {
+    let converted = match fields.amount.checked_mul_f64(rate) {
+        Some(value) => value,
+        None => {
+            let message = format!(
+                "Amount {} {} can't be converted with rate {}.",
+                fields.amount, currency, rate
+            );
+            return Err(message);
+        }
+    };
+
+    fields.original_currency = Some(currency.to_string());
+    fields.original_amount = Some(fields.amount);
+    fields.amount = converted;
+
+    Ok(())
+}
+
+impl ExchangeRate {
+    // Rates are replaced when they're loaded for the same currency and date again
+    pub async fn upsert_all(db: &Database, rates: &[ExchangeRate]) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        for rate in rates {
+            sqlx::query!(
+                "INSERT INTO exchange_rates (currency, date, rate) VALUES (?1, ?2, ?3)
+                ON CONFLICT(currency, date) DO UPDATE SET rate = excluded.rate",
+                rate.currency,
+                rate.date,
+                rate.rate,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch_all(db: &Database) -> anyhow::Result<ExchangeRates> {
+        let mut conn = db.acquire_db_conn().await?;
+        let results = sqlx::query_as::<_, ExchangeRate>(
+            "SELECT * FROM exchange_rates ORDER BY currency, date",
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(ExchangeRates { rates: results })
+    }
+
+    // Latest rate known on the date, rates aren't published on weekends and holidays
+    pub async fn fetch_rate(
+        db: &Database,
+        currency: &str,
+        date: &str,
+    ) -> anyhow::Result<Option<f64>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query_scalar::<_, f64>(
+            "SELECT rate FROM exchange_rates
+            WHERE currency = ?1 AND date <= ?2
+            ORDER BY date DESC LIMIT 1",
+        )
+        .bind(currency)
+        .bind(date)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::money::Money;
+
+    #[test]
+    fn test_looks_like_currency_code() {
+        assert!(looks_like_currency_code("EUR"));
+        assert!(!looks_like_currency_code("eur"));
+        assert!(!looks_like_currency_code("EURO"));
+        assert!(!looks_like_currency_code(""));
+    }
+
+    #[test]
+    fn test_convert_to_home_currency() {
+        let mut fields = ExpenseFields {
+            account_id: 2,
+            transaction_date: String::from("2025-07-14"),
+            transaction_time: None,
+            description: String::from("Boulangerie"),
+            amount: Money::from_cents(1250),
+            raw_csv: None,
+            balance: None,
+            original_currency: None,
+            original_amount: None,
+        };
+
+        assert!(convert_to_home_currency(&mut fields, "EUR", 1.0837).is_ok());
+        assert_eq!(fields.amount, Money::from_cents(1355));
+        assert_eq!(fields.original_amount, Some(Money::from_cents(1250)));
+        assert_eq!(fields.original_currency, Some(String::from("EUR")));
+        assert_eq!(fields.account_amount(), Money::from_cents(1250));
+    }
+}
@@ -3,6 +3,7 @@ use chrono::offset::Utc;
 use chrono::{DateTime, Datelike, Timelike};
 use chrono_tz::America::Chicago;
 use dateparser::parse_with;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
@@ -32,6 +33,13 @@ fn get_datetime(datetime: &str, tz: &TZ) -> anyhow::Result<DateTime<chrono_tz::T
     Ok(datetime_local)
 }
 
+// Checks the format only, e.g. "2025-02-31" passes
+pub fn looks_like_valid_date(date: &str) -> bool {
+    let re = Regex::new(r"^20\d\d-[01]\d-[0123]\d$").unwrap();
+
+    re.is_match(date)
+}
+
 pub fn to_local_date(datetime: &str, tz: &TZ) -> anyhow::Result<String> {
     let datetime_local = get_datetime(datetime, tz)?;
 
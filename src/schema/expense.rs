@@ -1,9 +1,11 @@
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{Acquire, FromRow, SqliteConnection};
 use ts_rs::TS;
 
 use crate::common::TS_FILE;
 use crate::database::{Database, ID};
+use crate::schema::money::Money;
+use crate::schema::snapshot::{Snapshot, SnapshotKind};
 
 #[derive(Debug, FromRow, Deserialize, Serialize, TS, PartialEq)]
 #[ts(export_to = TS_FILE)]
@@ -12,9 +14,22 @@ pub struct ExpenseFields {
     pub transaction_date: String,
     pub transaction_time: Option<String>,
     pub description: String,
-    pub amount: i32,
+    pub amount: Money,
     #[ts(skip)]
     pub raw_csv: Option<String>,
+    #[ts(optional = nullable)]
+    pub balance: Option<Money>, // as printed on the statement, if its schema maps balance column
+    #[ts(optional = nullable)]
+    pub original_currency: Option<String>, // None for expenses in home currency
+    #[ts(optional = nullable)]
+    pub original_amount: Option<Money>, // amount in original currency, before conversion
+}
+
+impl ExpenseFields {
+    // Amount in currency of the account, i.e. as it's on the statement
+    pub fn account_amount(&self) -> Money {
+        self.original_amount.unwrap_or(self.amount)
+    }
 }
 
 #[derive(Debug, FromRow, Deserialize, Serialize, TS)]
@@ -69,14 +84,20 @@ impl Expense {
               transaction_time,
               description,
               amount,
-              raw_csv
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6) RETURNING id",
+              raw_csv,
+              balance,
+              original_currency,
+              original_amount
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9) RETURNING id",
             fields.account_id,
             fields.transaction_date,
             fields.transaction_time,
             fields.description,
             fields.amount,
             fields.raw_csv,
+            fields.balance,
+            fields.original_currency,
+            fields.original_amount,
         )
         .fetch_one(&mut *conn)
         .await?
@@ -91,8 +112,26 @@ impl Expense {
         db: &Database,
         id: ID,
         budget_item_id: Option<ID>,
+        write_log_id: ID,
     ) -> anyhow::Result<()> {
         let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        Expense::update_budget_item_id_with_conn(&mut tx, id, budget_item_id, write_log_id).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // Same as above, in transaction of the caller, e.g. undo restoring several rows
+    pub async fn update_budget_item_id_with_conn(
+        conn: &mut SqliteConnection,
+        id: ID,
+        budget_item_id: Option<ID>,
+        write_log_id: ID,
+    ) -> anyhow::Result<()> {
+        let kind = SnapshotKind::ExpenseCategory;
+        let before = Snapshot::fetch_image(conn, kind, id).await?;
 
         sqlx::query!(
             "UPDATE expenses SET budget_item_id = ?1 WHERE id = ?2",
@@ -102,16 +141,42 @@ impl Expense {
         .execute(&mut *conn)
         .await?;
 
+        Snapshot::record(conn, write_log_id, kind, id, before).await?;
+
         Ok(())
     }
 
-    pub async fn update_notes(db: &Database, id: ID, notes: Option<String>) -> anyhow::Result<()> {
+    pub async fn update_notes(
+        db: &Database,
+        id: ID,
+        notes: Option<String>,
+        write_log_id: ID,
+    ) -> anyhow::Result<()> {
         let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        Expense::update_notes_with_conn(&mut tx, id, notes, write_log_id).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // Same as above, in transaction of the caller, e.g. undo restoring several rows
+    pub async fn update_notes_with_conn(
+        conn: &mut SqliteConnection,
+        id: ID,
+        notes: Option<String>,
+        write_log_id: ID,
+    ) -> anyhow::Result<()> {
+        let kind = SnapshotKind::ExpenseNotes;
+        let before = Snapshot::fetch_image(conn, kind, id).await?;
 
         sqlx::query!("UPDATE expenses SET notes = ?1 WHERE id = ?2", notes, id)
             .execute(&mut *conn)
             .await?;
 
+        Snapshot::record(conn, write_log_id, kind, id, before).await?;
+
         Ok(())
     }
 
@@ -151,124 +216,15 @@ impl Expense {
         Ok(result)
     }
 
-    pub async fn fetch_by_account_id_and_period(
-        db: &Database,
-        account_id: ID,
-        period: String,
-    ) -> anyhow::Result<Expenses> {
-        let mut conn = db.acquire_db_conn().await?;
-        let results = sqlx::query_as::<_, Expense>(
-            "SELECT * FROM expenses
-            WHERE account_id = ?1
-            AND transaction_date like ?2
-            ORDER BY transaction_date DESC, transaction_time DESC",
-        )
-        .bind(account_id)
-        .bind(format!("{}-%", period))
-        .fetch_all(&mut *conn)
-        .await?;
-
-        Ok(Expenses { expenses: results })
-    }
-
-    pub async fn fetch_by_budget_item_id_and_period(
-        db: &Database,
-        budget_item_id: ID,
-        period: String,
-    ) -> anyhow::Result<Expenses> {
-        let mut conn = db.acquire_db_conn().await?;
-        let results = sqlx::query_as::<_, Expense>(
-            "SELECT * FROM expenses WHERE
-              budget_item_id = ?1
-              AND transaction_date LIKE ?2
-            ORDER BY transaction_date DESC, transaction_time DESC",
-        )
-        .bind(budget_item_id)
-        .bind(format!("{}-%", period))
-        .fetch_all(&mut *conn)
-        .await?;
-
-        Ok(Expenses { expenses: results })
-    }
-
-    pub async fn fetch_by_budget_category_id_and_period(
-        db: &Database,
-        budget_category_id: ID,
-        period: String,
-    ) -> anyhow::Result<Expenses> {
-        let mut conn = db.acquire_db_conn().await?;
-
-        let results = sqlx::query_as::<_, Expense>(
-            "SELECT
-              expenses.*
-            FROM expenses
-            JOIN budget_items
-            ON (expenses.budget_item_id = budget_items.id)
-            WHERE
-              budget_items.category_id = ?1
-              AND expenses.transaction_date LIKE ?2
-            ORDER BY
-              expenses.transaction_date DESC,
-              expenses.transaction_time DESC",
-        )
-        .bind(budget_category_id)
-        .bind(format!("{}-%", period))
-        .fetch_all(&mut *conn)
-        .await?;
-
-        Ok(Expenses { expenses: results })
-    }
-
-    pub async fn fetch_uncategorized_by_period(
-        db: &Database,
-        period: String,
-    ) -> anyhow::Result<Expenses> {
-        let mut conn = db.acquire_db_conn().await?;
-        let results = sqlx::query_as::<_, Expense>(
-            "SELECT * FROM expenses WHERE
-              budget_item_id IS NULL
-              AND transaction_date LIKE ?1
-            ORDER BY transaction_date DESC, transaction_time DESC",
-        )
-        .bind(format!("{}-%", period))
-        .fetch_all(&mut *conn)
-        .await?;
-
-        Ok(Expenses { expenses: results })
-    }
-
-    pub async fn fetch_all_not_ignored_by_period(
-        db: &Database,
-        period: String,
-    ) -> anyhow::Result<Expenses> {
-        let mut conn = db.acquire_db_conn().await?;
-        let results = sqlx::query_as::<_, Expense>(
-            "SELECT
-              expenses.*
-            FROM expenses
-            JOIN view_budget_items
-              ON (expenses.budget_item_id = view_budget_items.id)
-            WHERE
-              view_budget_items.ignored = 0
-              AND expenses.transaction_date LIKE ?1
-            ORDER BY
-              expenses.transaction_date DESC,
-              expenses.transaction_time DESC",
-        )
-        .bind(format!("{}-%", period))
-        .fetch_all(&mut *conn)
-        .await?;
-
-        Ok(Expenses { expenses: results })
-    }
-
+    // Returns expenses as they were imported, with any later edits undone, as that's what they
+    // will look like in the next statement.
     pub async fn fetch_latest_expenses(
         db: &Database,
         account_id: ID,
     ) -> anyhow::Result<Option<LatestExpenses>> {
         let mut conn = db.acquire_db_conn().await?;
         let result = sqlx::query_scalar!(
-            "SELECT transaction_date FROM expenses WHERE account_id = ?1
+            "SELECT transaction_date FROM view_original_expenses WHERE account_id = ?1
             ORDER BY transaction_date DESC LIMIT 1",
             account_id,
         )
@@ -281,7 +237,7 @@ impl Expense {
         };
 
         let transactions = sqlx::query_as::<_, Expense>(
-            "SELECT * FROM expenses WHERE account_id = ?1 AND transaction_date = ?2",
+            "SELECT * FROM view_original_expenses WHERE account_id = ?1 AND transaction_date = ?2",
         )
         .bind(account_id)
         .bind(&date)
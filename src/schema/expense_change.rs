@@ -0,0 +1,318 @@
+use regex::Regex;
+use serde::Serialize;
+use sqlx::{Acquire, FromRow};
+use ts_rs::TS;
+
+use crate::common::TS_FILE;
+use crate::database::{Database, ID};
+use crate::schema::account::AccountType;
+use crate::schema::datetime::looks_like_valid_date;
+use crate::schema::expense::ExpenseFields;
+use crate::schema::money::Money;
+
+#[derive(Debug, FromRow, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct ExpenseOriginal {
+    pub transaction_date: String,
+    pub transaction_time: Option<String>,
+    pub description: String,
+    pub amount: Money,
+}
+
+#[derive(Debug, FromRow, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct ExpenseChange {
+    pub id: ID,
+    pub username: String,
+    #[ts(type = "number")]
+    pub timestamp: i64, // millis since epoch
+    pub old_transaction_date: String,
+    pub old_transaction_time: Option<String>,
+    pub old_description: String,
+    pub old_amount: Money,
+    pub new_transaction_date: String,
+    pub new_transaction_time: Option<String>,
+    pub new_description: String,
+    pub new_amount: Money,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct ExpenseHistory {
+    pub original: Option<ExpenseOriginal>, // None if expense was never edited
+    pub changes: Vec<ExpenseChange>,
+}
+
+fn looks_like_valid_time(time: &str) -> bool {
+    let re = Regex::new(r"^[012]\d:[0-5]\d:[0-5]\d$").unwrap();
+
+    re.is_match(time)
+}
+
+/* Cash expenses are entered by hand, so all of their core fields can be changed. For imported
+expenses, date and time are kept as they were on the statement, as they decide which expenses
+the next import and "delete newer than" operate on; only description and amount can be fixed. */
+pub fn validate_expense_edit(
+    account_type: &AccountType,
+    old: &ExpenseFields,
+    new: &ExpenseFields,
+) -> Result<(), String> {
+    if new.account_id != old.account_id {
+        return Err(String::from(
+            "Expense can't be moved to a different account.",
+        ));
+    }
+
+    if !looks_like_valid_date(&new.transaction_date) {
+        return Err(format!(
+            "Incorrect date '{}', expected 'yyyy-MM-dd'",
+            new.transaction_date
+        ));
+    }
+
+    if let Some(time) = &new.transaction_time {
+        if !looks_like_valid_time(time) {
+            return Err(format!("Incorrect time '{}', expected 'HH:mm:ss'", time));
+        }
+    }
+
+    if new.description.trim().is_empty() {
+        return Err(String::from("Description can't be empty."));
+    }
+
+    if *account_type != AccountType::Cash
+        && (new.transaction_date != old.transaction_date
+            || new.transaction_time != old.transaction_time)
+    {
+        return Err(String::from(
+            "Date and time of imported expenses can't be changed.",
+        ));
+    }
+
+    Ok(())
+}
+
+/* On accounts in other currency the edit carries the amount as on the statement, same as imports.
+Returns whether it has to be converted again; when neither the amount nor date changed, the
+expense keeps its conversion, so that a later loaded rate doesn't change it behind user's back. */
+pub fn prepare_converted_edit(old: &ExpenseFields, new: &mut ExpenseFields) -> bool {
+    if new.amount == old.account_amount() && new.transaction_date == old.transaction_date {
+        new.amount = old.amount;
+        new.original_currency = old.original_currency.clone();
+        new.original_amount = old.original_amount;
+        return false;
+    }
+
+    new.original_currency = None;
+    new.original_amount = None;
+    true
+}
+
+impl ExpenseChange {
+    // Applies the edit to the expense, saving its original values on the first edit
+    pub async fn apply(
+        db: &Database,
+        expense_id: ID,
+        write_log_id: ID,
+        old: &ExpenseFields,
+        new: &ExpenseFields,
+    ) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        sqlx::query!(
+            "INSERT OR IGNORE INTO expense_originals (
+              expense_id,
+              transaction_date,
+              transaction_time,
+              description,
+              amount,
+              original_currency,
+              original_amount
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            expense_id,
+            old.transaction_date,
+            old.transaction_time,
+            old.description,
+            old.amount,
+            old.original_currency,
+            old.original_amount,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE expenses SET
+              transaction_date = ?2,
+              transaction_time = ?3,
+              description = ?4,
+              amount = ?5,
+              original_currency = ?6,
+              original_amount = ?7
+            WHERE id = ?1",
+            expense_id,
+            new.transaction_date,
+            new.transaction_time,
+            new.description,
+            new.amount,
+            new.original_currency,
+            new.original_amount,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "INSERT INTO expense_changes (
+              expense_id,
+              write_log_id,
+              old_transaction_date,
+              old_transaction_time,
+              old_description,
+              old_amount,
+              new_transaction_date,
+              new_transaction_time,
+              new_description,
+              new_amount
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            expense_id,
+            write_log_id,
+            old.transaction_date,
+            old.transaction_time,
+            old.description,
+            old.amount,
+            new.transaction_date,
+            new.transaction_time,
+            new.description,
+            new.amount,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+impl ExpenseHistory {
+    pub async fn fetch_by_expense_id(db: &Database, expense_id: ID) -> anyhow::Result<Self> {
+        let mut conn = db.acquire_db_conn().await?;
+        let original = sqlx::query_as::<_, ExpenseOriginal>(
+            "SELECT transaction_date, transaction_time, description, amount
+            FROM expense_originals WHERE expense_id = ?1",
+        )
+        .bind(expense_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let changes = sqlx::query_as::<_, ExpenseChange>(
+            "SELECT
+              changes.*,
+              write_log.username,
+              write_log.start_ts AS `timestamp`
+            FROM expense_changes changes
+            JOIN write_log
+              ON (changes.write_log_id = write_log.id)
+            WHERE changes.expense_id = ?1
+            ORDER BY changes.id",
+        )
+        .bind(expense_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(ExpenseHistory { original, changes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::currency::convert_to_home_currency;
+
+    fn get_fields() -> ExpenseFields {
+        ExpenseFields {
+            account_id: 3,
+            transaction_date: String::from("2025-04-12"),
+            transaction_time: None,
+            description: String::from("STARBUKCS #123"),
+            amount: Money::from_cents(650),
+            raw_csv: Some(String::from("2025-04-12,650,STARBUKCS #123")),
+            balance: None,
+            original_currency: None,
+            original_amount: None,
+        }
+    }
+
+    #[test]
+    fn test_description_and_amount_editable_for_all_accounts() {
+        let mut new = get_fields();
+        new.description = String::from("Starbucks");
+        new.amount = Money::from_cents(560);
+
+        for account_type in [
+            AccountType::Bank,
+            AccountType::CreditCard,
+            AccountType::Shop,
+            AccountType::Cash,
+        ] {
+            assert!(validate_expense_edit(&account_type, &get_fields(), &new).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_date_and_time_editable_for_cash_only() {
+        let mut new = get_fields();
+        new.transaction_date = String::from("2025-04-11");
+        assert!(validate_expense_edit(&AccountType::Cash, &get_fields(), &new).is_ok());
+        assert!(validate_expense_edit(&AccountType::Bank, &get_fields(), &new).is_err());
+
+        let mut new = get_fields();
+        new.transaction_time = Some(String::from("08:15:00"));
+        assert!(validate_expense_edit(&AccountType::Cash, &get_fields(), &new).is_ok());
+        assert!(validate_expense_edit(&AccountType::CreditCard, &get_fields(), &new).is_err());
+    }
+
+    #[test]
+    fn test_invalid_edits() {
+        let mut new = get_fields();
+        new.account_id = 4;
+        assert!(validate_expense_edit(&AccountType::Cash, &get_fields(), &new).is_err());
+
+        let mut new = get_fields();
+        new.transaction_date = String::from("04/11/2025");
+        assert!(validate_expense_edit(&AccountType::Cash, &get_fields(), &new).is_err());
+
+        let mut new = get_fields();
+        new.transaction_time = Some(String::from("8:15"));
+        assert!(validate_expense_edit(&AccountType::Cash, &get_fields(), &new).is_err());
+
+        let mut new = get_fields();
+        new.description = String::from("  ");
+        assert!(validate_expense_edit(&AccountType::Cash, &get_fields(), &new).is_err());
+    }
+
+    #[test]
+    fn test_prepare_converted_edit() {
+        let mut old = get_fields();
+        convert_to_home_currency(&mut old, "EUR", 1.0837).unwrap();
+        assert_eq!(old.amount, Money::from_cents(704));
+
+        // description only, conversion is kept
+        let mut new = get_fields();
+        new.description = String::from("Starbucks");
+        assert!(!prepare_converted_edit(&old, &mut new));
+        assert_eq!(new.amount, Money::from_cents(704));
+        assert_eq!(new.original_amount, Some(Money::from_cents(650)));
+        assert_eq!(new.original_currency, Some(String::from("EUR")));
+
+        // statement amount changed, both amounts follow
+        let mut new = get_fields();
+        new.amount = Money::from_cents(2000);
+        new.original_amount = Some(Money::from_cents(650)); // stale value sent back by client
+        assert!(prepare_converted_edit(&old, &mut new));
+        convert_to_home_currency(&mut new, "EUR", 1.0837).unwrap();
+        assert_eq!(new.amount, Money::from_cents(2167));
+        assert_eq!(new.original_amount, Some(Money::from_cents(2000)));
+        assert_eq!(new.account_amount(), Money::from_cents(2000));
+    }
+}
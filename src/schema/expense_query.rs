@@ -0,0 +1,353 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, QueryBuilder, Sqlite};
+use ts_rs::TS;
+
+use crate::common::TS_FILE;
+use crate::database::{Database, ID};
+use crate::schema::expense::Expense;
+use crate::schema::household::push_expenses_scope;
+use crate::schema::money::Money;
+use crate::schema::tag::{push_tag_conditions, ExpenseTag};
+
+const MAX_PAGE_SIZE: u32 = 1000;
+
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[serde(tag = "variant")]
+#[ts(export_to = TS_FILE, tag = "variant")]
+pub enum ExpensesQuerySelector {
+    AllNotIgnored,
+    Uncategorized,
+    Account { id: ID },
+    BudgetItem { id: ID },
+    BudgetCategory { id: ID },
+}
+
+/* Expenses are returned newest first, ordered by (transaction_date, transaction_time, id). Cursor
+holds those values for the last expense on the page, and next page starts right after it, so
+pages stay consistent when expenses are added or removed in between requests. */
+#[derive(Debug, Clone, Deserialize, Serialize, TS, PartialEq)]
+#[ts(export_to = TS_FILE)]
+pub struct ExpensesCursor {
+    pub transaction_date: String,
+    pub transaction_time: Option<String>,
+    pub id: ID,
+}
+
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct ExpensesPageRequest {
+    pub after: Option<ExpensesCursor>, // None for the first page
+    pub limit: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct ExpensesQuery {
+    pub period: String, // expected format YYYY or YYYY-mm
+    pub selector: ExpensesQuerySelector,
+    #[ts(optional)]
+    pub page: Option<ExpensesPageRequest>, // None returns all matching expenses
+    #[ts(optional)]
+    pub tag_ids: Option<Vec<ID>>, // expenses have to match all tags
+}
+
+#[derive(Debug, FromRow)]
+struct ExpensesTotals {
+    count: i32,
+    amount: Money,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct ExpensesQueryResult {
+    pub expenses: Vec<Expense>,
+    pub tags: Vec<ExpenseTag>,        // tags of returned expenses
+    pub next: Option<ExpensesCursor>, // None on the last page
+    pub total_count: i32,             // computed for the whole query, not just the page
+    pub total_amount: Money,          // computed for the whole query, not just the page
+}
+
+fn looks_like_valid_period(period: &str) -> bool {
+    let re = Regex::new(r"^20\d\d(-(0\d|1[012]))?$").unwrap();
+
+    re.is_match(period)
+}
+
+impl ExpensesQuery {
+    pub fn validate(&self) -> Result<(), String> {
+        if !looks_like_valid_period(&self.period) {
+            return Err(format!(
+                "Incorrect period: '{}'. Expected 'YYYY[-mm]'",
+                self.period
+            ));
+        }
+
+        if let Some(page) = &self.page {
+            if page.limit == 0 || page.limit > MAX_PAGE_SIZE {
+                return Err(format!(
+                    "Page limit has to be between 1 and {}",
+                    MAX_PAGE_SIZE
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push_conditions(&self, builder: &mut QueryBuilder<'static, Sqlite>, username: &str) {
+        builder.push(" WHERE expenses.transaction_date LIKE ");
+        builder.push_bind(format!("{}-%", self.period));
+
+        match self.selector {
+            ExpensesQuerySelector::AllNotIgnored => builder.push(
+                " AND expenses.budget_item_id IN
+                  (SELECT id FROM view_budget_items WHERE ignored = 0)",
+            ),
+            ExpensesQuerySelector::Uncategorized => {
+                builder.push(" AND expenses.budget_item_id IS NULL")
+            }
+            ExpensesQuerySelector::Account { id } => {
+                builder.push(" AND expenses.account_id = ").push_bind(id)
+            }
+            ExpensesQuerySelector::BudgetItem { id } => builder
+                .push(" AND expenses.budget_item_id = ")
+                .push_bind(id),
+            ExpensesQuerySelector::BudgetCategory { id } => builder
+                .push(
+                    " AND expenses.budget_item_id IN
+                      (SELECT id FROM budget_items WHERE category_id = ",
+                )
+                .push_bind(id)
+                .push(")"),
+        };
+
+        if let Some(tag_ids) = &self.tag_ids {
+            push_tag_conditions(builder, tag_ids);
+        }
+
+        push_expenses_scope(builder, username);
+    }
+
+    fn push_order(builder: &mut QueryBuilder<'static, Sqlite>) {
+        // transaction_time is optional; NULLs sort last, same as empty string would
+        builder.push(
+            " ORDER BY
+              expenses.transaction_date DESC,
+              COALESCE(expenses.transaction_time, '') DESC,
+              expenses.id DESC",
+        );
+    }
+
+    // All matching expenses, ignoring the page. Used for streaming responses.
+    pub fn build_select(&self, username: &str) -> QueryBuilder<'static, Sqlite> {
+        let mut builder = QueryBuilder::new("SELECT expenses.* FROM expenses");
+        self.push_conditions(&mut builder, username);
+        ExpensesQuery::push_order(&mut builder);
+
+        builder
+    }
+
+    fn build_page_select(
+        &self,
+        username: &str,
+        page: &ExpensesPageRequest,
+    ) -> QueryBuilder<'static, Sqlite> {
+        let mut builder = QueryBuilder::new("SELECT expenses.* FROM expenses");
+        self.push_conditions(&mut builder, username);
+
+        if let Some(cursor) = &page.after {
+            builder.push(
+                " AND (
+                  expenses.transaction_date,
+                  COALESCE(expenses.transaction_time, ''),
+                  expenses.id
+                ) < (",
+            );
+            builder.push_bind(cursor.transaction_date.clone());
+            builder.push(", ");
+            builder.push_bind(cursor.transaction_time.clone().unwrap_or_default());
+            builder.push(", ");
+            builder.push_bind(cursor.id);
+            builder.push(")");
+        }
+
+        ExpensesQuery::push_order(&mut builder);
+
+        // fetch one extra row to know whether there is a next page
+        builder.push(" LIMIT ");
+        builder.push_bind(page.limit + 1);
+
+        builder
+    }
+
+    fn build_totals_select(&self, username: &str) -> QueryBuilder<'static, Sqlite> {
+        let mut builder = QueryBuilder::new(
+            "SELECT COUNT(*) AS count, COALESCE(SUM(expenses.amount), 0) AS amount FROM expenses",
+        );
+        self.push_conditions(&mut builder, username);
+
+        builder
+    }
+
+    pub async fn fetch(
+        &self,
+        db: &Database,
+        username: &str,
+    ) -> anyhow::Result<ExpensesQueryResult> {
+        let mut conn = db.acquire_db_conn().await?;
+
+        let totals = self
+            .build_totals_select(username)
+            .build_query_as::<ExpensesTotals>()
+            .fetch_one(&mut *conn)
+            .await?;
+
+        let (expenses, next) = match &self.page {
+            None => {
+                let expenses = self
+                    .build_select(username)
+                    .build_query_as::<Expense>()
+                    .fetch_all(&mut *conn)
+                    .await?;
+
+                (expenses, None)
+            }
+            Some(page) => {
+                let mut expenses = self
+                    .build_page_select(username, page)
+                    .build_query_as::<Expense>()
+                    .fetch_all(&mut *conn)
+                    .await?;
+
+                let limit = page.limit as usize;
+                let next = match expenses.len() > limit {
+                    true => {
+                        expenses.truncate(limit);
+                        expenses.last().map(|last| ExpensesCursor {
+                            transaction_date: last.fields.transaction_date.clone(),
+                            transaction_time: last.fields.transaction_time.clone(),
+                            id: last.id,
+                        })
+                    }
+                    false => None,
+                };
+
+                (expenses, next)
+            }
+        };
+
+        let expense_ids: Vec<ID> = expenses.iter().map(|expense| expense.id).collect();
+        let tags = ExpenseTag::fetch_by_expense_ids(db, &expense_ids).await?;
+
+        Ok(ExpensesQueryResult {
+            expenses,
+            tags,
+            next,
+            total_count: totals.count,
+            total_amount: totals.amount,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_query(selector: ExpensesQuerySelector) -> ExpensesQuery {
+        ExpensesQuery {
+            period: String::from("2025-03"),
+            selector,
+            page: None,
+            tag_ids: None,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(get_query(ExpensesQuerySelector::Uncategorized)
+            .validate()
+            .is_ok());
+
+        let mut query = get_query(ExpensesQuerySelector::Uncategorized);
+        query.period = String::from("2025-13");
+        assert!(query.validate().is_err());
+
+        let mut query = get_query(ExpensesQuerySelector::Uncategorized);
+        query.page = Some(ExpensesPageRequest {
+            after: None,
+            limit: 0,
+        });
+        assert!(query.validate().is_err());
+    }
+
+    #[test]
+    fn test_selector_conditions() {
+        let cases = vec![
+            (
+                ExpensesQuerySelector::AllNotIgnored,
+                "(SELECT id FROM view_budget_items WHERE ignored = 0)",
+            ),
+            (
+                ExpensesQuerySelector::Uncategorized,
+                "AND expenses.budget_item_id IS NULL",
+            ),
+            (
+                ExpensesQuerySelector::Account { id: 1 },
+                "AND expenses.account_id = ?",
+            ),
+            (
+                ExpensesQuerySelector::BudgetItem { id: 1 },
+                "AND expenses.budget_item_id = ?",
+            ),
+            (
+                ExpensesQuerySelector::BudgetCategory { id: 1 },
+                "(SELECT id FROM budget_items WHERE category_id = ?)",
+            ),
+        ];
+
+        for (selector, expected_condition) in cases.into_iter() {
+            let query = get_query(selector);
+            let mut builder = QueryBuilder::new("");
+            query.push_conditions(&mut builder, "tester");
+            let sql = builder.into_sql();
+            assert!(sql.starts_with(" WHERE expenses.transaction_date LIKE ?"));
+            assert!(sql.contains(expected_condition), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_tag_conditions() {
+        let mut query = get_query(ExpensesQuerySelector::AllNotIgnored);
+        query.tag_ids = Some(vec![3, 7]);
+        let mut builder = QueryBuilder::new("");
+        query.push_conditions(&mut builder, "tester");
+        let sql = builder.into_sql();
+        let condition = "expenses.id IN (SELECT expense_id FROM expense_tags WHERE tag_id = ?)";
+        assert_eq!(sql.matches(condition).count(), 2);
+    }
+
+    #[test]
+    fn test_page_select_starts_after_cursor() {
+        let query = get_query(ExpensesQuerySelector::Uncategorized);
+        let first_page = ExpensesPageRequest {
+            after: None,
+            limit: 50,
+        };
+        let sql = query.build_page_select("tester", &first_page).into_sql();
+        assert!(!sql.contains(") < ("));
+        assert!(sql.ends_with(" LIMIT ?"));
+
+        let next_page = ExpensesPageRequest {
+            after: Some(ExpensesCursor {
+                transaction_date: String::from("2025-03-14"),
+                transaction_time: None,
+                id: 42,
+            }),
+            limit: 50,
+        };
+        let sql = query.build_page_select("tester", &next_page).into_sql();
+        assert!(sql.contains(") < (?, ?, ?)"));
+        assert!(sql.ends_with(" LIMIT ?"));
+    }
+}
@@ -0,0 +1,312 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{QueryBuilder, Sqlite};
+use ts_rs::TS;
+
+use crate::common::TS_FILE;
+use crate::database::{Database, ID};
+use crate::schema::datetime::looks_like_valid_date;
+use crate::schema::expense::Expense;
+use crate::schema::household::push_expenses_scope;
+use crate::schema::money::Money;
+use crate::schema::tag::{push_tag_conditions, ExpenseTag};
+
+const DEFAULT_LIMIT: u32 = 100;
+const MAX_LIMIT: u32 = 1000;
+
+#[derive(Debug, Deserialize, Serialize, TS, PartialEq)]
+#[ts(export_to = TS_FILE)]
+pub enum ExpensesSortField {
+    Date,
+    Amount,
+    Description,
+}
+
+/* All filters are optional and combined with AND. Unlike ExpensesQuery, which only selects
+expenses for the expenses page views, this is meant for finding specific expenses. */
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct ExpensesSearch {
+    pub text: Option<String>, // full-text match on description and notes
+    pub min_amount: Option<Money>,
+    pub max_amount: Option<Money>,
+    pub from_date: Option<String>, // inclusive, expected format yyyy-MM-dd
+    pub to_date: Option<String>,   // inclusive, expected format yyyy-MM-dd
+    pub account_ids: Vec<ID>,      // empty matches all accounts
+    pub tag_ids: Vec<ID>,          // expenses have to match all tags
+    pub categorized: Option<bool>,
+    pub has_notes: Option<bool>,
+    pub sort_by: ExpensesSortField,
+    pub descending: bool,
+    pub limit: Option<u32>,
+    pub offset: u32,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct ExpensesSearchResults {
+    pub expenses: Vec<Expense>,
+    pub tags: Vec<ExpenseTag>, // tags of returned expenses
+    pub total: i32,            // all matching expenses, regardless of limit and offset
+}
+
+/* User input can't be passed to FTS5 MATCH verbatim, as quotes, parentheses, AND/OR/NOT etc. are
+part of the query syntax. Every word is quoted instead, and matched as prefix, so "dent" will find
+"Dentist". */
+fn to_fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|word| word.replace('"', ""))
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word))
+        .collect();
+
+    match terms.is_empty() {
+        true => None,
+        false => Some(terms.join(" ")),
+    }
+}
+
+impl ExpensesSearch {
+    pub fn validate(&self) -> Result<(), String> {
+        for date in [&self.from_date, &self.to_date].into_iter().flatten() {
+            if !looks_like_valid_date(date) {
+                return Err(format!("Incorrect date '{}', expected 'yyyy-MM-dd'", date));
+            }
+        }
+
+        if let (Some(min), Some(max)) = (self.min_amount, self.max_amount) {
+            if min > max {
+                return Err(format!("Minimum amount {} exceeds maximum {}", min, max));
+            }
+        }
+
+        if self.limit.unwrap_or(DEFAULT_LIMIT) > MAX_LIMIT {
+            return Err(format!("Limit can't exceed {}", MAX_LIMIT));
+        }
+
+        Ok(())
+    }
+
+    fn push_conditions(&self, builder: &mut QueryBuilder<'_, Sqlite>, username: &str) {
+        builder.push(" WHERE 1 = 1");
+        push_expenses_scope(builder, username);
+
+        if let Some(query) = self.text.as_deref().and_then(to_fts_query) {
+            builder.push(
+                " AND expenses.id IN (SELECT rowid FROM expenses_fts WHERE expenses_fts MATCH ",
+            );
+            builder.push_bind(query);
+            builder.push(")");
+        }
+
+        if let Some(min_amount) = self.min_amount {
+            builder.push(" AND expenses.amount >= ");
+            builder.push_bind(min_amount);
+        }
+
+        if let Some(max_amount) = self.max_amount {
+            builder.push(" AND expenses.amount <= ");
+            builder.push_bind(max_amount);
+        }
+
+        if let Some(from_date) = &self.from_date {
+            builder.push(" AND expenses.transaction_date >= ");
+            builder.push_bind(from_date.clone());
+        }
+
+        if let Some(to_date) = &self.to_date {
+            builder.push(" AND expenses.transaction_date <= ");
+            builder.push_bind(to_date.clone());
+        }
+
+        if !self.account_ids.is_empty() {
+            builder.push(" AND expenses.account_id IN (");
+            let mut separated = builder.separated(", ");
+            for account_id in self.account_ids.iter() {
+                separated.push_bind(*account_id);
+            }
+            builder.push(")");
+        }
+
+        push_tag_conditions(builder, &self.tag_ids);
+
+        match self.categorized {
+            Some(true) => builder.push(" AND expenses.budget_item_id IS NOT NULL"),
+            Some(false) => builder.push(" AND expenses.budget_item_id IS NULL"),
+            None => builder,
+        };
+
+        match self.has_notes {
+            Some(true) => builder.push(" AND COALESCE(expenses.notes, '') != ''"),
+            Some(false) => builder.push(" AND COALESCE(expenses.notes, '') = ''"),
+            None => builder,
+        };
+    }
+
+    fn push_order_and_limit(&self, builder: &mut QueryBuilder<'_, Sqlite>) {
+        let direction = match self.descending {
+            true => "DESC",
+            false => "ASC",
+        };
+
+        let order_by = match self.sort_by {
+            ExpensesSortField::Date => format!(
+                "expenses.transaction_date {0}, expenses.transaction_time {0}",
+                direction
+            ),
+            ExpensesSortField::Amount => format!("expenses.amount {}", direction),
+            ExpensesSortField::Description => {
+                format!("expenses.description COLLATE NOCASE {}", direction)
+            }
+        };
+
+        // id as the last sort key keeps pages stable when sort values repeat
+        builder.push(format!(" ORDER BY {}, expenses.id {}", order_by, direction));
+        builder.push(" LIMIT ");
+        builder.push_bind(self.limit.unwrap_or(DEFAULT_LIMIT));
+        builder.push(" OFFSET ");
+        builder.push_bind(self.offset);
+    }
+
+    pub async fn fetch(
+        &self,
+        db: &Database,
+        username: &str,
+    ) -> anyhow::Result<ExpensesSearchResults> {
+        let mut conn = db.acquire_db_conn().await?;
+
+        let mut count_builder = QueryBuilder::new("SELECT COUNT(*) FROM expenses");
+        self.push_conditions(&mut count_builder, username);
+        let total = count_builder
+            .build_query_scalar::<i32>()
+            .fetch_one(&mut *conn)
+            .await?;
+
+        let mut builder = QueryBuilder::new("SELECT expenses.* FROM expenses");
+        self.push_conditions(&mut builder, username);
+        self.push_order_and_limit(&mut builder);
+        let results = builder
+            .build_query_as::<Expense>()
+            .fetch_all(&mut *conn)
+            .await?;
+
+        let expense_ids: Vec<ID> = results.iter().map(|expense| expense.id).collect();
+        let tags = ExpenseTag::fetch_by_expense_ids(db, &expense_ids).await?;
+
+        Ok(ExpensesSearchResults {
+            expenses: results,
+            tags,
+            total,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_search() -> ExpensesSearch {
+        ExpensesSearch {
+            text: None,
+            min_amount: None,
+            max_amount: None,
+            from_date: None,
+            to_date: None,
+            account_ids: vec![],
+            tag_ids: vec![],
+            categorized: None,
+            has_notes: None,
+            sort_by: ExpensesSortField::Date,
+            descending: true,
+            limit: None,
+            offset: 0,
+        }
+    }
+
+    fn get_sql(search: &ExpensesSearch) -> String {
+        let mut builder = QueryBuilder::new("SELECT expenses.* FROM expenses");
+        search.push_conditions(&mut builder, "tester");
+        search.push_order_and_limit(&mut builder);
+
+        builder.sql().to_string()
+    }
+
+    #[test]
+    fn test_to_fts_query_quotes_words_as_prefixes() {
+        let cases = vec![
+            ("dentist", Some("\"dentist\"*")),
+            ("  dent  spring ", Some("\"dent\"* \"spring\"*")),
+            ("\"quoted\" OR", Some("\"quoted\"* \"OR\"*")),
+            ("   ", None),
+            ("\"\"", None),
+        ];
+
+        for (input, expected) in cases.into_iter() {
+            assert_eq!(to_fts_query(input).as_deref(), expected);
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(get_search().validate().is_ok());
+
+        let mut search = get_search();
+        search.from_date = Some(String::from("2025-04-01"));
+        search.to_date = Some(String::from("04/30/2025"));
+        assert!(search.validate().is_err());
+
+        let mut search = get_search();
+        search.min_amount = Some(Money::from_cents(500));
+        search.max_amount = Some(Money::from_cents(100));
+        assert!(search.validate().is_err());
+
+        let mut search = get_search();
+        search.limit = Some(MAX_LIMIT + 1);
+        assert!(search.validate().is_err());
+    }
+
+    #[test]
+    fn test_no_filters() {
+        let sql = get_sql(&get_search());
+        assert_eq!(
+            sql,
+            "SELECT expenses.* FROM expenses WHERE 1 = 1 \
+            AND expenses.account_id IN (SELECT account_id FROM view_account_access WHERE username = ?) \
+            ORDER BY expenses.transaction_date DESC, expenses.transaction_time DESC, \
+            expenses.id DESC LIMIT ? OFFSET ?"
+        );
+    }
+
+    #[test]
+    fn test_all_filters() {
+        let mut search = get_search();
+        search.text = Some(String::from("dentist"));
+        search.min_amount = Some(Money::from_cents(100));
+        search.max_amount = Some(Money::from_cents(50000));
+        search.from_date = Some(String::from("2025-03-01"));
+        search.to_date = Some(String::from("2025-05-31"));
+        search.account_ids = vec![1, 3];
+        search.tag_ids = vec![2];
+        search.categorized = Some(false);
+        search.has_notes = Some(true);
+        search.sort_by = ExpensesSortField::Amount;
+        search.descending = false;
+
+        let sql = get_sql(&search);
+        assert_eq!(
+            sql,
+            "SELECT expenses.* FROM expenses WHERE 1 = 1 \
+            AND expenses.account_id IN (SELECT account_id FROM view_account_access WHERE username = ?) \
+            AND expenses.id IN (SELECT rowid FROM expenses_fts WHERE expenses_fts MATCH ?) \
+            AND expenses.amount >= ? \
+            AND expenses.amount <= ? \
+            AND expenses.transaction_date >= ? \
+            AND expenses.transaction_date <= ? \
+            AND expenses.account_id IN (?, ?) \
+            AND expenses.id IN (SELECT expense_id FROM expense_tags WHERE tag_id = ?) \
+            AND expenses.budget_item_id IS NULL \
+            AND COALESCE(expenses.notes, '') != '' \
+            ORDER BY expenses.amount ASC, expenses.id ASC LIMIT ? OFFSET ?"
+        );
+    }
+}
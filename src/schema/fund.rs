@@ -1,9 +1,10 @@
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{Acquire, FromRow, SqliteConnection};
 use ts_rs::TS;
 
 use crate::common::TS_FILE;
 use crate::database::{Database, ID};
+use crate::schema::snapshot::{Snapshot, SnapshotKind};
 
 #[derive(Debug, FromRow, Deserialize, Serialize, TS)]
 #[ts(export_to = TS_FILE)]
@@ -15,6 +16,7 @@ pub struct FundFields {
 #[ts(export_to = TS_FILE)]
 pub struct Fund {
     pub id: ID,
+    pub household_id: ID, // immutable after creation
 
     #[serde(flatten)]
     #[sqlx(flatten)]
@@ -23,26 +25,53 @@ pub struct Fund {
 }
 
 impl Fund {
-    pub async fn create(db: &Database, fields: FundFields) -> anyhow::Result<ID> {
+    pub async fn create(db: &Database, household_id: ID, fields: FundFields) -> anyhow::Result<ID> {
         let mut conn = db.acquire_db_conn().await?;
         let id: ID = sqlx::query_scalar!(
-            "INSERT INTO funds (name) VALUES (?1) RETURNING id",
+            "INSERT INTO funds (household_id, name) VALUES (?1, ?2) RETURNING id",
+            household_id,
             fields.name,
         )
         .fetch_one(&mut *conn)
         .await?
+        .expect("INSERT failed, likely FOREIGN KEY constraint")
         .try_into()
         .unwrap();
 
         Ok(id)
     }
 
-    pub async fn update(db: &Database, id: ID, fields: FundFields) -> anyhow::Result<()> {
+    pub async fn update(
+        db: &Database,
+        id: ID,
+        fields: FundFields,
+        write_log_id: ID,
+    ) -> anyhow::Result<()> {
         let mut conn = db.acquire_db_conn().await?;
-        sqlx::query!("UPDATE funds SET name = ?1 WHERE id = ?2", fields.name, id,)
+        let mut tx = conn.begin().await?;
+
+        Fund::update_with_conn(&mut tx, id, fields, write_log_id).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // Same as above, in transaction of the caller, e.g. undo restoring several rows
+    pub async fn update_with_conn(
+        conn: &mut SqliteConnection,
+        id: ID,
+        fields: FundFields,
+        write_log_id: ID,
+    ) -> anyhow::Result<()> {
+        let kind = SnapshotKind::Fund;
+        let before = Snapshot::fetch_image(conn, kind, id).await?;
+
+        sqlx::query!("UPDATE funds SET name = ?1 WHERE id = ?2", fields.name, id)
             .execute(&mut *conn)
             .await?;
 
+        Snapshot::record(conn, write_log_id, kind, id, before).await?;
+
         Ok(())
     }
 
@@ -55,11 +84,17 @@ impl Fund {
         Ok(())
     }
 
-    pub async fn fetch_all(db: &Database) -> anyhow::Result<Vec<Fund>> {
+    // Funds of all households the user is member of
+    pub async fn fetch_all(db: &Database, username: &str) -> anyhow::Result<Vec<Fund>> {
         let mut conn = db.acquire_db_conn().await?;
-        let results = sqlx::query_as::<_, Fund>("SELECT * FROM funds ORDER BY name")
-            .fetch_all(&mut *conn)
-            .await?;
+        let results = sqlx::query_as::<_, Fund>(
+            "SELECT * FROM funds
+            WHERE household_id IN (SELECT household_id FROM household_members WHERE username = ?1)
+            ORDER BY name",
+        )
+        .bind(username)
+        .fetch_all(&mut *conn)
+        .await?;
 
         Ok(results)
     }
@@ -0,0 +1,383 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Acquire, FromRow, QueryBuilder, Sqlite};
+use ts_rs::TS;
+
+use crate::common::TS_FILE;
+use crate::database::{Database, ID};
+
+/* Accounts, budget categories and funds belong to a household, and users see data of households
+they are members of. Account can also have an owner, who can edit it without being a member, so
+a teenager can get their own account in the family household, without seeing the rest of it.
+Expenses, budget items and planned expenses follow the account or category they belong to. */
+
+// Ordered by what the role allows, so the best of several roles is the max
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, TS, PartialEq, Eq, PartialOrd, Ord)]
+#[ts(export_to = TS_FILE)]
+pub enum HouseholdRole {
+    Viewer,
+    Editor,
+    Owner, // can also manage members
+}
+
+impl HouseholdRole {
+    pub fn can_edit(self) -> bool {
+        self >= HouseholdRole::Editor
+    }
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct HouseholdFields {
+    pub name: String,
+}
+
+// Household as seen by one of its members
+#[derive(Debug, FromRow, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct Household {
+    pub id: ID,
+    pub role: HouseholdRole,
+
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    #[ts(flatten)]
+    pub fields: HouseholdFields,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct Households {
+    pub households: Vec<Household>,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct HouseholdMember {
+    pub username: String,
+    pub role: HouseholdRole,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct HouseholdMembers {
+    pub members: Vec<HouseholdMember>,
+}
+
+// Limits expenses of the query to accounts the user has access to
+pub fn push_expenses_scope(builder: &mut QueryBuilder<'_, Sqlite>, username: &str) {
+    builder.push(
+        " AND expenses.account_id IN (SELECT account_id FROM view_account_access WHERE username = ",
+    );
+    builder.push_bind(username.to_string());
+    builder.push(")");
+}
+
+impl Household {
+    // Creator becomes the owner
+    pub async fn create(
+        db: &Database,
+        username: &str,
+        fields: HouseholdFields,
+    ) -> anyhow::Result<ID> {
+        let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        let id: ID = sqlx::query_scalar!(
+            "INSERT INTO households (name) VALUES (?1) RETURNING id",
+            fields.name,
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .try_into()
+        .unwrap();
+
+        sqlx::query!(
+            "INSERT INTO household_members (household_id, username, role) VALUES (?1, ?2, ?3)",
+            id,
+            username,
+            HouseholdRole::Owner,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(id)
+    }
+
+    pub async fn update(db: &Database, id: ID, fields: HouseholdFields) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query!(
+            "UPDATE households SET name = ?2 WHERE id = ?1",
+            id,
+            fields.name,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch_by_username(db: &Database, username: &str) -> anyhow::Result<Households> {
+        let mut conn = db.acquire_db_conn().await?;
+        let results = sqlx::query_as::<_, Household>(
+            "SELECT households.id, households.name, household_members.role
+            FROM households
+            JOIN household_members ON (households.id = household_members.household_id)
+            WHERE household_members.username = ?1
+            ORDER BY households.name",
+        )
+        .bind(username)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(Households {
+            households: results,
+        })
+    }
+}
+
+impl HouseholdMember {
+    // Adds the user, or changes their role when they're already a member
+    pub async fn upsert(
+        db: &Database,
+        household_id: ID,
+        member: &HouseholdMember,
+    ) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query!(
+            "INSERT INTO household_members (household_id, username, role) VALUES (?1, ?2, ?3)
+            ON CONFLICT(household_id, username) DO UPDATE SET role = excluded.role",
+            household_id,
+            member.username,
+            member.role,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(db: &Database, household_id: ID, username: &str) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query!(
+            "DELETE FROM household_members WHERE household_id = ?1 AND username = ?2",
+            household_id,
+            username,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch_by_household_id(
+        db: &Database,
+        household_id: ID,
+    ) -> anyhow::Result<HouseholdMembers> {
+        let mut conn = db.acquire_db_conn().await?;
+        let results = sqlx::query_as::<_, HouseholdMember>(
+            "SELECT username, role FROM household_members
+            WHERE household_id = ?1
+            ORDER BY username",
+        )
+        .bind(household_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(HouseholdMembers { members: results })
+    }
+}
+
+/* Role of the user for single record, None when they can't see it at all, or it doesn't exist.
+Queries return one row per way the user has access, e.g. being both member and account owner. */
+impl HouseholdRole {
+    async fn fetch_best(
+        db: &Database,
+        query: &'static str,
+        username: &str,
+        id: ID,
+    ) -> anyhow::Result<Option<HouseholdRole>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let roles = sqlx::query_scalar::<_, HouseholdRole>(query)
+            .bind(username)
+            .bind(id)
+            .fetch_all(&mut *conn)
+            .await?;
+
+        Ok(roles.into_iter().max())
+    }
+
+    pub async fn fetch_for_household(
+        db: &Database,
+        username: &str,
+        id: ID,
+    ) -> anyhow::Result<Option<HouseholdRole>> {
+        let query = "SELECT role FROM household_members WHERE username = ?1 AND household_id = ?2";
+
+        HouseholdRole::fetch_best(db, query, username, id).await
+    }
+
+    pub async fn fetch_for_account(
+        db: &Database,
+        username: &str,
+        id: ID,
+    ) -> anyhow::Result<Option<HouseholdRole>> {
+        let query = "SELECT role FROM view_account_access WHERE username = ?1 AND account_id = ?2";
+
+        HouseholdRole::fetch_best(db, query, username, id).await
+    }
+
+    pub async fn fetch_for_expense(
+        db: &Database,
+        username: &str,
+        id: ID,
+    ) -> anyhow::Result<Option<HouseholdRole>> {
+        let query = "SELECT role FROM view_account_access
+            WHERE username = ?1 AND account_id = (SELECT account_id FROM expenses WHERE id = ?2)";
+
+        HouseholdRole::fetch_best(db, query, username, id).await
+    }
+
+    pub async fn fetch_for_category(
+        db: &Database,
+        username: &str,
+        id: ID,
+    ) -> anyhow::Result<Option<HouseholdRole>> {
+        let query = "SELECT role FROM household_members
+            WHERE username = ?1
+              AND household_id = (SELECT household_id FROM budget_categories WHERE id = ?2)";
+
+        HouseholdRole::fetch_best(db, query, username, id).await
+    }
+
+    pub async fn fetch_for_budget_item(
+        db: &Database,
+        username: &str,
+        id: ID,
+    ) -> anyhow::Result<Option<HouseholdRole>> {
+        let query = "SELECT role FROM household_members
+            WHERE username = ?1
+              AND household_id = (SELECT household_id FROM view_budget_items WHERE id = ?2)";
+
+        HouseholdRole::fetch_best(db, query, username, id).await
+    }
+
+    pub async fn fetch_for_fund(
+        db: &Database,
+        username: &str,
+        id: ID,
+    ) -> anyhow::Result<Option<HouseholdRole>> {
+        let query = "SELECT role FROM household_members
+            WHERE username = ?1 AND household_id = (SELECT household_id FROM funds WHERE id = ?2)";
+
+        HouseholdRole::fetch_best(db, query, username, id).await
+    }
+
+    pub async fn fetch_for_tag(
+        db: &Database,
+        username: &str,
+        id: ID,
+    ) -> anyhow::Result<Option<HouseholdRole>> {
+        let query = "SELECT role FROM household_members
+            WHERE username = ?1 AND household_id = (SELECT household_id FROM tags WHERE id = ?2)";
+
+        HouseholdRole::fetch_best(db, query, username, id).await
+    }
+
+    pub async fn fetch_for_planned_expense(
+        db: &Database,
+        username: &str,
+        id: ID,
+    ) -> anyhow::Result<Option<HouseholdRole>> {
+        let query = "SELECT role FROM household_members
+            WHERE username = ?1
+              AND household_id = (
+                SELECT household_id FROM view_budget_items
+                WHERE id = (SELECT budget_item_id FROM planned_expenses WHERE id = ?2)
+              )";
+
+        HouseholdRole::fetch_best(db, query, username, id).await
+    }
+
+    pub async fn fetch_for_reconciliation(
+        db: &Database,
+        username: &str,
+        id: ID,
+    ) -> anyhow::Result<Option<HouseholdRole>> {
+        let query = "SELECT role FROM view_account_access
+            WHERE username = ?1
+              AND account_id = (SELECT account_id FROM reconciliations WHERE id = ?2)";
+
+        HouseholdRole::fetch_best(db, query, username, id).await
+    }
+
+    pub async fn fetch_for_attachment(
+        db: &Database,
+        username: &str,
+        id: ID,
+    ) -> anyhow::Result<Option<HouseholdRole>> {
+        let query = "SELECT role FROM view_account_access
+            WHERE username = ?1
+              AND account_id = (
+                SELECT account_id FROM expenses
+                WHERE id = (SELECT expense_id FROM attachments WHERE id = ?2)
+              )";
+
+        HouseholdRole::fetch_best(db, query, username, id).await
+    }
+
+    // Households where the user can add new accounts, categories, funds and tags
+    pub async fn fetch_editable_household_ids(
+        db: &Database,
+        username: &str,
+    ) -> anyhow::Result<Vec<ID>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let results = sqlx::query_scalar::<_, ID>(
+            "SELECT household_id FROM household_members
+            WHERE username = ?1 AND role IN (?2, ?3)
+            ORDER BY household_id",
+        )
+        .bind(username)
+        .bind(HouseholdRole::Editor)
+        .bind(HouseholdRole::Owner)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_can_edit() {
+        assert!(HouseholdRole::Owner.can_edit());
+        assert!(HouseholdRole::Editor.can_edit());
+        assert!(!HouseholdRole::Viewer.can_edit());
+        assert_eq!(
+            vec![
+                HouseholdRole::Editor,
+                HouseholdRole::Owner,
+                HouseholdRole::Viewer
+            ]
+            .into_iter()
+            .max(),
+            Some(HouseholdRole::Owner)
+        );
+    }
+
+    #[test]
+    fn test_push_expenses_scope() {
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT * FROM expenses WHERE 1 = 1");
+        push_expenses_scope(&mut builder, "teen");
+
+        assert_eq!(
+            builder.sql(),
+            "SELECT * FROM expenses WHERE 1 = 1 \
+            AND expenses.account_id IN (SELECT account_id FROM view_account_access WHERE username = ?)"
+        );
+    }
+}
@@ -1,19 +1,33 @@
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{Acquire, FromRow, QueryBuilder, SqliteConnection};
 use ts_rs::TS;
 
 use crate::common::TS_FILE;
 use crate::database::{Database, ID};
-
-type CentAmount = i32;
+use crate::schema::money::Money;
+use crate::schema::snapshot::{Snapshot, SnapshotKind};
 
 #[derive(Debug, Deserialize, Serialize, TS)]
 #[serde(tag = "variant", content = "amount")]
 #[ts(export_to = TS_FILE, tag = "variant", content = "amount")]
 pub enum Allowance {
-    Weekly(CentAmount),
-    Monthly(CentAmount),
-    Yearly(CentAmount),
+    Weekly(Money),
+    Monthly(Money),
+    Yearly(Money),
+}
+
+impl Allowance {
+    pub fn amount_per_year(&self) -> Money {
+        match self {
+            Allowance::Weekly(amount) => *amount * 52,
+            Allowance::Monthly(amount) => *amount * 12,
+            Allowance::Yearly(amount) => *amount,
+        }
+    }
+
+    pub fn amount_per_month(&self) -> Money {
+        self.amount_per_year() / 12
+    }
 }
 
 #[derive(Debug, FromRow, Deserialize, Serialize, TS)]
@@ -43,7 +57,7 @@ pub struct BudgetItem {
 #[ts(export_to = TS_FILE)]
 pub struct BudgetItemWithSpend {
     pub year: i32, // computed, inherited from Category
-    pub spend: CentAmount,
+    pub spend: Money,
 
     #[serde(flatten)]
     #[sqlx(flatten)]
@@ -78,8 +92,30 @@ impl BudgetItem {
         Ok(id)
     }
 
-    pub async fn update(db: &Database, id: ID, fields: BudgetItemFields) -> anyhow::Result<()> {
+    pub async fn update(
+        db: &Database,
+        id: ID,
+        fields: BudgetItemFields,
+        write_log_id: ID,
+    ) -> anyhow::Result<()> {
         let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        BudgetItem::update_with_conn(&mut tx, id, fields, write_log_id).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // Same as above, in transaction of the caller, e.g. undo restoring several rows
+    pub async fn update_with_conn(
+        conn: &mut SqliteConnection,
+        id: ID,
+        fields: BudgetItemFields,
+        write_log_id: ID,
+    ) -> anyhow::Result<()> {
+        let kind = SnapshotKind::BudgetItem;
+        let before = Snapshot::fetch_image(conn, kind, id).await?;
 
         sqlx::query!(
             "UPDATE budget_items SET
@@ -99,6 +135,8 @@ impl BudgetItem {
         .execute(&mut *conn)
         .await?;
 
+        Snapshot::record(conn, write_log_id, kind, id, before).await?;
+
         Ok(())
     }
 
@@ -111,38 +149,110 @@ impl BudgetItem {
         Ok(())
     }
 
-    pub async fn fetch_by_year(db: &Database, year: i32) -> anyhow::Result<Vec<BudgetItem>> {
+    // Items of all households the user is member of
+    pub async fn fetch_by_year(
+        db: &Database,
+        username: &str,
+        year: i32,
+    ) -> anyhow::Result<Vec<BudgetItem>> {
         let mut conn = db.acquire_db_conn().await?;
         let results = sqlx::query_as::<_, BudgetItem>(
             "SELECT * FROM view_budget_items
-            WHERE year = ?1
+            WHERE
+              year = ?1
+              AND household_id IN (SELECT household_id FROM household_members WHERE username = ?2)
             ORDER BY category_id, name",
         )
         .bind(year)
+        .bind(username)
         .fetch_all(&mut *conn)
         .await?;
 
         Ok(results)
     }
 
-    pub async fn fetch_all_fund_items(db: &Database) -> anyhow::Result<Vec<BudgetItemWithSpend>> {
+    pub async fn fetch_by_ids(db: &Database, ids: &[ID]) -> anyhow::Result<Vec<BudgetItem>> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut conn = db.acquire_db_conn().await?;
+        let mut builder = QueryBuilder::new("SELECT * FROM view_budget_items WHERE id IN (");
+        let mut separated = builder.separated(", ");
+        for id in ids {
+            separated.push_bind(*id);
+        }
+        builder.push(")");
+
+        let results = builder
+            .build_query_as::<BudgetItem>()
+            .fetch_all(&mut *conn)
+            .await?;
+
+        Ok(results)
+    }
+
+    pub async fn fetch_all_fund_items(
+        db: &Database,
+        username: &str,
+    ) -> anyhow::Result<Vec<BudgetItemWithSpend>> {
         let mut conn = db.acquire_db_conn().await?;
         let result = sqlx::query_as::<_, BudgetItemWithSpend>(
             "SELECT
               items.*,
-              SUM(expenses.amount) AS spend
+              SUM(spend.amount) AS spend
             FROM view_budget_items items
-            LEFT JOIN expenses
-              ON (items.id = expenses.budget_item_id)
-            WHERE fund_id IS NOT NULL
+            LEFT JOIN view_expense_spend spend
+              ON (items.id = spend.budget_item_id)
+            WHERE
+              fund_id IS NOT NULL
+              AND household_id IN (SELECT household_id FROM household_members WHERE username = ?1)
             GROUP BY items.id",
         )
+        .bind(username)
         .fetch_all(&mut *conn)
         .await?;
 
         Ok(result)
     }
 
+    // Expenses are only categorized with items of their own household
+    pub async fn is_in_household_of_expense(
+        db: &Database,
+        id: ID,
+        expense_id: ID,
+    ) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        BudgetItem::is_in_household_of_expense_with_conn(&mut conn, id, expense_id).await
+    }
+
+    // Same as above, in transaction of the caller, e.g. undo restoring several rows
+    pub async fn is_in_household_of_expense_with_conn(
+        conn: &mut SqliteConnection,
+        id: ID,
+        expense_id: ID,
+    ) -> anyhow::Result<bool> {
+        let result = sqlx::query_scalar!(
+            "SELECT EXISTS (
+              SELECT 1 FROM expenses
+              JOIN accounts ON (expenses.account_id = accounts.id)
+              JOIN budget_categories ON (budget_categories.household_id = accounts.household_id)
+              JOIN budget_items ON (budget_items.category_id = budget_categories.id)
+              WHERE expenses.id = ?1 AND budget_items.id = ?2
+            )",
+            expense_id,
+            id,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if result == 0 {
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
     pub async fn any_has_category_id(db: &Database, id: ID) -> anyhow::Result<bool> {
         let mut conn = db.acquire_db_conn().await?;
         let result = sqlx::query_scalar!(
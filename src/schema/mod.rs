@@ -1,12 +1,27 @@
 pub mod account;
+pub mod attachment;
+pub mod balance;
 pub mod budget;
 pub mod category;
+pub mod currency;
 pub mod datetime;
 pub mod expense;
+pub mod expense_change;
+pub mod expense_query;
+pub mod expense_search;
 pub mod fund;
+pub mod household;
 pub mod item;
+pub mod money;
+pub mod planned_expense;
 pub mod record_mapping;
+pub mod recurring;
+pub mod reimbursement;
+pub mod snapshot;
 pub mod spending_data;
 pub mod sqlx_enum;
 pub mod statement_schema;
 pub mod statement_schema_test;
+pub mod tag;
+pub mod token_scope;
+pub mod user_role;
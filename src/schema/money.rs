@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+use ts_rs::TS;
+
+use crate::common::TS_FILE;
+
+/* Amount of money in cents, positive for spend. Sums over years of expenses are nowhere near
+i64 limits, so overflow means a bug or garbage input; operators panic instead of wrapping, and
+checked_* variants are there for values coming from outside, e.g. parsed statements. Newtype
+serializes as plain number of cents. */
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize, TS,
+)]
+#[ts(export_to = TS_FILE, type = "number")]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    pub const fn from_cents(cents: i64) -> Money {
+        Money(cents)
+    }
+
+    pub const fn cents(self) -> i64 {
+        self.0
+    }
+
+    pub fn checked_abs(self) -> Option<Money> {
+        self.0.checked_abs().map(Money)
+    }
+
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        self.0.checked_add(other.0).map(Money)
+    }
+
+    pub fn checked_sub(self, other: Money) -> Option<Money> {
+        self.0.checked_sub(other.0).map(Money)
+    }
+
+    pub fn checked_mul(self, factor: i64) -> Option<Money> {
+        self.0.checked_mul(factor).map(Money)
+    }
+
+    pub fn checked_div(self, divisor: i64) -> Option<Money> {
+        self.0.checked_div(divisor).map(Money)
+    }
+
+    // Rounds to the nearest cent; None when result doesn't fit, e.g. for NaN or huge rate
+    pub fn checked_mul_f64(self, factor: f64) -> Option<Money> {
+        from_f64_cents(self.0 as f64 * factor)
+    }
+
+    // Parses decimal amount in whole units, e.g. 12.34, into cents
+    pub fn checked_from_units(units: f64) -> Option<Money> {
+        from_f64_cents(units * 100.)
+    }
+}
+
+fn from_f64_cents(cents: f64) -> Option<Money> {
+    let rounded = cents.round();
+    // i64::MAX as f64 rounds up to 2^63, which is already out of range
+    if !rounded.is_finite() || rounded >= i64::MAX as f64 || rounded < i64::MIN as f64 {
+        return None;
+    }
+
+    Some(Money(rounded as i64))
+}
+
+impl From<i64> for Money {
+    fn from(cents: i64) -> Money {
+        Money(cents)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        self.checked_add(other).expect("Money overflow")
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        self.checked_sub(other).expect("Money overflow")
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        *self = *self - other;
+    }
+}
+
+impl Mul<i64> for Money {
+    type Output = Money;
+
+    fn mul(self, factor: i64) -> Money {
+        self.checked_mul(factor).expect("Money overflow")
+    }
+}
+
+// Rounds towards zero, like integer division
+impl Div<i64> for Money {
+    type Output = Money;
+
+    fn div(self, divisor: i64) -> Money {
+        self.checked_div(divisor).expect("Money overflow")
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(self.0.checked_neg().expect("Money overflow"))
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, |total, amount| total + amount)
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        iter.copied().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let max = Money::from_cents(i64::MAX);
+        assert_eq!(max.checked_add(Money::from_cents(1)), None);
+        assert_eq!(
+            Money::from_cents(i64::MIN).checked_sub(Money::from_cents(1)),
+            None
+        );
+        assert_eq!(max.checked_mul(2), None);
+        assert_eq!(
+            Money::from_cents(1200).checked_div(12),
+            Some(Money::from_cents(100))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Money overflow")]
+    fn test_add_panics_on_overflow() {
+        let _ = Money::from_cents(i64::MAX) + Money::from_cents(1);
+    }
+
+    #[test]
+    fn test_checked_from_units() {
+        assert_eq!(Money::checked_from_units(0.69), Some(Money::from_cents(69)));
+        assert_eq!(
+            Money::checked_from_units(-12345.67),
+            Some(Money::from_cents(-1234567))
+        );
+        // more than i32 could hold
+        assert_eq!(
+            Money::checked_from_units(50_000_000.),
+            Some(Money::from_cents(5_000_000_000))
+        );
+        assert_eq!(Money::checked_from_units(1e30), None);
+        assert_eq!(Money::checked_from_units(f64::NAN), None);
+    }
+
+    #[test]
+    fn test_serializes_as_number() {
+        let amount = Money::from_cents(-1250);
+        assert_eq!(serde_json::to_string(&amount).unwrap(), "-1250");
+        assert_eq!(serde_json::from_str::<Money>("-1250").unwrap(), amount);
+        assert_eq!(Money::inline(), "number");
+    }
+}
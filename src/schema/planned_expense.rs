@@ -0,0 +1,369 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use ts_rs::TS;
+
+use crate::common::TS_FILE;
+use crate::database::{Database, ID};
+use crate::schema::expense::Expense;
+use crate::schema::money::Money;
+
+// Imported transaction can post this many days before or after the expected date
+const RECONCILE_WINDOW_DAYS: i64 = 14;
+// and its amount can differ from the planned one by this much, e.g. due to fees
+const RECONCILE_AMOUNT_PERCENT: i64 = 5;
+// a billion, well below overflow of the amount comparison
+const MAX_PLANNED_AMOUNT: Money = Money::from_cents(100_000_000_000);
+
+#[derive(Debug, FromRow, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct PlannedExpenseFields {
+    pub budget_item_id: ID,
+    pub expected_date: String, // yyyy-MM-dd
+    pub amount: Money,
+    pub description: String,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct PlannedExpense {
+    pub id: ID,
+    pub expense_id: Option<ID>, // set once reconciled with actual expense
+
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    #[ts(flatten)]
+    pub fields: PlannedExpenseFields,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct PlannedExpenses {
+    pub planned_expenses: Vec<PlannedExpense>,
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+pub fn validate_planned_expense(fields: &PlannedExpenseFields) -> Result<(), String> {
+    if parse_date(&fields.expected_date).is_none() {
+        return Err(format!(
+            "Incorrect date '{}', expected 'yyyy-MM-dd'",
+            fields.expected_date
+        ));
+    }
+
+    if fields.amount <= Money::ZERO {
+        return Err(String::from("Planned amount has to be positive."));
+    }
+
+    if fields.amount > MAX_PLANNED_AMOUNT {
+        return Err(format!(
+            "Planned amount can't be more than {}.",
+            MAX_PLANNED_AMOUNT
+        ));
+    }
+
+    if fields.description.trim().is_empty() {
+        return Err(String::from("Description can't be empty."));
+    }
+
+    Ok(())
+}
+
+/* Picks the expense closest to the expected date, among those within the reconcile window and
+with amount close enough to the planned one. Ties go to the closer amount. */
+pub fn find_matching_expense(planned: &PlannedExpenseFields, candidates: &[Expense]) -> Option<ID> {
+    let expected_date = parse_date(&planned.expected_date)?;
+
+    candidates
+        .iter()
+        .filter_map(|expense| {
+            let date = parse_date(&expense.fields.transaction_date)?;
+            let days = (date - expected_date).num_days().abs();
+            // amounts of imported expenses aren't limited, overflow is just not a match
+            let amount_diff = expense
+                .fields
+                .amount
+                .checked_sub(planned.amount)?
+                .checked_abs()?;
+            let tolerance = planned
+                .amount
+                .checked_abs()?
+                .checked_mul(RECONCILE_AMOUNT_PERCENT)?;
+
+            let matches =
+                days <= RECONCILE_WINDOW_DAYS && amount_diff.checked_mul(100)? <= tolerance;
+            match matches {
+                true => Some((days, amount_diff, expense.id)),
+                false => None,
+            }
+        })
+        .min()
+        .map(|(_, _, id)| id)
+}
+
+impl PlannedExpense {
+    pub async fn create(db: &Database, fields: PlannedExpenseFields) -> anyhow::Result<ID> {
+        let mut conn = db.acquire_db_conn().await?;
+        let id: ID = sqlx::query_scalar!(
+            "INSERT INTO planned_expenses (
+              budget_item_id,
+              expected_date,
+              amount,
+              description
+            ) VALUES (?1, ?2, ?3, ?4) RETURNING id",
+            fields.budget_item_id,
+            fields.expected_date,
+            fields.amount,
+            fields.description,
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .expect("INSERT failed, likely FOREIGN KEY constraint")
+        .try_into()
+        .unwrap();
+
+        Ok(id)
+    }
+
+    pub async fn update(db: &Database, id: ID, fields: PlannedExpenseFields) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query!(
+            "UPDATE planned_expenses SET
+              budget_item_id = ?2,
+              expected_date = ?3,
+              amount = ?4,
+              description = ?5
+            WHERE id = ?1",
+            id,
+            fields.budget_item_id,
+            fields.expected_date,
+            fields.amount,
+            fields.description,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(db: &Database, id: ID) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query!("DELETE FROM planned_expenses WHERE id = ?1", id)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(())
+    }
+
+    // None unlinks the planned expense, making it committed spend again
+    pub async fn update_expense_id(
+        db: &Database,
+        id: ID,
+        expense_id: Option<ID>,
+    ) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query!(
+            "UPDATE planned_expenses SET expense_id = ?2 WHERE id = ?1",
+            id,
+            expense_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    // Planned expenses of all households the user is member of
+    pub async fn fetch_by_year(
+        db: &Database,
+        username: &str,
+        year: i32,
+    ) -> anyhow::Result<PlannedExpenses> {
+        let mut conn = db.acquire_db_conn().await?;
+        let results = sqlx::query_as::<_, PlannedExpense>(
+            "SELECT * FROM planned_expenses
+            WHERE
+              SUBSTR(expected_date, 1, 4) = ?1
+              AND budget_item_id IN (
+                SELECT id FROM view_budget_items
+                WHERE household_id IN
+                  (SELECT household_id FROM household_members WHERE username = ?2)
+              )
+            ORDER BY expected_date, id",
+        )
+        .bind(year.to_string())
+        .bind(username)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(PlannedExpenses {
+            planned_expenses: results,
+        })
+    }
+
+    pub async fn any_has_budget_item_id(db: &Database, id: ID) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query_scalar!(
+            "SELECT EXISTS (SELECT 1 FROM planned_expenses WHERE budget_item_id = ?1)",
+            id,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if result == 0 {
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    /* Links each planned expense, which is not reconciled yet, to the best matching expense of
+    the same household, not linked to any other planned expense. Uncategorized expenses also get the Budget Item of the
+    planned expense. Run after new expenses are added. */
+    pub async fn reconcile_all(db: &Database) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        let unreconciled = sqlx::query_as::<_, PlannedExpense>(
+            "SELECT * FROM planned_expenses
+            WHERE expense_id IS NULL
+            ORDER BY expected_date, id",
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        for planned in unreconciled {
+            let candidates = sqlx::query_as::<_, Expense>(
+                "SELECT * FROM expenses
+                WHERE
+                  transaction_date BETWEEN date(?1, ?2) AND date(?1, ?3)
+                  AND account_id IN (
+                    SELECT accounts.id FROM accounts
+                    JOIN view_budget_items items ON (accounts.household_id = items.household_id)
+                    WHERE items.id = ?4
+                  )
+                  AND id NOT IN
+                    (SELECT expense_id FROM planned_expenses WHERE expense_id IS NOT NULL)",
+            )
+            .bind(&planned.fields.expected_date)
+            .bind(format!("-{} days", RECONCILE_WINDOW_DAYS))
+            .bind(format!("+{} days", RECONCILE_WINDOW_DAYS))
+            .bind(planned.fields.budget_item_id)
+            .fetch_all(&mut *conn)
+            .await?;
+
+            let expense_id = match find_matching_expense(&planned.fields, &candidates) {
+                Some(value) => value,
+                None => continue,
+            };
+
+            sqlx::query!(
+                "UPDATE planned_expenses SET expense_id = ?2 WHERE id = ?1",
+                planned.id,
+                expense_id,
+            )
+            .execute(&mut *conn)
+            .await?;
+
+            sqlx::query!(
+                "UPDATE expenses SET budget_item_id = ?2
+                WHERE id = ?1 AND budget_item_id IS NULL",
+                expense_id,
+                planned.fields.budget_item_id,
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::expense::{ExpenseCategory, ExpenseFields, ExpenseNotes};
+
+    fn get_planned() -> PlannedExpenseFields {
+        PlannedExpenseFields {
+            budget_item_id: 4,
+            expected_date: String::from("2025-06-15"),
+            amount: Money::from_cents(120000),
+            description: String::from("Car insurance"),
+        }
+    }
+
+    fn get_expense(id: ID, date: &str, amount: i64) -> Expense {
+        Expense {
+            id,
+            fields: ExpenseFields {
+                account_id: 1,
+                transaction_date: date.to_string(),
+                transaction_time: None,
+                description: String::from("PROGRESSIVE INS"),
+                amount: Money::from_cents(amount),
+                raw_csv: None,
+                balance: None,
+                original_currency: None,
+                original_amount: None,
+            },
+            category: ExpenseCategory {
+                budget_item_id: None,
+            },
+            notes: ExpenseNotes { notes: None },
+        }
+    }
+
+    #[test]
+    fn test_validate_planned_expense() {
+        assert!(validate_planned_expense(&get_planned()).is_ok());
+
+        let mut planned = get_planned();
+        planned.expected_date = String::from("06/15/2025");
+        assert!(validate_planned_expense(&planned).is_err());
+
+        let mut planned = get_planned();
+        planned.amount = Money::ZERO;
+        assert!(validate_planned_expense(&planned).is_err());
+
+        let mut planned = get_planned();
+        planned.amount = Money::from_cents(i64::MAX);
+        assert!(validate_planned_expense(&planned).is_err());
+    }
+
+    #[test]
+    fn test_find_matching_expense_picks_closest_date() {
+        let candidates = [
+            get_expense(1, "2025-06-05", 120000),
+            get_expense(2, "2025-06-17", 121500),
+            get_expense(3, "2025-06-15", 90000),
+        ];
+
+        assert_eq!(find_matching_expense(&get_planned(), &candidates), Some(2));
+    }
+
+    #[test]
+    fn test_find_matching_expense_outside_window_or_amount() {
+        let candidates = [
+            get_expense(1, "2025-05-30", 120000),
+            get_expense(2, "2025-06-15", 130000),
+        ];
+
+        assert_eq!(find_matching_expense(&get_planned(), &candidates), None);
+    }
+
+    #[test]
+    fn test_find_matching_expense_huge_amounts() {
+        let candidates = [
+            get_expense(1, "2025-06-15", i64::MIN),
+            get_expense(2, "2025-06-15", i64::MAX),
+            get_expense(3, "2025-06-16", 120000),
+        ];
+        assert_eq!(find_matching_expense(&get_planned(), &candidates), Some(3));
+
+        let mut planned = get_planned();
+        planned.amount = Money::from_cents(i64::MAX);
+        assert_eq!(find_matching_expense(&planned, &candidates), None);
+    }
+}
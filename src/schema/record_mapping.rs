@@ -6,8 +6,10 @@ use ts_rs::TS;
 use crate::common::TS_FILE;
 use crate::database::ID;
 
+use crate::schema::balance::BalanceGap;
 use crate::schema::datetime::{to_local_date, to_local_time, TZ};
 use crate::schema::expense::ExpenseFields;
+use crate::schema::money::Money;
 
 const SEPARATOR: &str = "\u{241F}";
 
@@ -19,6 +21,13 @@ pub enum ImportResult {
     Error { message: String },
 }
 
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub gaps: Vec<BalanceGap>, // empty unless statement schema maps balance column
+}
+
 #[derive(Debug, Serialize, Deserialize, TS)]
 #[serde(tag = "variant", content = "params")]
 #[ts(export_to = TS_FILE, tag = "variant", content = "params")]
@@ -51,6 +60,15 @@ pub enum AmountField {
     },
 }
 
+/* Balance follows the sign convention of account balances, i.e. money owed on a credit card is
+negative. Statements which print the owed amount as positive need to invert it. */
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[serde(tag = "variant", content = "params")]
+#[ts(export_to = TS_FILE, tag = "variant", content = "params")]
+pub enum BalanceField {
+    FromColumn { col: ColID, invert: bool },
+}
+
 #[derive(Debug, Serialize, Deserialize, TS)]
 #[serde(tag = "variant", content = "params")]
 #[ts(export_to = TS_FILE, tag = "variant", content = "params")]
@@ -65,6 +83,9 @@ pub struct RecordMapping {
     pub transaction_time: TimeField,
     pub description: TextField,
     pub amount: AmountField,
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub balance: Option<BalanceField>, // running balance column, if statement has one
 }
 
 fn get_col(record: &StringRecord, col: ColID) -> Result<&str, ImportResult> {
@@ -125,7 +146,7 @@ impl TimeField {
 }
 
 impl AmountField {
-    fn from_record(&self, record: &StringRecord) -> Result<i32, ImportResult> {
+    fn from_record(&self, record: &StringRecord) -> Result<Money, ImportResult> {
         match self {
             AmountField::FromColumn {
                 col,
@@ -162,7 +183,7 @@ impl AmountField {
         }
     }
 
-    fn parse_from_str(field: &str, invert: bool) -> Result<i32, ImportResult> {
+    fn parse_from_str(field: &str, invert: bool) -> Result<Money, ImportResult> {
         let value_float: f64 = match strip_thousands_separator(field).parse::<f64>() {
             Ok(value) => value,
             Err(_) => {
@@ -171,7 +192,13 @@ impl AmountField {
             }
         };
 
-        let value: i32 = (value_float * 100.).round() as i32;
+        let value = match Money::checked_from_units(value_float) {
+            Some(value) => value,
+            None => {
+                let message = format!("Amount '{}' is out of range", field);
+                return Err(ImportResult::Error { message: message });
+            }
+        };
 
         match invert {
             true => Ok(-value),
@@ -190,6 +217,22 @@ fn strip_thousands_separator(string: &str) -> String {
     }
 }
 
+impl BalanceField {
+    // Some banks leave balance empty, e.g. for pending transactions
+    fn from_record(&self, record: &StringRecord) -> Result<Option<Money>, ImportResult> {
+        match self {
+            BalanceField::FromColumn { col, invert } => {
+                let field = get_col(&record, *col)?;
+                if field.trim().is_empty() {
+                    return Ok(None);
+                }
+
+                AmountField::parse_from_str(field, *invert).map(Some)
+            }
+        }
+    }
+}
+
 impl TextField {
     fn from_record(&self, record: &StringRecord) -> Result<String, ImportResult> {
         match self {
@@ -223,6 +266,10 @@ impl RecordMapping {
         let description = self.description.from_record(&record)?;
         let amount = self.amount.from_record(&record)?;
         let raw_csv = Some(record_to_string(&record));
+        let balance = match &self.balance {
+            Some(field) => field.from_record(&record)?,
+            None => None,
+        };
 
         let expense = ExpenseFields {
             account_id: account_id,
@@ -231,6 +278,9 @@ impl RecordMapping {
             description: description,
             amount: amount,
             raw_csv: raw_csv,
+            balance,
+            original_currency: None,
+            original_amount: None,
         };
 
         Ok(expense)
@@ -267,7 +317,7 @@ mod tests {
         }
         .from_record(&record);
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), 69);
+        assert_eq!(result.unwrap(), Money::from_cents(69));
     }
 
     #[test]
@@ -280,7 +330,20 @@ mod tests {
         }
         .from_record(&record);
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), 1234567);
+        assert_eq!(result.unwrap(), Money::from_cents(1234567));
+    }
+
+    #[test]
+    fn test_amount_field_out_of_i32_range() {
+        let record = StringRecord::from(vec!["ab", "30,000,000.00"]);
+        let result = AmountField::FromColumn {
+            col: 1,
+            invert: false,
+            skip_pattern: None,
+        }
+        .from_record(&record);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), Money::from_cents(3_000_000_000));
     }
 
     #[test]
@@ -293,7 +356,7 @@ mod tests {
         }
         .from_record(&record);
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), -69);
+        assert_eq!(result.unwrap(), Money::from_cents(-69));
     }
 
     #[test]
@@ -322,6 +385,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_balance_field() {
+        let field = BalanceField::FromColumn {
+            col: 1,
+            invert: true,
+        };
+
+        let record = StringRecord::from(vec!["ab", "1,234.50"]);
+        assert_eq!(
+            field.from_record(&record).unwrap(),
+            Some(Money::from_cents(-123450))
+        );
+
+        let record = StringRecord::from(vec!["ab", ""]);
+        assert_eq!(field.from_record(&record).unwrap(), None);
+    }
+
     #[test]
     fn record_to_string_result_can_be_reconstructed_to_original_record() {
         let input_record =
@@ -0,0 +1,604 @@
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use chrono_tz::America::Chicago;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use ts_rs::TS;
+
+use crate::common::TS_FILE;
+use crate::database::{Database, ID};
+use crate::schema::expense::Expense;
+use crate::schema::item::BudgetItem;
+use crate::schema::money::Money;
+
+// Long enough to see yearly charges at least twice
+const LOOKBACK_MONTHS: u32 = 25;
+// Amounts within series can vary this much from the median, e.g. utilities
+const AMOUNT_TOLERANCE_PERCENT: i64 = 25;
+// Latest charge differing from previous ones more than this raises an alert
+const AMOUNT_JUMP_PERCENT: i64 = 10;
+pub const MAX_FORECAST_MONTHS: u32 = 24;
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, TS, PartialEq)]
+#[ts(export_to = TS_FILE)]
+pub enum RecurringCadence {
+    Weekly,
+    Biweekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+#[derive(Debug, Deserialize, Serialize, TS, PartialEq)]
+#[ts(export_to = TS_FILE)]
+pub enum RecurringStatus {
+    Active,
+    Missing, // expected charge is overdue
+    Ended,   // several charges in a row are missing, likely cancelled
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct RecurringSeries {
+    pub description: String, // of the latest charge
+    pub account_id: ID,
+    pub budget_item_id: Option<ID>, // of the latest charge
+    pub cadence: RecurringCadence,
+    pub expense_ids: Vec<ID>,
+    pub last_date: String,
+    pub last_amount: Money,
+    pub expected_date: String,
+    pub expected_amount: Money,
+    pub status: RecurringStatus,
+}
+
+#[derive(Debug, Serialize, TS, PartialEq)]
+#[serde(tag = "variant")]
+#[ts(export_to = TS_FILE, tag = "variant")]
+pub enum RecurringAlert {
+    MissingCharge {
+        description: String,
+        account_id: ID,
+        expected_date: String,
+        expected_amount: Money,
+    },
+    AmountChanged {
+        description: String,
+        account_id: ID,
+        expense_id: ID,
+        previous_amount: Money,
+        amount: Money,
+    },
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct RecurringSeriesList {
+    pub series: Vec<RecurringSeries>,
+    pub alerts: Vec<RecurringAlert>,
+}
+
+#[derive(Debug, Serialize, TS, PartialEq)]
+#[ts(export_to = TS_FILE)]
+pub struct RecurringForecastPoint {
+    pub month: String, // yyyy-MM
+    pub budget_item_id: Option<ID>,
+    pub amount: Money,                    // sum of expected recurring charges
+    pub monthly_allowance: Option<Money>, // Allowance of the budget item, converted to a month
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct RecurringForecast {
+    pub data: Vec<RecurringForecastPoint>,
+}
+
+const ALL_CADENCES: [RecurringCadence; 5] = [
+    RecurringCadence::Weekly,
+    RecurringCadence::Biweekly,
+    RecurringCadence::Monthly,
+    RecurringCadence::Quarterly,
+    RecurringCadence::Yearly,
+];
+
+impl RecurringCadence {
+    // Accepted number of days between charges, inclusive
+    fn interval_days(&self) -> (i64, i64) {
+        match self {
+            RecurringCadence::Weekly => (5, 9),
+            RecurringCadence::Biweekly => (12, 16),
+            RecurringCadence::Monthly => (26, 35),
+            RecurringCadence::Quarterly => (84, 98),
+            RecurringCadence::Yearly => (350, 380),
+        }
+    }
+
+    fn min_occurrences(&self) -> usize {
+        match self {
+            RecurringCadence::Yearly => 2,
+            _ => 3,
+        }
+    }
+
+    // How late a charge can be before it's reported as missing
+    fn grace_days(&self) -> i64 {
+        match self {
+            RecurringCadence::Weekly => 3,
+            RecurringCadence::Biweekly => 4,
+            RecurringCadence::Monthly => 7,
+            RecurringCadence::Quarterly => 14,
+            RecurringCadence::Yearly => 21,
+        }
+    }
+
+    fn next_date(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            RecurringCadence::Weekly => date + chrono::Days::new(7),
+            RecurringCadence::Biweekly => date + chrono::Days::new(14),
+            RecurringCadence::Monthly => date + Months::new(1),
+            RecurringCadence::Quarterly => date + Months::new(3),
+            RecurringCadence::Yearly => date + Months::new(12),
+        }
+    }
+}
+
+/* Statement descriptions of the same merchant usually differ in reference numbers, dates or
+store numbers, e.g. "NETFLIX.COM 866-579-7172" or "SPOTIFY P2A8F1C3D9". Only letters are kept,
+and words mixing letters and digits are dropped. */
+pub fn normalize_description(description: &str) -> String {
+    description
+        .split(|c: char| c.is_whitespace() || c.is_ascii_punctuation())
+        .filter(|word| !word.is_empty() && word.chars().all(|c| c.is_alphabetic()))
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+fn median(amounts: &[Money]) -> Money {
+    let mut sorted = amounts.to_vec();
+    sorted.sort();
+
+    sorted[sorted.len() / 2]
+}
+
+// Amounts too far apart to even compare without overflow differ too
+fn differs_by_more_than(amount: Money, reference: Money, percent: i64) -> bool {
+    let diff = amount
+        .checked_sub(reference)
+        .and_then(Money::checked_abs)
+        .and_then(|diff| diff.checked_mul(100));
+    let tolerance = reference
+        .checked_abs()
+        .and_then(|reference| reference.checked_mul(percent));
+
+    match (diff, tolerance) {
+        (Some(diff), Some(tolerance)) => diff > tolerance,
+        _ => true,
+    }
+}
+
+// At least 3 out of 4 intervals between charges have to match the cadence
+fn detect_cadence(dates: &[NaiveDate]) -> Option<RecurringCadence> {
+    let intervals: Vec<i64> = dates
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).num_days())
+        .collect();
+    if intervals.is_empty() {
+        return None;
+    }
+
+    ALL_CADENCES.into_iter().find(|cadence| {
+        let (min, max) = cadence.interval_days();
+        let matching = intervals
+            .iter()
+            .filter(|days| (min..=max).contains(*days))
+            .count();
+
+        dates.len() >= cadence.min_occurrences() && matching * 4 >= intervals.len() * 3
+    })
+}
+
+fn to_series(charges: &[&Expense], today: NaiveDate) -> Option<RecurringSeries> {
+    let dates: Vec<NaiveDate> = charges
+        .iter()
+        .map(|expense| parse_date(&expense.fields.transaction_date))
+        .collect::<Option<Vec<NaiveDate>>>()?;
+    let cadence = detect_cadence(&dates)?;
+
+    // the latest charge is left out, so that price change can be reported instead of breaking
+    // the series
+    let (last, previous) = charges.split_last()?;
+    let previous_amounts: Vec<Money> = previous.iter().map(|e| e.fields.amount).collect();
+    let reference = median(&previous_amounts);
+    if previous_amounts
+        .iter()
+        .any(|amount| differs_by_more_than(*amount, reference, AMOUNT_TOLERANCE_PERCENT))
+    {
+        return None;
+    }
+
+    let last_date = *dates.last()?;
+    let expected_date = cadence.next_date(last_date);
+    let expected_amount =
+        match differs_by_more_than(last.fields.amount, reference, AMOUNT_JUMP_PERCENT) {
+            true => last.fields.amount,
+            false => {
+                let all_amounts: Vec<Money> = charges.iter().map(|e| e.fields.amount).collect();
+                median(&all_amounts[all_amounts.len().saturating_sub(3)..])
+            }
+        };
+
+    let (_, max_interval) = cadence.interval_days();
+    let overdue_days = (today - expected_date).num_days();
+    let status = if overdue_days > cadence.grace_days() + max_interval {
+        RecurringStatus::Ended
+    } else if overdue_days > cadence.grace_days() {
+        RecurringStatus::Missing
+    } else {
+        RecurringStatus::Active
+    };
+
+    Some(RecurringSeries {
+        description: last.fields.description.clone(),
+        account_id: last.fields.account_id,
+        budget_item_id: last.category.budget_item_id,
+        cadence,
+        expense_ids: charges.iter().map(|e| e.id).collect(),
+        last_date: last.fields.transaction_date.clone(),
+        last_amount: last.fields.amount,
+        expected_date: expected_date.format("%Y-%m-%d").to_string(),
+        expected_amount,
+        status,
+    })
+}
+
+fn to_alerts(series: &RecurringSeries, previous_amount: Money) -> Vec<RecurringAlert> {
+    let mut alerts = vec![];
+    if series.status == RecurringStatus::Ended {
+        return alerts;
+    }
+
+    if differs_by_more_than(series.last_amount, previous_amount, AMOUNT_JUMP_PERCENT) {
+        alerts.push(RecurringAlert::AmountChanged {
+            description: series.description.clone(),
+            account_id: series.account_id,
+            expense_id: *series.expense_ids.last().unwrap(),
+            previous_amount,
+            amount: series.last_amount,
+        });
+    }
+
+    if series.status == RecurringStatus::Missing {
+        alerts.push(RecurringAlert::MissingCharge {
+            description: series.description.clone(),
+            account_id: series.account_id,
+            expected_date: series.expected_date.clone(),
+            expected_amount: series.expected_amount,
+        });
+    }
+
+    alerts
+}
+
+// Expenses are expected to be sorted by transaction date
+pub fn detect_series(expenses: &[Expense], today: NaiveDate) -> RecurringSeriesList {
+    let mut groups: BTreeMap<(ID, String), Vec<&Expense>> = BTreeMap::new();
+    for expense in expenses {
+        let normalized = normalize_description(&expense.fields.description);
+        if normalized.is_empty() {
+            continue;
+        }
+
+        groups
+            .entry((expense.fields.account_id, normalized))
+            .or_default()
+            .push(expense);
+    }
+
+    let mut series = vec![];
+    let mut alerts = vec![];
+    for charges in groups.values() {
+        if let Some(value) = to_series(charges, today) {
+            let previous: Vec<Money> = charges[..charges.len() - 1]
+                .iter()
+                .map(|e| e.fields.amount)
+                .collect();
+            alerts.append(&mut to_alerts(&value, median(&previous)));
+            series.push(value);
+        }
+    }
+
+    RecurringSeriesList { series, alerts }
+}
+
+/* Expected charges from today until the end of the month `months` months from now, summed by
+month and Budget Item. Charges which are already missing are not included. */
+pub fn forecast(
+    series: &[RecurringSeries],
+    today: NaiveDate,
+    months: u32,
+) -> Vec<RecurringForecastPoint> {
+    let first_of_month = today.with_day(1).unwrap();
+    let end = first_of_month + Months::new(months + 1);
+
+    let mut amounts: BTreeMap<(String, Option<ID>), Money> = BTreeMap::new();
+    for series in series.iter() {
+        if series.status == RecurringStatus::Ended {
+            continue;
+        }
+
+        let mut date = match parse_date(&series.expected_date) {
+            Some(value) => value,
+            None => continue,
+        };
+        while date < today {
+            date = series.cadence.next_date(date);
+        }
+
+        while date < end {
+            let month = date.format("%Y-%m").to_string();
+            *amounts.entry((month, series.budget_item_id)).or_default() += series.expected_amount;
+            date = series.cadence.next_date(date);
+        }
+    }
+
+    amounts
+        .into_iter()
+        .map(|((month, budget_item_id), amount)| RecurringForecastPoint {
+            month,
+            budget_item_id,
+            amount,
+            monthly_allowance: None,
+        })
+        .collect()
+}
+
+fn today() -> NaiveDate {
+    Utc::now().with_timezone(&Chicago).date_naive()
+}
+
+async fn fetch_recent_expenses(
+    db: &Database,
+    username: &str,
+    today: NaiveDate,
+) -> anyhow::Result<Vec<Expense>> {
+    let since = (today - Months::new(LOOKBACK_MONTHS))
+        .format("%Y-%m-%d")
+        .to_string();
+
+    // only spend, refunds and income are not subscriptions
+    let mut conn = db.acquire_db_conn().await?;
+    let results = sqlx::query_as::<_, Expense>(
+        "SELECT * FROM expenses
+        WHERE
+          transaction_date >= ?1
+          AND amount > 0
+          AND account_id IN (SELECT account_id FROM view_account_access WHERE username = ?2)
+        ORDER BY transaction_date, transaction_time, id",
+    )
+    .bind(since)
+    .bind(username)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(results)
+}
+
+impl RecurringSeriesList {
+    pub async fn fetch(db: &Database, username: &str) -> anyhow::Result<RecurringSeriesList> {
+        let today = today();
+        let expenses = fetch_recent_expenses(db, username, today).await?;
+
+        Ok(detect_series(&expenses, today))
+    }
+}
+
+impl RecurringForecast {
+    pub async fn fetch(
+        db: &Database,
+        username: &str,
+        months: u32,
+    ) -> anyhow::Result<RecurringForecast> {
+        let today = today();
+        let expenses = fetch_recent_expenses(db, username, today).await?;
+        let series = detect_series(&expenses, today).series;
+        let mut data = forecast(&series, today, months);
+
+        let mut item_ids: Vec<ID> = data.iter().filter_map(|p| p.budget_item_id).collect();
+        item_ids.sort();
+        item_ids.dedup();
+        let items = BudgetItem::fetch_by_ids(db, &item_ids).await?;
+
+        for point in data.iter_mut() {
+            point.monthly_allowance = items
+                .iter()
+                .find(|item| Some(item.id) == point.budget_item_id)
+                .and_then(|item| item.fields.allowance.as_ref())
+                .map(|allowance| allowance.amount_per_month());
+        }
+
+        Ok(RecurringForecast { data })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::expense::{ExpenseCategory, ExpenseFields, ExpenseNotes};
+    use crate::schema::item::Allowance;
+
+    fn get_expense(id: ID, date: &str, description: &str, amount: i64) -> Expense {
+        Expense {
+            id,
+            fields: ExpenseFields {
+                account_id: 1,
+                transaction_date: date.to_string(),
+                transaction_time: None,
+                description: description.to_string(),
+                amount: Money::from_cents(amount),
+                raw_csv: None,
+                balance: None,
+                original_currency: None,
+                original_amount: None,
+            },
+            category: ExpenseCategory {
+                budget_item_id: Some(7),
+            },
+            notes: ExpenseNotes { notes: None },
+        }
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        parse_date(value).unwrap()
+    }
+
+    fn monthly_netflix(amounts: &[i64]) -> Vec<Expense> {
+        amounts
+            .iter()
+            .enumerate()
+            .map(|(i, amount)| {
+                let description = format!("NETFLIX.COM 866-579-71{:02}", i);
+                let date = format!("2025-{:02}-15", i + 1);
+                get_expense(i as ID + 1, &date, &description, *amount)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_normalize_description() {
+        let cases = vec![
+            ("NETFLIX.COM 866-579-7172", "netflix com"),
+            ("SPOTIFY P2A8F1C3D9 Stockholm", "spotify stockholm"),
+            ("Comcast   #8821", "comcast"),
+            ("12345", ""),
+        ];
+
+        for (input, expected) in cases.into_iter() {
+            assert_eq!(normalize_description(input), expected);
+        }
+    }
+
+    #[test]
+    fn test_detect_cadence() {
+        let weekly = ["2025-03-03", "2025-03-10", "2025-03-17", "2025-03-25"];
+        let monthly = ["2025-01-31", "2025-02-28", "2025-03-31", "2025-04-30"];
+        let yearly = ["2024-06-02", "2025-06-01"];
+        let irregular = ["2025-01-02", "2025-01-05", "2025-02-20", "2025-02-21"];
+
+        let cases = vec![
+            (&weekly[..], Some(RecurringCadence::Weekly)),
+            (&monthly[..], Some(RecurringCadence::Monthly)),
+            (&yearly[..], Some(RecurringCadence::Yearly)),
+            (&irregular[..], None),
+            (&monthly[..2], None),
+        ];
+
+        for (dates, expected) in cases.into_iter() {
+            let dates: Vec<NaiveDate> = dates.iter().map(|d| date(d)).collect();
+            assert_eq!(detect_cadence(&dates), expected);
+        }
+    }
+
+    #[test]
+    fn test_detect_monthly_series() {
+        let expenses = monthly_netflix(&[1549, 1549, 1549, 1549]);
+        let result = detect_series(&expenses, date("2025-05-01"));
+
+        assert_eq!(result.series.len(), 1);
+        assert!(result.alerts.is_empty());
+        let series = &result.series[0];
+        assert_eq!(series.cadence, RecurringCadence::Monthly);
+        assert_eq!(series.expense_ids, vec![1, 2, 3, 4]);
+        assert_eq!(series.expected_date, "2025-05-15");
+        assert_eq!(series.expected_amount, Money::from_cents(1549));
+        assert_eq!(series.status, RecurringStatus::Active);
+    }
+
+    #[test]
+    fn test_amount_jump_raises_alert() {
+        let expenses = monthly_netflix(&[1549, 1549, 1549, 1799]);
+        let result = detect_series(&expenses, date("2025-05-01"));
+
+        assert_eq!(result.series.len(), 1);
+        assert_eq!(result.series[0].expected_amount, Money::from_cents(1799));
+        assert_eq!(
+            result.alerts,
+            vec![RecurringAlert::AmountChanged {
+                description: String::from("NETFLIX.COM 866-579-7103"),
+                account_id: 1,
+                expense_id: 4,
+                previous_amount: Money::from_cents(1549),
+                amount: Money::from_cents(1799),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_missing_and_ended_series() {
+        let expenses = monthly_netflix(&[1549, 1549, 1549, 1549]);
+
+        let result = detect_series(&expenses, date("2025-05-25"));
+        assert_eq!(result.series[0].status, RecurringStatus::Missing);
+        assert_eq!(result.alerts.len(), 1);
+
+        let result = detect_series(&expenses, date("2025-08-01"));
+        assert_eq!(result.series[0].status, RecurringStatus::Ended);
+        assert!(result.alerts.is_empty());
+    }
+
+    #[test]
+    fn test_differs_by_more_than() {
+        let reference = Money::from_cents(1549);
+        assert!(!differs_by_more_than(Money::from_cents(1600), reference, 5));
+        assert!(differs_by_more_than(Money::from_cents(1700), reference, 5));
+        assert!(differs_by_more_than(
+            Money::from_cents(i64::MIN),
+            reference,
+            5
+        ));
+        assert!(differs_by_more_than(
+            reference,
+            Money::from_cents(i64::MAX),
+            5
+        ));
+    }
+
+    #[test]
+    fn test_dissimilar_amounts_are_not_series() {
+        let expenses = monthly_netflix(&[1549, 4000, 1549, 1549]);
+        let result = detect_series(&expenses, date("2025-05-01"));
+
+        assert!(result.series.is_empty());
+    }
+
+    #[test]
+    fn test_forecast() {
+        let expenses = monthly_netflix(&[1549, 1549, 1549, 1549]);
+        let series = detect_series(&expenses, date("2025-05-01")).series;
+        let result = forecast(&series, date("2025-05-01"), 2);
+
+        let months: Vec<&str> = result.iter().map(|p| p.month.as_str()).collect();
+        assert_eq!(months, vec!["2025-05", "2025-06", "2025-07"]);
+        assert!(result.iter().all(|p| p.amount == Money::from_cents(1549)));
+        assert!(result.iter().all(|p| p.budget_item_id == Some(7)));
+    }
+
+    #[test]
+    fn test_allowance_per_month() {
+        assert_eq!(
+            Allowance::Weekly(Money::from_cents(1000)).amount_per_month(),
+            Money::from_cents(4333)
+        );
+        assert_eq!(
+            Allowance::Monthly(Money::from_cents(1000)).amount_per_month(),
+            Money::from_cents(1000)
+        );
+        assert_eq!(
+            Allowance::Yearly(Money::from_cents(12000)).amount_per_month(),
+            Money::from_cents(1000)
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Acquire, FromRow};
+use ts_rs::TS;
+
+use crate::common::TS_FILE;
+use crate::database::{Database, ID};
+use crate::schema::expense::Expense;
+use crate::schema::money::Money;
+
+/* Refunds and reimbursements are imported as regular expenses, usually with amount of opposite
+sign than expense they pay back. Linking them to original expense(s) makes them count against
+the original expense budget item instead, see view_expense_spend. Amount is expressed in the same
+sign as the original expense, so reimbursement of 50.00 for a 120.00 dinner is stored as 5000. */
+#[derive(Debug, FromRow, Deserialize, Serialize, TS, PartialEq)]
+#[ts(export_to = TS_FILE)]
+pub struct ReimbursementFields {
+    pub expense_id: ID,
+    pub amount: Money,
+}
+
+#[derive(Debug, FromRow, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct Reimbursement {
+    pub id: ID,
+    pub reimbursement_id: ID,
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    #[ts(flatten)]
+    pub fields: ReimbursementFields,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct Reimbursements {
+    pub reimbursements: Vec<Reimbursement>,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct PendingReimbursementFields {
+    pub amount: Option<Money>, // expected amount, in the same sign as the expense; None to clear
+}
+
+#[derive(Debug, FromRow, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct PendingReimbursement {
+    pub expected_amount: Money,
+    pub received_amount: Money, // computed, sum of linked reimbursements
+    pub age_days: i32,          // computed, days since transaction_date
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    #[ts(flatten)]
+    pub expense: Expense,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct PendingReimbursements {
+    pub expenses: Vec<PendingReimbursement>,
+}
+
+/* Reimbursement has to be fully distributed between expenses it pays back, otherwise the part
+not assigned to any expense would silently disappear from spending data. */
+pub fn validate_reimbursements(
+    reimbursement: &Expense,
+    fields: &[ReimbursementFields],
+) -> Result<(), String> {
+    let mut total = Money::ZERO;
+    for (index, link) in fields.iter().enumerate() {
+        if link.expense_id == reimbursement.id {
+            return Err(String::from("Expense can't reimburse itself."));
+        }
+
+        if link.amount == Money::ZERO {
+            let message = format!("Reimbursed amount for expense {} is 0.", link.expense_id);
+            return Err(message);
+        }
+
+        if fields[..index]
+            .iter()
+            .any(|x| x.expense_id == link.expense_id)
+        {
+            let message = format!("Expense {} is listed more than once.", link.expense_id);
+            return Err(message);
+        }
+
+        total = match total.checked_add(link.amount) {
+            Some(value) => value,
+            None => return Err(String::from("Reimbursed amounts are too large.")),
+        };
+    }
+
+    if !fields.is_empty() && total != -reimbursement.fields.amount {
+        let message = format!(
+            "Reimbursed amounts add up to {}, but reimbursement amount is {}.",
+            total, reimbursement.fields.amount,
+        );
+        return Err(message);
+    }
+
+    Ok(())
+}
+
+impl Reimbursement {
+    pub async fn replace(
+        db: &Database,
+        reimbursement_id: ID,
+        fields: Vec<ReimbursementFields>,
+    ) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        sqlx::query!(
+            "DELETE FROM reimbursements WHERE reimbursement_id = ?1",
+            reimbursement_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        for link in fields {
+            sqlx::query!(
+                "INSERT INTO reimbursements (reimbursement_id, expense_id, amount)
+                VALUES (?1, ?2, ?3)",
+                reimbursement_id,
+                link.expense_id,
+                link.amount,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // Returns links in both directions: where expense is reimbursement, and where it's reimbursed
+    pub async fn fetch_by_expense_id(db: &Database, id: ID) -> anyhow::Result<Reimbursements> {
+        let mut conn = db.acquire_db_conn().await?;
+        let results = sqlx::query_as::<_, Reimbursement>(
+            "SELECT * FROM reimbursements
+            WHERE reimbursement_id = ?1 OR expense_id = ?1
+            ORDER BY id",
+        )
+        .bind(id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(Reimbursements {
+            reimbursements: results,
+        })
+    }
+
+    pub async fn any_has_expense_id(db: &Database, id: ID) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query_scalar!(
+            "SELECT EXISTS (SELECT 1 FROM reimbursements WHERE expense_id = ?1)",
+            id,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if result == 0 {
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    pub async fn any_has_reimbursement_id(db: &Database, id: ID) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query_scalar!(
+            "SELECT EXISTS (SELECT 1 FROM reimbursements WHERE reimbursement_id = ?1)",
+            id,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if result == 0 {
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+}
+
+impl PendingReimbursement {
+    pub async fn update(
+        db: &Database,
+        expense_id: ID,
+        fields: PendingReimbursementFields,
+    ) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+
+        match fields.amount {
+            Some(amount) => {
+                sqlx::query!(
+                    "INSERT INTO pending_reimbursements (expense_id, amount) VALUES (?1, ?2)
+                    ON CONFLICT(expense_id) DO UPDATE SET amount = ?2",
+                    expense_id,
+                    amount,
+                )
+                .execute(&mut *conn)
+                .await?;
+            }
+            None => {
+                sqlx::query!(
+                    "DELETE FROM pending_reimbursements WHERE expense_id = ?1",
+                    expense_id
+                )
+                .execute(&mut *conn)
+                .await?;
+            }
+        };
+
+        Ok(())
+    }
+
+    // Expenses stay pending until linked reimbursements cover the expected amount
+    pub async fn fetch_all(db: &Database, username: &str) -> anyhow::Result<PendingReimbursements> {
+        let mut conn = db.acquire_db_conn().await?;
+        let results = sqlx::query_as::<_, PendingReimbursement>(
+            "SELECT
+              expenses.*,
+              pending.amount AS expected_amount,
+              COALESCE(SUM(reimbursements.amount), 0) AS received_amount,
+              CAST(julianday('now') - julianday(expenses.transaction_date) AS INTEGER) AS age_days
+            FROM pending_reimbursements pending
+            JOIN expenses
+              ON (pending.expense_id = expenses.id)
+            LEFT JOIN reimbursements
+              ON (pending.expense_id = reimbursements.expense_id)
+            WHERE expenses.account_id IN
+              (SELECT account_id FROM view_account_access WHERE username = ?1)
+            GROUP BY expenses.id
+            HAVING ABS(received_amount) < ABS(expected_amount)
+            ORDER BY
+              expenses.transaction_date,
+              expenses.transaction_time",
+        )
+        .bind(username)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(PendingReimbursements { expenses: results })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::expense::{ExpenseCategory, ExpenseFields, ExpenseNotes};
+
+    fn get_expense(id: ID, amount: i64) -> Expense {
+        Expense {
+            id,
+            fields: ExpenseFields {
+                account_id: 1,
+                transaction_date: "2025-03-01".to_string(),
+                transaction_time: None,
+                description: "Refund".to_string(),
+                amount: Money::from_cents(amount),
+                raw_csv: None,
+                balance: None,
+                original_currency: None,
+                original_amount: None,
+            },
+            category: ExpenseCategory {
+                budget_item_id: None,
+            },
+            notes: ExpenseNotes { notes: None },
+        }
+    }
+
+    fn link(expense_id: ID, amount: i64) -> ReimbursementFields {
+        ReimbursementFields {
+            expense_id,
+            amount: Money::from_cents(amount),
+        }
+    }
+
+    #[test]
+    fn test_validate_reimbursements_split_between_expenses() {
+        let reimbursement = get_expense(10, -5000);
+        let fields = [link(1, 2000), link(2, 3000)];
+        assert!(validate_reimbursements(&reimbursement, &fields).is_ok());
+    }
+
+    #[test]
+    fn test_validate_reimbursements_empty_unlinks() {
+        let reimbursement = get_expense(10, -5000);
+        assert!(validate_reimbursements(&reimbursement, &[]).is_ok());
+    }
+
+    #[test]
+    fn test_validate_reimbursements_must_add_up_to_reimbursement_amount() {
+        let reimbursement = get_expense(10, -5000);
+        let fields = [link(1, 2000)];
+        assert!(validate_reimbursements(&reimbursement, &fields).is_err());
+
+        let wrong_sign = [link(1, -5000)];
+        assert!(validate_reimbursements(&reimbursement, &wrong_sign).is_err());
+    }
+
+    #[test]
+    fn test_validate_reimbursements_rejects_self_and_duplicate_links() {
+        let reimbursement = get_expense(10, -5000);
+        let to_self = [link(10, 5000)];
+        assert!(validate_reimbursements(&reimbursement, &to_self).is_err());
+
+        let duplicate = [link(1, 2500), link(1, 2500)];
+        assert!(validate_reimbursements(&reimbursement, &duplicate).is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{FromRow, SqliteConnection};
+
+use crate::database::{Database, ID};
+use crate::schema::category::{BudgetCategory, BudgetCategoryFields};
+use crate::schema::expense::{Expense, ExpenseCategory, ExpenseNotes};
+use crate::schema::fund::{Fund, FundFields};
+use crate::schema::item::{BudgetItem, BudgetItemFields};
+use crate::schema::statement_schema::{StatementSchema, StatementSchemaFields};
+use crate::schema::tag::{Tag, TagFields};
+
+/* Updates save editable fields of the row from before and after the change, tied to write log
+entry of the request. Undo restores the before image through the same update, so undo is logged
+and can be undone too. It's refused when the row doesn't match the after image anymore, as that
+would silently revert later changes. */
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub enum SnapshotKind {
+    ExpenseCategory,
+    ExpenseNotes,
+    BudgetItem,
+    BudgetCategory,
+    StatementSchema,
+    Fund,
+    Tag,
+}
+
+impl SnapshotKind {
+    // Entity of the write log history, see write_log_entities
+    pub fn entity(self) -> &'static str {
+        match self {
+            SnapshotKind::ExpenseCategory | SnapshotKind::ExpenseNotes => "expenses",
+            SnapshotKind::BudgetItem => "budget_items",
+            SnapshotKind::BudgetCategory => "budget_categories",
+            SnapshotKind::StatementSchema => "schemas",
+            SnapshotKind::Fund => "funds",
+            SnapshotKind::Tag => "tags",
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+pub struct Snapshot {
+    pub kind: SnapshotKind,
+    pub entity_id: ID,
+    pub before_image: String,
+    pub after_image: String,
+}
+
+async fn fetch_row<T>(
+    conn: &mut SqliteConnection,
+    sql: &str,
+    id: ID,
+) -> anyhow::Result<Option<String>>
+where
+    T: for<'r> FromRow<'r, SqliteRow> + Serialize + Send + Unpin,
+{
+    let row = sqlx::query_as::<_, T>(sql)
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    match row {
+        Some(value) => Ok(Some(serde_json::to_string(&value)?)),
+        None => Ok(None),
+    }
+}
+
+impl Snapshot {
+    // JSON of the editable fields, None if the row doesn't exist
+    pub async fn fetch_image(
+        conn: &mut SqliteConnection,
+        kind: SnapshotKind,
+        id: ID,
+    ) -> anyhow::Result<Option<String>> {
+        match kind {
+            SnapshotKind::ExpenseCategory => {
+                let sql = "SELECT budget_item_id FROM expenses WHERE id = ?1";
+                fetch_row::<ExpenseCategory>(conn, sql, id).await
+            }
+            SnapshotKind::ExpenseNotes => {
+                let sql = "SELECT notes FROM expenses WHERE id = ?1";
+                fetch_row::<ExpenseNotes>(conn, sql, id).await
+            }
+            SnapshotKind::BudgetItem => {
+                let sql = "SELECT category_id, fund_id, name, allowance, budget_only
+                    FROM budget_items WHERE id = ?1";
+                fetch_row::<BudgetItemFields>(conn, sql, id).await
+            }
+            SnapshotKind::BudgetCategory => {
+                let sql = "SELECT name, ignored FROM budget_categories WHERE id = ?1";
+                fetch_row::<BudgetCategoryFields>(conn, sql, id).await
+            }
+            SnapshotKind::StatementSchema => {
+                let sql = "SELECT name, notes, record_mapping FROM statement_schemas WHERE id = ?1";
+                fetch_row::<StatementSchemaFields>(conn, sql, id).await
+            }
+            SnapshotKind::Fund => {
+                let sql = "SELECT name FROM funds WHERE id = ?1";
+                fetch_row::<FundFields>(conn, sql, id).await
+            }
+            SnapshotKind::Tag => {
+                let sql = "SELECT name FROM tags WHERE id = ?1";
+                fetch_row::<TagFields>(conn, sql, id).await
+            }
+        }
+    }
+
+    // Called after the update, in its transaction, with image fetched before it
+    pub async fn record(
+        conn: &mut SqliteConnection,
+        write_log_id: ID,
+        kind: SnapshotKind,
+        id: ID,
+        before_image: Option<String>,
+    ) -> anyhow::Result<()> {
+        let before_image = match before_image {
+            Some(value) => value,
+            None => return Ok(()), // nothing to update
+        };
+        let after_image = match Snapshot::fetch_image(conn, kind, id).await? {
+            Some(value) if value != before_image => value,
+            _ => return Ok(()), // nothing changed
+        };
+
+        sqlx::query!(
+            "INSERT INTO write_log_snapshots (
+              write_log_id,
+              kind,
+              entity_id,
+              before_image,
+              after_image
+            ) VALUES (?1, ?2, ?3, ?4, ?5)",
+            write_log_id,
+            kind,
+            id,
+            before_image,
+            after_image,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch_by_write_log_id(
+        db: &Database,
+        write_log_id: ID,
+    ) -> anyhow::Result<Vec<Snapshot>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let results = sqlx::query_as::<_, Snapshot>(
+            "SELECT kind, entity_id, before_image, after_image FROM write_log_snapshots
+            WHERE write_log_id = ?1 ORDER BY id",
+        )
+        .bind(write_log_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(results)
+    }
+
+    // Whether the row is still as the write left it
+    pub async fn is_current(&self, conn: &mut SqliteConnection) -> anyhow::Result<bool> {
+        let image = Snapshot::fetch_image(conn, self.kind, self.entity_id).await?;
+
+        Ok(image.as_deref() == Some(self.after_image.as_str()))
+    }
+
+    // Writes the before image back, as a change of write log entry `write_log_id`, in the
+    // transaction of the caller, which checks all rows are current first. Inner error when the
+    // image can't be restored anymore, e.g. budget item moved to another household since.
+    pub async fn restore(
+        &self,
+        conn: &mut SqliteConnection,
+        write_log_id: ID,
+    ) -> anyhow::Result<Result<(), String>> {
+        let id = self.entity_id;
+        let image = &self.before_image;
+
+        let result = match self.kind {
+            SnapshotKind::ExpenseCategory => {
+                let before: ExpenseCategory = serde_json::from_str(image)?;
+                let budget_item_id = before.budget_item_id;
+                if let Some(item_id) = budget_item_id {
+                    if !BudgetItem::is_in_household_of_expense_with_conn(conn, item_id, id).await? {
+                        let message = format!(
+                            "Budget item {} isn't in household of expense {} anymore.",
+                            item_id, id
+                        );
+                        return Ok(Err(message));
+                    }
+                }
+                Expense::update_budget_item_id_with_conn(conn, id, budget_item_id, write_log_id)
+                    .await
+            }
+            SnapshotKind::ExpenseNotes => {
+                let before: ExpenseNotes = serde_json::from_str(image)?;
+                Expense::update_notes_with_conn(conn, id, before.notes, write_log_id).await
+            }
+            SnapshotKind::BudgetItem => {
+                let before = serde_json::from_str(image)?;
+                BudgetItem::update_with_conn(conn, id, before, write_log_id).await
+            }
+            SnapshotKind::BudgetCategory => {
+                let before = serde_json::from_str(image)?;
+                BudgetCategory::update_with_conn(conn, id, before, write_log_id).await
+            }
+            SnapshotKind::StatementSchema => {
+                let before = serde_json::from_str(image)?;
+                StatementSchema::update_with_conn(conn, id, before, write_log_id).await
+            }
+            SnapshotKind::Fund => {
+                let before = serde_json::from_str(image)?;
+                Fund::update_with_conn(conn, id, before, write_log_id).await
+            }
+            SnapshotKind::Tag => {
+                let before = serde_json::from_str(image)?;
+                Tag::update_with_conn(conn, id, before, write_log_id).await
+            }
+        };
+
+        result.map(Ok)
+    }
+}
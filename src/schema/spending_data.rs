@@ -4,31 +4,75 @@ use ts_rs::TS;
 
 use crate::common::TS_FILE;
 use crate::database::{Database, ID};
+use crate::schema::money::Money;
 
 #[derive(Debug, FromRow, Serialize, TS)]
 #[ts(export_to = TS_FILE)]
 pub struct SpendingDataPoint {
     pub budget_item_id: Option<ID>,
     pub month: String,
-    pub amount: i32,
+    pub amount: Money,
 }
 
 impl SpendingDataPoint {
-    pub async fn fetch_by_year(db: &Database, year: i32) -> anyhow::Result<Vec<SpendingDataPoint>> {
+    // Spend on accounts the user has access to
+    pub async fn fetch_by_year(
+        db: &Database,
+        username: &str,
+        year: i32,
+    ) -> anyhow::Result<Vec<SpendingDataPoint>> {
         let mut conn = db.acquire_db_conn().await?;
         let results = sqlx::query_as::<_, SpendingDataPoint>(
             "SELECT
           budget_item_id,
           SUBSTR(transaction_date, 1, 7) AS `month`,
           sum(amount) as `amount`
-        FROM expenses
+        FROM view_expense_spend
         WHERE
           SUBSTR(transaction_date, 1, 4) = ?1
+          AND expense_id IN (
+            SELECT id FROM expenses
+            WHERE account_id IN (SELECT account_id FROM view_account_access WHERE username = ?2)
+          )
         GROUP BY
           budget_item_id,
           SUBSTR(transaction_date, 1, 7)",
         )
         .bind(year.to_string())
+        .bind(username)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(results)
+    }
+
+    // Planned expenses not reconciled yet, shown next to actual spend
+    pub async fn fetch_committed_by_year(
+        db: &Database,
+        username: &str,
+        year: i32,
+    ) -> anyhow::Result<Vec<SpendingDataPoint>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let results = sqlx::query_as::<_, SpendingDataPoint>(
+            "SELECT
+              budget_item_id,
+              SUBSTR(expected_date, 1, 7) AS `month`,
+              SUM(amount) AS `amount`
+            FROM planned_expenses
+            WHERE
+              expense_id IS NULL
+              AND SUBSTR(expected_date, 1, 4) = ?1
+              AND budget_item_id IN (
+                SELECT id FROM view_budget_items
+                WHERE household_id IN
+                  (SELECT household_id FROM household_members WHERE username = ?2)
+              )
+            GROUP BY
+              budget_item_id,
+              SUBSTR(expected_date, 1, 7)",
+        )
+        .bind(year.to_string())
+        .bind(username)
         .fetch_all(&mut *conn)
         .await?;
 
@@ -11,8 +11,13 @@ use sqlx::Database as SqlxDatabase;
 use sqlx::{Decode, Encode, Sqlite, Type};
 
 use crate::schema::account::AccountType;
+use crate::schema::household::HouseholdRole;
 use crate::schema::item::Allowance;
+use crate::schema::money::Money;
 use crate::schema::record_mapping::RecordMapping;
+use crate::schema::snapshot::SnapshotKind;
+use crate::schema::token_scope::TokenScopes;
+use crate::schema::user_role::UserRole;
 
 type BoxDynError = Box<dyn std::error::Error + 'static + Send + Sync>;
 
@@ -52,6 +57,42 @@ impl<'q> Encode<'q, Sqlite> for AccountType {
     }
 }
 
+impl<'r> Decode<'r, Sqlite> for HouseholdRole {
+    fn decode(value: <Sqlite as SqlxDatabase>::ValueRef<'r>) -> Result<HouseholdRole, BoxDynError> {
+        let json_string = <&str as Decode<Sqlite>>::decode(value)?;
+
+        let value: HouseholdRole = match serde_json::from_str(json_string) {
+            Ok(value) => value,
+            Err(e) => {
+                let err: BoxDynError = format!("{:?}", e).into();
+                return Err(err);
+            }
+        };
+
+        Ok(value)
+    }
+}
+
+impl Type<Sqlite> for HouseholdRole {
+    fn type_info() -> <Sqlite as SqlxDatabase>::TypeInfo {
+        <&str as Type<Sqlite>>::type_info()
+    }
+}
+
+impl<'q> Encode<'q, Sqlite> for HouseholdRole {
+    fn encode_by_ref(&self, buf: &mut Vec<SqliteArgumentValue<'q>>) -> Result<IsNull, BoxDynError> {
+        let string = match serde_json::to_string(&self) {
+            Ok(value) => value,
+            Err(e) => {
+                let err: BoxDynError = format!("{:?}", e).into();
+                return Err(err);
+            }
+        };
+
+        Encode::<Sqlite>::encode(string, buf)
+    }
+}
+
 impl<'r> Decode<'r, Sqlite> for Allowance {
     fn decode(value: <Sqlite as SqlxDatabase>::ValueRef<'r>) -> Result<Allowance, BoxDynError> {
         let json_string = <&str as Decode<Sqlite>>::decode(value)?;
@@ -123,3 +164,136 @@ impl<'q> Encode<'q, Sqlite> for RecordMapping {
         Encode::<Sqlite>::encode(string, buf)
     }
 }
+
+impl<'r> Decode<'r, Sqlite> for TokenScopes {
+    fn decode(value: <Sqlite as SqlxDatabase>::ValueRef<'r>) -> Result<TokenScopes, BoxDynError> {
+        let json_string = <&str as Decode<Sqlite>>::decode(value)?;
+
+        let value: TokenScopes = match serde_json::from_str(json_string) {
+            Ok(value) => value,
+            Err(e) => {
+                let err: BoxDynError = format!("{:?}", e).into();
+                return Err(err);
+            }
+        };
+
+        Ok(value)
+    }
+}
+
+impl Type<Sqlite> for TokenScopes {
+    fn type_info() -> <Sqlite as SqlxDatabase>::TypeInfo {
+        <&str as Type<Sqlite>>::type_info()
+    }
+}
+
+impl<'q> Encode<'q, Sqlite> for TokenScopes {
+    fn encode_by_ref(&self, buf: &mut Vec<SqliteArgumentValue<'q>>) -> Result<IsNull, BoxDynError> {
+        let string = match serde_json::to_string(&self) {
+            Ok(value) => value,
+            Err(e) => {
+                let err: BoxDynError = format!("{:?}", e).into();
+                return Err(err);
+            }
+        };
+
+        Encode::<Sqlite>::encode(string, buf)
+    }
+}
+
+impl<'r> Decode<'r, Sqlite> for UserRole {
+    fn decode(value: <Sqlite as SqlxDatabase>::ValueRef<'r>) -> Result<UserRole, BoxDynError> {
+        let json_string = <&str as Decode<Sqlite>>::decode(value)?;
+
+        let value: UserRole = match serde_json::from_str(json_string) {
+            Ok(value) => value,
+            Err(e) => {
+                let err: BoxDynError = format!("{:?}", e).into();
+                return Err(err);
+            }
+        };
+
+        Ok(value)
+    }
+}
+
+impl Type<Sqlite> for UserRole {
+    fn type_info() -> <Sqlite as SqlxDatabase>::TypeInfo {
+        <&str as Type<Sqlite>>::type_info()
+    }
+}
+
+impl<'q> Encode<'q, Sqlite> for UserRole {
+    fn encode_by_ref(&self, buf: &mut Vec<SqliteArgumentValue<'q>>) -> Result<IsNull, BoxDynError> {
+        let string = match serde_json::to_string(&self) {
+            Ok(value) => value,
+            Err(e) => {
+                let err: BoxDynError = format!("{:?}", e).into();
+                return Err(err);
+            }
+        };
+
+        Encode::<Sqlite>::encode(string, buf)
+    }
+}
+
+// Money is not JSON encoded like the rest, but stored as INTEGER cents so SQL can sum it
+impl<'r> Decode<'r, Sqlite> for Money {
+    fn decode(value: <Sqlite as SqlxDatabase>::ValueRef<'r>) -> Result<Money, BoxDynError> {
+        let cents = <i64 as Decode<Sqlite>>::decode(value)?;
+
+        Ok(Money::from_cents(cents))
+    }
+}
+
+impl Type<Sqlite> for Money {
+    fn type_info() -> <Sqlite as SqlxDatabase>::TypeInfo {
+        <i64 as Type<Sqlite>>::type_info()
+    }
+
+    fn compatible(ty: &<Sqlite as SqlxDatabase>::TypeInfo) -> bool {
+        <i64 as Type<Sqlite>>::compatible(ty)
+    }
+}
+
+impl<'q> Encode<'q, Sqlite> for Money {
+    fn encode_by_ref(&self, buf: &mut Vec<SqliteArgumentValue<'q>>) -> Result<IsNull, BoxDynError> {
+        Encode::<Sqlite>::encode(self.cents(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Sqlite> for SnapshotKind {
+    fn decode(value: <Sqlite as SqlxDatabase>::ValueRef<'r>) -> Result<SnapshotKind, BoxDynError> {
+        let json_string = <&str as Decode<Sqlite>>::decode(value)?;
+
+        let value: SnapshotKind = match serde_json::from_str(json_string) {
+            Ok(value) => value,
+            Err(e) => {
+                let err: BoxDynError = format!("{:?}", e).into();
+                return Err(err);
+            }
+        };
+
+        Ok(value)
+    }
+}
+
+impl Type<Sqlite> for SnapshotKind {
+    fn type_info() -> <Sqlite as SqlxDatabase>::TypeInfo {
+        <&str as Type<Sqlite>>::type_info()
+    }
+}
+
+impl<'q> Encode<'q, Sqlite> for SnapshotKind {
+    fn encode_by_ref(&self, buf: &mut Vec<SqliteArgumentValue<'q>>) -> Result<IsNull, BoxDynError> {
+        let string = match serde_json::to_string(&self) {
+            Ok(value) => value,
+            Err(e) => {
+                let err: BoxDynError = format!("{:?}", e).into();
+                return Err(err);
+            }
+        };
+
+        Encode::<Sqlite>::encode(string, buf)
+    }
+}
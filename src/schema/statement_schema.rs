@@ -1,10 +1,11 @@
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{Acquire, FromRow, SqliteConnection};
 use ts_rs::TS;
 
 use crate::common::TS_FILE;
 use crate::database::{Database, ID};
 use crate::schema::record_mapping::RecordMapping;
+use crate::schema::snapshot::{Snapshot, SnapshotKind};
 
 #[derive(Debug, FromRow, Serialize, Deserialize, TS)]
 #[ts(export_to = TS_FILE)]
@@ -52,8 +53,26 @@ impl StatementSchema {
         db: &Database,
         id: ID,
         fields: StatementSchemaFields,
+        write_log_id: ID,
     ) -> anyhow::Result<()> {
         let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        StatementSchema::update_with_conn(&mut tx, id, fields, write_log_id).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // Same as above, in transaction of the caller, e.g. undo restoring several rows
+    pub async fn update_with_conn(
+        conn: &mut SqliteConnection,
+        id: ID,
+        fields: StatementSchemaFields,
+        write_log_id: ID,
+    ) -> anyhow::Result<()> {
+        let kind = SnapshotKind::StatementSchema;
+        let before = Snapshot::fetch_image(conn, kind, id).await?;
 
         sqlx::query!(
             "UPDATE statement_schemas SET
@@ -69,6 +88,8 @@ impl StatementSchema {
         .execute(&mut *conn)
         .await?;
 
+        Snapshot::record(conn, write_log_id, kind, id, before).await?;
+
         Ok(())
     }
 
@@ -0,0 +1,281 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Acquire, FromRow, QueryBuilder, Sqlite, SqliteConnection};
+use ts_rs::TS;
+
+use crate::common::TS_FILE;
+use crate::database::{Database, ID};
+use crate::schema::money::Money;
+use crate::schema::snapshot::{Snapshot, SnapshotKind};
+
+#[derive(Debug, FromRow, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct TagFields {
+    pub name: String,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct Tag {
+    pub id: ID,
+    pub household_id: ID, // immutable after creation
+
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    #[ts(flatten)]
+    pub fields: TagFields,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct Tags {
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Debug, FromRow, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct ExpenseTag {
+    pub expense_id: ID,
+    pub tag_id: ID,
+}
+
+#[derive(Debug, FromRow, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct TagSpendingDataPoint {
+    pub tag_id: ID,
+    pub month: String,
+    pub amount: Money,
+}
+
+impl Tag {
+    pub async fn create(db: &Database, household_id: ID, fields: TagFields) -> anyhow::Result<ID> {
+        let mut conn = db.acquire_db_conn().await?;
+        let id: ID = sqlx::query_scalar!(
+            "INSERT INTO tags (household_id, name) VALUES (?1, ?2) RETURNING id",
+            household_id,
+            fields.name,
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .expect("INSERT failed, likely FOREIGN KEY constraint")
+        .try_into()
+        .unwrap();
+
+        Ok(id)
+    }
+
+    pub async fn update(
+        db: &Database,
+        id: ID,
+        fields: TagFields,
+        write_log_id: ID,
+    ) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        Tag::update_with_conn(&mut tx, id, fields, write_log_id).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // Same as above, in transaction of the caller, e.g. undo restoring several rows
+    pub async fn update_with_conn(
+        conn: &mut SqliteConnection,
+        id: ID,
+        fields: TagFields,
+        write_log_id: ID,
+    ) -> anyhow::Result<()> {
+        let kind = SnapshotKind::Tag;
+        let before = Snapshot::fetch_image(conn, kind, id).await?;
+
+        sqlx::query!("UPDATE tags SET name = ?1 WHERE id = ?2", fields.name, id)
+            .execute(&mut *conn)
+            .await?;
+
+        Snapshot::record(conn, write_log_id, kind, id, before).await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(db: &Database, id: ID) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query!("DELETE FROM tags WHERE id = ?1", id)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch_all(db: &Database, username: &str) -> anyhow::Result<Tags> {
+        let mut conn = db.acquire_db_conn().await?;
+        let results = sqlx::query_as::<_, Tag>(
+            "SELECT * FROM tags
+            WHERE household_id IN (SELECT household_id FROM household_members WHERE username = ?1)
+            ORDER BY name",
+        )
+        .bind(username)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(Tags { tags: results })
+    }
+
+    // Tags are only attached to expenses of their own household
+    pub async fn is_in_household_of_expense(
+        db: &Database,
+        id: ID,
+        expense_id: ID,
+    ) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query_scalar!(
+            "SELECT EXISTS (
+              SELECT 1 FROM expenses
+              JOIN accounts ON (expenses.account_id = accounts.id)
+              JOIN tags ON (tags.household_id = accounts.household_id)
+              WHERE expenses.id = ?1 AND tags.id = ?2
+            )",
+            expense_id,
+            id,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if result == 0 {
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    pub async fn add_to_expenses(db: &Database, id: ID, expense_ids: &[ID]) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        for expense_id in expense_ids {
+            sqlx::query!(
+                "INSERT OR IGNORE INTO expense_tags (expense_id, tag_id) VALUES (?1, ?2)",
+                expense_id,
+                id,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_from_expenses(
+        db: &Database,
+        id: ID,
+        expense_ids: &[ID],
+    ) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        for expense_id in expense_ids {
+            sqlx::query!(
+                "DELETE FROM expense_tags WHERE expense_id = ?1 AND tag_id = ?2",
+                expense_id,
+                id,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn any_has_expenses(db: &Database, id: ID) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query_scalar!(
+            "SELECT EXISTS (SELECT 1 FROM expense_tags WHERE tag_id = ?1)",
+            id,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if result == 0 {
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+}
+
+// Expenses have to match all given tags
+pub fn push_tag_conditions(builder: &mut QueryBuilder<'_, Sqlite>, tag_ids: &[ID]) {
+    for tag_id in tag_ids {
+        builder.push(" AND expenses.id IN (SELECT expense_id FROM expense_tags WHERE tag_id = ");
+        builder.push_bind(*tag_id);
+        builder.push(")");
+    }
+}
+
+impl ExpenseTag {
+    pub async fn fetch_by_expense_ids(
+        db: &Database,
+        expense_ids: &[ID],
+    ) -> anyhow::Result<Vec<ExpenseTag>> {
+        if expense_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut conn = db.acquire_db_conn().await?;
+        let mut builder = QueryBuilder::new("SELECT * FROM expense_tags WHERE expense_id IN (");
+        let mut separated = builder.separated(", ");
+        for expense_id in expense_ids {
+            separated.push_bind(*expense_id);
+        }
+        builder.push(") ORDER BY expense_id, tag_id");
+
+        let results = builder
+            .build_query_as::<ExpenseTag>()
+            .fetch_all(&mut *conn)
+            .await?;
+
+        Ok(results)
+    }
+}
+
+impl TagSpendingDataPoint {
+    // Same as SpendingDataPoint, but grouped by tag. Expenses from ignored categories are skipped,
+    // as there is no budget item to filter them out later on.
+    pub async fn fetch_by_year(
+        db: &Database,
+        username: &str,
+        year: i32,
+    ) -> anyhow::Result<Vec<TagSpendingDataPoint>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let results = sqlx::query_as::<_, TagSpendingDataPoint>(
+            "SELECT
+              expense_tags.tag_id,
+              SUBSTR(spend.transaction_date, 1, 7) AS `month`,
+              SUM(spend.amount) AS `amount`
+            FROM view_expense_spend spend
+            JOIN expense_tags
+              ON (spend.expense_id = expense_tags.expense_id)
+            LEFT JOIN view_budget_items items
+              ON (spend.budget_item_id = items.id)
+            WHERE
+              SUBSTR(spend.transaction_date, 1, 4) = ?1
+              AND COALESCE(items.ignored, 0) = 0
+              AND spend.expense_id IN (
+                SELECT id FROM expenses
+                WHERE account_id IN (SELECT account_id FROM view_account_access WHERE username = ?2)
+              )
+            GROUP BY
+              expense_tags.tag_id,
+              SUBSTR(spend.transaction_date, 1, 7)",
+        )
+        .bind(year.to_string())
+        .bind(username)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(results)
+    }
+}
@@ -0,0 +1,25 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::common::TS_FILE;
+
+// What an API token can be used for; scopes don't imply each other
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, TS, ValueEnum, PartialEq, Eq)]
+#[ts(export_to = TS_FILE)]
+pub enum TokenScope {
+    Read,   // GET requests and queries
+    Import, // statement imports
+    Write,  // everything else that changes data, including imports
+}
+
+// Stored as JSON array in single column
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(transparent)]
+pub struct TokenScopes(pub Vec<TokenScope>);
+
+impl TokenScopes {
+    pub fn contains(&self, scope: TokenScope) -> bool {
+        self.0.contains(&scope)
+    }
+}
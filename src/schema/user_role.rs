@@ -0,0 +1,39 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::common::TS_FILE;
+
+/* Role of the user across the whole app, unlike household roles, which only cover data of single
+household. Read-only users can't write anything, and admin is required for operations which change
+lot of data at once, or affect every household, e.g. bulk deletes and statement schemas. */
+
+// Ordered by what the role allows, so required role is just the minimum
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, TS, ValueEnum, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[ts(export_to = TS_FILE)]
+pub enum UserRole {
+    ReadOnly,
+    Editor,
+    Admin,
+}
+
+impl UserRole {
+    pub fn allows(self, required: UserRole) -> bool {
+        self >= required
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_allows() {
+        assert!(UserRole::Admin.allows(UserRole::Editor));
+        assert!(UserRole::Editor.allows(UserRole::Editor));
+        assert!(!UserRole::Editor.allows(UserRole::Admin));
+        assert!(!UserRole::ReadOnly.allows(UserRole::Editor));
+    }
+}
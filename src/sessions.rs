@@ -0,0 +1,199 @@
+use serde::Serialize;
+use sqlx::FromRow;
+use ts_rs::TS;
+
+use crate::common::{now, TS_FILE};
+use crate::crypto::{random_hex, sha256_hex};
+use crate::database::{Database, ID};
+use crate::guards::client::ClientInfo;
+
+/* Login creates a session, and its random token is kept in private cookie. Session ends when it
+wasn't used for IDLE_EXPIRY_MS, or ABSOLUTE_EXPIRY_MS after login no matter how much it's used,
+or when it's revoked. Tokens are stored hashed, so leaked database doesn't allow to log in. */
+
+pub const SESSION_COOKIE: &str = "session";
+
+const TOKEN_BYTES: usize = 32;
+const IDLE_EXPIRY_MS: i64 = 14 * 24 * 60 * 60 * 1000;
+pub const ABSOLUTE_EXPIRY_MS: i64 = 90 * 24 * 60 * 60 * 1000;
+const LAST_SEEN_RESOLUTION_MS: i64 = 60 * 1000; // saves db write on every request
+
+#[derive(Debug, FromRow, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct Session {
+    pub id: ID,
+    #[serde(skip)]
+    #[ts(skip)]
+    pub username: String,
+    #[ts(type = "number")]
+    pub created_ts: i64,
+    #[ts(type = "number")]
+    pub last_seen_ts: i64,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct Sessions {
+    pub sessions: Vec<Session>,
+    pub current_id: Option<ID>,
+}
+
+fn is_expired(created_ts: i64, last_seen_ts: i64, now: i64) -> bool {
+    now - last_seen_ts > IDLE_EXPIRY_MS || now - created_ts > ABSOLUTE_EXPIRY_MS
+}
+
+impl Session {
+    // Returns id and token for the cookie, token can't be recovered later
+    pub async fn create(
+        db: &Database,
+        username: &str,
+        client: &ClientInfo,
+    ) -> anyhow::Result<(ID, String)> {
+        let token = random_hex(TOKEN_BYTES);
+        let token_hash = sha256_hex(token.as_bytes());
+        let ts = now();
+
+        let mut conn = db.acquire_db_conn().await?;
+        let id: ID = sqlx::query_scalar!(
+            "INSERT INTO sessions (
+              token_hash,
+              username,
+              created_ts,
+              last_seen_ts,
+              user_agent,
+              ip
+            ) VALUES (?1, ?2, ?3, ?3, ?4, ?5) RETURNING id",
+            token_hash,
+            username,
+            ts,
+            client.user_agent,
+            client.ip,
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .expect("INSERT failed, likely FOREIGN KEY constraint")
+        .try_into()
+        .unwrap();
+
+        Ok((id, token))
+    }
+
+    // Valid session of the token, marking it as used; expired session is deleted
+    pub async fn fetch_by_token(db: &Database, token: &str) -> anyhow::Result<Option<Session>> {
+        let token_hash = sha256_hex(token.as_bytes());
+        let ts = now();
+
+        let mut conn = db.acquire_db_conn().await?;
+        let mut session = match sqlx::query_as::<_, Session>(
+            "SELECT id, username, created_ts, last_seen_ts, user_agent, ip
+            FROM sessions WHERE token_hash = ?1",
+        )
+        .bind(token_hash)
+        .fetch_optional(&mut *conn)
+        .await?
+        {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        if is_expired(session.created_ts, session.last_seen_ts, ts) {
+            sqlx::query!("DELETE FROM sessions WHERE id = ?1", session.id)
+                .execute(&mut *conn)
+                .await?;
+
+            return Ok(None);
+        }
+
+        if ts - session.last_seen_ts > LAST_SEEN_RESOLUTION_MS {
+            sqlx::query!(
+                "UPDATE sessions SET last_seen_ts = ?2 WHERE id = ?1",
+                session.id,
+                ts,
+            )
+            .execute(&mut *conn)
+            .await?;
+            session.last_seen_ts = ts;
+        }
+
+        Ok(Some(session))
+    }
+
+    pub async fn fetch_by_username(db: &Database, username: &str) -> anyhow::Result<Vec<Session>> {
+        let ts = now();
+
+        let mut conn = db.acquire_db_conn().await?;
+        let results = sqlx::query_as::<_, Session>(
+            "SELECT id, username, created_ts, last_seen_ts, user_agent, ip
+            FROM sessions
+            WHERE username = ?1 AND last_seen_ts >= ?2 AND created_ts >= ?3
+            ORDER BY last_seen_ts DESC",
+        )
+        .bind(username)
+        .bind(ts - IDLE_EXPIRY_MS)
+        .bind(ts - ABSOLUTE_EXPIRY_MS)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(results)
+    }
+
+    // Users can only revoke their own sessions; returns whether there was one to revoke
+    pub async fn delete(db: &Database, username: &str, id: ID) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query!(
+            "DELETE FROM sessions WHERE id = ?1 AND username = ?2",
+            id,
+            username,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_by_username(db: &Database, username: &str) -> anyhow::Result<u64> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query!("DELETE FROM sessions WHERE username = ?1", username)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn delete_expired(db: &Database) -> anyhow::Result<()> {
+        let ts = now();
+        let idle_cutoff = ts - IDLE_EXPIRY_MS;
+        let absolute_cutoff = ts - ABSOLUTE_EXPIRY_MS;
+
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query!(
+            "DELETE FROM sessions WHERE last_seen_ts < ?1 OR created_ts < ?2",
+            idle_cutoff,
+            absolute_cutoff,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_expired() {
+        let ts = 1_000_000_000_000;
+        assert!(!is_expired(ts, ts, ts));
+        assert!(!is_expired(ts, ts, ts + IDLE_EXPIRY_MS));
+        assert!(is_expired(ts, ts, ts + IDLE_EXPIRY_MS + 1));
+
+        // used all the time, but too old
+        let last_seen = ts + ABSOLUTE_EXPIRY_MS;
+        assert!(!is_expired(ts, last_seen, ts + ABSOLUTE_EXPIRY_MS));
+        assert!(is_expired(ts, last_seen, ts + ABSOLUTE_EXPIRY_MS + 1));
+    }
+}
@@ -0,0 +1,377 @@
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sqlx::{Acquire, FromRow};
+
+use crate::crypto::{random_bytes, random_hex, sha256_hex};
+use crate::database::Database;
+
+/* Optional second factor, RFC 6238 time-based codes with the defaults every authenticator app
+supports: HMAC-SHA1, 6 digits, 30 second steps. Codes of neighbouring steps are accepted too, as
+clocks drift, but each step can be used only once. Recovery codes are single use, and stored
+hashed just like session tokens. */
+
+const ISSUER: &str = "Budget";
+const SECRET_BYTES: usize = 20;
+const STEP_SECS: i64 = 30;
+const DIGITS: usize = 6;
+const RECOVERY_CODES: usize = 10;
+const RECOVERY_CODE_BYTES: usize = 10; // 80 bits, too many to brute force from the hashes
+const RECOVERY_CODE_GROUP: usize = 5;
+
+// Too many wrong codes lock the second step for a while, even for correct codes
+const MAX_FAILURES: i32 = 5;
+const LOCKOUT_MS: i64 = 15 * 60 * 1000;
+
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+type HmacSha1 = Hmac<Sha1>;
+
+// RFC 4648 without padding, as used in otpauth URIs
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut result = String::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            result.push(BASE32_ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+
+    if bits > 0 {
+        result.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+
+    result
+}
+
+fn base32_decode(string: &str) -> Option<Vec<u8>> {
+    let mut result = Vec::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for c in string.trim_end_matches('=').chars() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a as char == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            result.push(((buffer >> bits) & 0xff) as u8);
+        }
+    }
+
+    Some(result)
+}
+
+// RFC 4226 HOTP, truncated to DIGITS
+fn hotp(secret: &[u8], counter: u64) -> String {
+    let mut mac = HmacSha1::new_from_slice(secret).expect("HMAC takes key of any size");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0xf) as usize;
+    let value = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+
+    format!(
+        "{:0width$}",
+        value % 10u32.pow(DIGITS as u32),
+        width = DIGITS
+    )
+}
+
+// Step the code matched, None when it's wrong or its step was already used
+fn verify_code(secret: &str, code: &str, now_secs: i64, last_step: Option<i64>) -> Option<i64> {
+    if code.len() != DIGITS || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let secret = base32_decode(secret)?;
+    let current_step = now_secs / STEP_SECS;
+
+    (current_step - 1..=current_step + 1)
+        .filter(|step| last_step.is_none_or(|last| *step > last))
+        .find(|step| hotp(&secret, *step as u64) == code)
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+pub fn generate_secret() -> String {
+    base32_encode(&random_bytes(SECRET_BYTES))
+}
+
+// Printed in dash separated groups for readability, dashes are optional when typing it in
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODES)
+        .map(|_| {
+            let code = random_hex(RECOVERY_CODE_BYTES);
+            let groups: Vec<&str> = (0..code.len())
+                .step_by(RECOVERY_CODE_GROUP)
+                .map(|i| &code[i..i + RECOVERY_CODE_GROUP])
+                .collect();
+            groups.join("-")
+        })
+        .collect()
+}
+
+pub fn otpauth_uri(username: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        ISSUER, username, secret, ISSUER, DIGITS, STEP_SECS
+    )
+}
+
+// Used while enrolling, to check the app was set up correctly before enabling TOTP
+pub fn is_valid_code(secret: &str, code: &str, now_secs: i64) -> bool {
+    verify_code(secret, code, now_secs, None).is_some()
+}
+
+#[derive(Debug, FromRow)]
+pub struct TotpState {
+    pub username: String,
+    pub secret: String,
+    pub last_step: Option<i64>,
+}
+
+impl TotpState {
+    pub fn matching_step(&self, code: &str, now: i64) -> Option<i64> {
+        verify_code(&self.secret, code, now / 1000, self.last_step)
+    }
+
+    // None when user doesn't have TOTP enabled
+    pub async fn fetch_by_username(
+        db: &Database,
+        username: &str,
+    ) -> anyhow::Result<Option<TotpState>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query_as::<_, TotpState>(
+            "SELECT
+              username,
+              totp_secret AS secret,
+              totp_last_step AS last_step
+            FROM credentials
+            WHERE username = ?1 AND totp_secret IS NOT NULL",
+        )
+        .bind(username)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(result)
+    }
+
+    // Replaces previous secret and recovery codes, if there were any
+    pub async fn enable(
+        db: &Database,
+        username: &str,
+        secret: &str,
+        recovery_codes: &[String],
+    ) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        sqlx::query!(
+            "UPDATE credentials SET
+              totp_secret = ?2,
+              totp_last_step = NULL,
+              totp_failures = 0,
+              totp_failed_ts = NULL
+            WHERE username = ?1",
+            username,
+            secret,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!("DELETE FROM recovery_codes WHERE username = ?1", username)
+            .execute(&mut *tx)
+            .await?;
+
+        for code in recovery_codes {
+            let code_hash = sha256_hex(normalize_recovery_code(code).as_bytes());
+            sqlx::query!(
+                "INSERT INTO recovery_codes (username, code_hash) VALUES (?1, ?2)",
+                username,
+                code_hash,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn disable(db: &Database, username: &str) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        sqlx::query!(
+            "UPDATE credentials SET
+              totp_secret = NULL,
+              totp_last_step = NULL,
+              totp_failures = 0,
+              totp_failed_ts = NULL
+            WHERE username = ?1",
+            username,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!("DELETE FROM recovery_codes WHERE username = ?1", username)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // Marks unused recovery code as used; returns whether there was one
+    pub async fn use_recovery_code(
+        &self,
+        db: &Database,
+        code: &str,
+        now: i64,
+    ) -> anyhow::Result<bool> {
+        let code_hash = sha256_hex(normalize_recovery_code(code).as_bytes());
+
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query!(
+            "UPDATE recovery_codes SET used_ts = ?3
+            WHERE username = ?1 AND code_hash = ?2 AND used_ts IS NULL",
+            self.username,
+            code_hash,
+            now,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /* Step is None for recovery codes, which don't use one. Returns false when the step was used
+    by a concurrent login since the state was fetched, so the same code can't log in twice. */
+    pub async fn record_success(&self, db: &Database, step: Option<i64>) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query!(
+            "UPDATE credentials SET
+              totp_last_step = COALESCE(?2, totp_last_step),
+              totp_failures = 0,
+              totp_failed_ts = NULL
+            WHERE username = ?1
+              AND (?2 IS NULL OR totp_last_step IS NULL OR totp_last_step < ?2)",
+            self.username,
+            step,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /* Every attempt counts as a failure until record_success clears it, and is claimed before
+    the code is checked, in one statement, so that codes sent in parallel can't all get past the
+    lock. Returns false when the second step is locked; locked attempts don't extend the lock. */
+    pub async fn claim_attempt(&self, db: &Database, now: i64) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query!(
+            "UPDATE credentials SET
+              totp_failures = CASE
+                WHEN totp_failed_ts IS NOT NULL AND ?2 - totp_failed_ts < ?3
+                  THEN totp_failures + 1
+                ELSE 1
+              END,
+              totp_failed_ts = ?2
+            WHERE username = ?1
+              AND NOT (
+                totp_failures >= ?4
+                AND totp_failed_ts IS NOT NULL
+                AND ?2 - totp_failed_ts < ?3
+              )",
+            self.username,
+            now,
+            LOCKOUT_MS,
+            MAX_FAILURES,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // secret of RFC 6238 test vectors for SHA1
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_base32() {
+        assert_eq!(base32_encode(b""), "");
+        assert_eq!(base32_encode(b"f"), "MY");
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32_decode("MZXW6YTBOI"), Some(b"foobar".to_vec()));
+        assert_eq!(base32_decode("mzxw6ytboi======"), Some(b"foobar".to_vec()));
+        assert_eq!(base32_decode("MZXW1"), None);
+
+        let secret = base32_encode(RFC_SECRET);
+        assert_eq!(secret, "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        assert_eq!(base32_decode(&secret), Some(RFC_SECRET.to_vec()));
+    }
+
+    #[test]
+    fn test_hotp() {
+        // RFC 4226 appendix D
+        assert_eq!(hotp(RFC_SECRET, 0), "755224");
+        assert_eq!(hotp(RFC_SECRET, 1), "287082");
+        assert_eq!(hotp(RFC_SECRET, 9), "520489");
+    }
+
+    #[test]
+    fn test_verify_code() {
+        let secret = base32_encode(RFC_SECRET);
+
+        // RFC 6238 appendix B, last 6 digits of 94287082 at time 59
+        assert_eq!(verify_code(&secret, "287082", 59, None), Some(1));
+        // previous and next step are accepted too
+        assert_eq!(verify_code(&secret, "287082", 89, None), Some(1));
+        assert_eq!(verify_code(&secret, "287082", 0, None), Some(1));
+        assert_eq!(verify_code(&secret, "287082", 120, None), None);
+
+        // used steps are rejected
+        assert_eq!(verify_code(&secret, "287082", 59, Some(1)), None);
+        assert_eq!(verify_code(&secret, "287082", 59, Some(0)), Some(1));
+
+        assert_eq!(verify_code(&secret, "287083", 59, None), None);
+        assert_eq!(verify_code(&secret, "28708", 59, None), None);
+        assert_eq!(verify_code(&secret, "28708a", 59, None), None);
+    }
+
+    #[test]
+    fn test_recovery_codes() {
+        assert!(crate::crypto::init_crypto().is_ok());
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODES);
+        assert_eq!(codes[0].len(), 23);
+        assert_eq!(codes[0].matches('-').count(), 3);
+        assert_eq!(normalize_recovery_code(&codes[0]).len(), 20);
+        assert_eq!(normalize_recovery_code(" AB12c-3D4e5\n"), "ab12c3d4e5");
+    }
+}
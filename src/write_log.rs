@@ -0,0 +1,434 @@
+use rocket::form::FromForm;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{FromRow, QueryBuilder, Sqlite};
+use ts_rs::TS;
+
+use crate::common::TS_FILE;
+use crate::database::{Database, ID};
+
+/* Reading back the write log. Every logged request is linked to the entities it refers to, so that
+history of e.g. expense 1234 doesn't have to search through uris and content. Entities are named
+after their api collections, ids are taken from the uri and from known id fields anywhere in the
+content. Id of a created entity isn't known when its request is logged, so creation shows up in
+history of its parent, e.g. the account of a new expense. */
+
+const DEFAULT_LIMIT: u32 = 100;
+const MAX_LIMIT: u32 = 1000;
+
+pub const ENTITIES: [&str; 11] = [
+    "accounts",
+    "attachments",
+    "budget_categories",
+    "budget_items",
+    "expenses",
+    "funds",
+    "households",
+    "planned_expenses",
+    "reconciliations",
+    "schemas",
+    "tags",
+];
+
+// Fields of content and query string, and entity of their ids
+const ID_FIELDS: [(&str, &str); 13] = [
+    ("account_id", "accounts"),
+    ("account_ids", "accounts"),
+    ("budget_item_id", "budget_items"),
+    ("category_id", "budget_categories"),
+    ("expense_id", "expenses"),
+    ("expense_ids", "expenses"),
+    ("fund_id", "funds"),
+    ("household_id", "households"),
+    ("planned_expense_id", "planned_expenses"),
+    ("reimbursement_id", "expenses"),
+    ("statement_schema_id", "schemas"),
+    ("tag_id", "tags"),
+    ("tag_ids", "tags"),
+];
+
+#[derive(Debug, FromRow, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct WriteLogRecord {
+    pub id: ID,
+    pub uri: String,
+    pub method: String,
+    pub username: String,
+    pub api_token_id: Option<ID>,
+    pub content: Option<String>, // JSON, as set by the route
+    pub status: Option<String>,  // None if the request never finished
+    #[ts(type = "number")]
+    pub start_ts: i64,
+    #[ts(type = "number | null")]
+    pub end_ts: Option<i64>,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export_to = TS_FILE)]
+pub struct WriteLogRecords {
+    pub entries: Vec<WriteLogRecord>,
+}
+
+/* All filters are optional and combined with AND. Entries come newest first, next page is
+requested with before_id set to id of the last entry. */
+#[derive(Debug, Default, FromForm)]
+pub struct WriteLogFilter {
+    pub username: Option<String>,
+    pub method: Option<String>,
+    pub status: Option<String>, // exact code, or class like "4xx"
+    pub uri: Option<String>,    // whole uri including query, '*' matches anything
+    pub from_ts: Option<i64>,   // inclusive, millis since epoch
+    pub to_ts: Option<i64>,     // exclusive, millis since epoch
+    pub before_id: Option<ID>,
+    pub limit: Option<u32>,
+}
+
+fn entity_of_field(field: &str) -> Option<&'static str> {
+    ID_FIELDS
+        .iter()
+        .find(|(name, _)| *name == field)
+        .map(|(_, entity)| *entity)
+}
+
+fn push_id(entities: &mut Vec<(&'static str, ID)>, entity: &'static str, value: &Value) {
+    if let Some(id) = value.as_i64().and_then(|id| ID::try_from(id).ok()) {
+        entities.push((entity, id));
+    }
+}
+
+fn collect_content_ids(value: &Value, entities: &mut Vec<(&'static str, ID)>) {
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                match (entity_of_field(key), value) {
+                    (Some(entity), Value::Array(items)) => {
+                        for item in items {
+                            push_id(entities, entity, item);
+                        }
+                    }
+                    (Some(entity), value) => push_id(entities, entity, value),
+                    (None, value) => collect_content_ids(value, entities),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_content_ids(item, entities);
+            }
+        }
+        _ => (),
+    }
+}
+
+pub fn extract_entities(method: &str, uri: &str, content: Option<&str>) -> Vec<(&'static str, ID)> {
+    let mut entities = vec![];
+    let (path, query) = uri.split_once('?').unwrap_or((uri, ""));
+
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    for (i, pair) in segments.windows(2).enumerate() {
+        let entity = match ENTITIES.iter().find(|entity| **entity == pair[0]) {
+            Some(value) => *value,
+            None => continue,
+        };
+
+        // POST with trailing number creates something under it, like categories of a year
+        if method == "POST" && i + 2 == segments.len() {
+            continue;
+        }
+
+        if let Ok(id) = pair[1].parse::<ID>() {
+            entities.push((entity, id));
+        }
+    }
+
+    for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+        if let (Some(entity), Ok(id)) = (entity_of_field(key), value.parse::<ID>()) {
+            entities.push((entity, id));
+        }
+    }
+
+    if let Some(value) = content.and_then(|content| serde_json::from_str::<Value>(content).ok()) {
+        collect_content_ids(&value, &mut entities);
+    }
+
+    entities.sort();
+    entities.dedup();
+    entities
+}
+
+fn uri_pattern_to_like(pattern: &str) -> String {
+    pattern
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+        .replace('*', "%")
+}
+
+fn looks_like_status(status: &str) -> bool {
+    let bytes = status.as_bytes();
+
+    bytes.len() == 3
+        && (b'1'..=b'5').contains(&bytes[0])
+        && bytes[1..].iter().all(|b| b.is_ascii_digit() || *b == b'x')
+}
+
+impl WriteLogFilter {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(status) = &self.status {
+            if !looks_like_status(status) {
+                return Err(format!(
+                    "Incorrect status '{}', expected code like '404' or class like '4xx'",
+                    status
+                ));
+            }
+        }
+
+        if let (Some(from_ts), Some(to_ts)) = (self.from_ts, self.to_ts) {
+            if from_ts > to_ts {
+                return Err(format!("from_ts {} is after to_ts {}", from_ts, to_ts));
+            }
+        }
+
+        if self.limit.unwrap_or(DEFAULT_LIMIT) > MAX_LIMIT {
+            return Err(format!("Limit can't exceed {}", MAX_LIMIT));
+        }
+
+        Ok(())
+    }
+
+    fn push_conditions(&self, builder: &mut QueryBuilder<'_, Sqlite>, entity: Option<(&str, ID)>) {
+        builder.push(" WHERE 1 = 1");
+
+        if let Some((entity, entity_id)) = entity {
+            builder.push(
+                " AND write_log.id IN (SELECT write_log_id FROM write_log_entities WHERE entity = ",
+            );
+            builder.push_bind(entity.to_string());
+            builder.push(" AND entity_id = ");
+            builder.push_bind(entity_id);
+            builder.push(")");
+        }
+
+        if let Some(username) = &self.username {
+            builder.push(" AND write_log.username = ");
+            builder.push_bind(username.clone());
+        }
+
+        if let Some(method) = &self.method {
+            builder.push(" AND write_log.method = ");
+            builder.push_bind(method.to_uppercase());
+        }
+
+        if let Some(status) = &self.status {
+            builder.push(" AND write_log.status LIKE ");
+            builder.push_bind(status.replace('x', "_"));
+        }
+
+        // uri is stored as JSON string
+        if let Some(uri) = &self.uri {
+            builder.push(" AND json_extract(write_log.uri, '$') LIKE ");
+            builder.push_bind(uri_pattern_to_like(uri));
+            builder.push(" ESCAPE '\\'");
+        }
+
+        if let Some(from_ts) = self.from_ts {
+            builder.push(" AND write_log.start_ts >= ");
+            builder.push_bind(from_ts);
+        }
+
+        if let Some(to_ts) = self.to_ts {
+            builder.push(" AND write_log.start_ts < ");
+            builder.push_bind(to_ts);
+        }
+
+        if let Some(before_id) = self.before_id {
+            builder.push(" AND write_log.id < ");
+            builder.push_bind(before_id);
+        }
+    }
+
+    // Entity limits the entries to history of that entity
+    pub async fn fetch(
+        &self,
+        db: &Database,
+        entity: Option<(&str, ID)>,
+    ) -> anyhow::Result<WriteLogRecords> {
+        let mut builder = QueryBuilder::new(
+            "SELECT
+              id,
+              json_extract(uri, '$') AS uri,
+              method,
+              username,
+              api_token_id,
+              content,
+              status,
+              start_ts,
+              end_ts
+            FROM write_log",
+        );
+        self.push_conditions(&mut builder, entity);
+        builder.push(" ORDER BY write_log.id DESC LIMIT ");
+        builder.push_bind(self.limit.unwrap_or(DEFAULT_LIMIT));
+
+        let mut conn = db.acquire_db_conn().await?;
+        let results = builder
+            .build_query_as::<WriteLogRecord>()
+            .fetch_all(&mut *conn)
+            .await?;
+
+        Ok(WriteLogRecords { entries: results })
+    }
+}
+
+pub async fn record_entities(
+    db: &Database,
+    write_log_id: ID,
+    entities: &[(&str, ID)],
+) -> anyhow::Result<()> {
+    let mut conn = db.acquire_db_conn().await?;
+    for (entity, entity_id) in entities {
+        sqlx::query!(
+            "INSERT OR IGNORE INTO write_log_entities (write_log_id, entity, entity_id)
+            VALUES (?1, ?2, ?3)",
+            write_log_id,
+            entity,
+            entity_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+// Links entries logged before entities were recorded, returns number of entries processed
+pub async fn backfill_entities(db: &Database) -> anyhow::Result<usize> {
+    let entries = {
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query_as::<_, (ID, String, String, Option<String>)>(
+            "SELECT id, json_extract(uri, '$'), method, content FROM write_log
+            WHERE id NOT IN (SELECT write_log_id FROM write_log_entities)",
+        )
+        .fetch_all(&mut *conn)
+        .await?
+    };
+
+    for (id, uri, method, content) in entries.iter() {
+        let entities = extract_entities(method, uri, content.as_deref());
+        record_entities(db, *id, &entities).await?;
+    }
+
+    Ok(entries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_entities_from_uri() {
+        assert_eq!(
+            extract_entities("PUT", "/api/expenses/1234/category", None),
+            vec![("expenses", 1234)]
+        );
+        assert_eq!(
+            extract_entities("DELETE", "/api/households/1/members/teen", None),
+            vec![("households", 1)]
+        );
+        assert_eq!(
+            extract_entities("POST", "/api/tags/7/tag", None),
+            vec![("tags", 7)]
+        );
+        assert_eq!(
+            extract_entities("POST", "/api/accounts?household_id=2", None),
+            vec![("households", 2)]
+        );
+
+        // year, not a category
+        assert_eq!(
+            extract_entities("POST", "/api/budget_categories/2025", None),
+            vec![]
+        );
+        assert_eq!(
+            extract_entities("PUT", "/api/budget_categories/2025", None),
+            vec![("budget_categories", 2025)]
+        );
+    }
+
+    #[test]
+    fn test_extract_entities_from_content() {
+        let content = r#"{"expense_ids":[3,1,3]}"#;
+        assert_eq!(
+            extract_entities("POST", "/api/tags/7/tag", Some(content)),
+            vec![("expenses", 1), ("expenses", 3), ("tags", 7)]
+        );
+
+        let content = r#"{"reimbursements":[{"expense_id":5,"amount":100}]}"#;
+        assert_eq!(
+            extract_entities("PUT", "/api/expenses/9/reimbursements", Some(content)),
+            vec![("expenses", 5), ("expenses", 9)]
+        );
+
+        let content = r#"{"category_id":4,"fund_id":null,"name":"Rent"}"#;
+        assert_eq!(
+            extract_entities("POST", "/api/budget_items", Some(content)),
+            vec![("budget_categories", 4)]
+        );
+
+        assert_eq!(
+            extract_entities(
+                "POST",
+                "/api/expenses/import",
+                Some(r#""File of length 53""#)
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_uri_pattern_to_like() {
+        assert_eq!(uri_pattern_to_like("/api/expenses/*"), "/api/expenses/%");
+        assert_eq!(
+            uri_pattern_to_like("/api/budget_items/*"),
+            "/api/budget\\_items/%"
+        );
+        assert_eq!(uri_pattern_to_like("100%"), "100\\%");
+    }
+
+    #[test]
+    fn test_validate_filter() {
+        let filter = WriteLogFilter {
+            ..Default::default()
+        };
+        assert!(filter.validate().is_ok());
+
+        for status in ["200", "4xx", "5x0"] {
+            let filter = WriteLogFilter {
+                status: Some(String::from(status)),
+                ..Default::default()
+            };
+            assert!(filter.validate().is_ok(), "{}", status);
+        }
+
+        for status in ["20", "abc", "600", "2000"] {
+            let filter = WriteLogFilter {
+                status: Some(String::from(status)),
+                ..Default::default()
+            };
+            assert!(filter.validate().is_err(), "{}", status);
+        }
+
+        let filter = WriteLogFilter {
+            from_ts: Some(2),
+            to_ts: Some(1),
+            ..Default::default()
+        };
+        assert!(filter.validate().is_err());
+
+        let filter = WriteLogFilter {
+            limit: Some(MAX_LIMIT + 1),
+            ..Default::default()
+        };
+        assert!(filter.validate().is_err());
+    }
+}